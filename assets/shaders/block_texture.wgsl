@@ -0,0 +1,24 @@
+#ifndef BLOCK_TEXTURE_WGSL
+#define BLOCK_TEXTURE_WGSL
+
+// The block textures in group 1, stored as set by `TextureBackend`.
+
+@group(1) @binding(0)
+#ifdef TEXTURE_ARRAY
+var block_texture: texture_2d_array<f32>;
+#else
+var block_texture: texture_2d<f32>;
+#endif
+@group(1) @binding(1)
+var block_sampler: sampler;
+
+// samples the atlas or the texture array layer, depending on the backend.
+fn sample_block_texture(tex_coords: vec2<f32>, layer: u32) -> vec4<f32> {
+#ifdef TEXTURE_ARRAY
+    return textureSample(block_texture, block_sampler, tex_coords, layer);
+#else
+    return textureSample(block_texture, block_sampler, tex_coords);
+#endif
+}
+
+#endif
@@ -0,0 +1,74 @@
+#ifndef COMMON_WGSL
+#define COMMON_WGSL
+
+// Uniform layouts shared between shaders, they match the structs in
+// `renderer.rs`, `renderer/sky.rs`, `renderer/fog.rs`,
+// `renderer/shadows.rs` and `renderer/water.rs`.
+
+struct CameraUniform {
+    view_projection: mat4x4<f32>,
+    position: vec3<f32>,
+}
+
+// the sun or moon, whichever is above the horizon.
+struct LightUniform {
+    direction: vec3<f32>,
+    color: vec3<f32>,
+    ambient: f32,
+}
+
+struct ShadowUniform {
+    // from world space into each cascade's shadow map.
+    cascades: array<mat4x4<f32>, 4>,
+    // the size of a shadow map texel of each cascade in blocks.
+    texel_sizes: vec4<f32>,
+    // 0 when shadows are turned off.
+    cascade_count: u32,
+    // shadows fade out towards this distance from the camera.
+    distance: f32,
+}
+
+struct SkyUniform {
+    inverse_view_projection: mat4x4<f32>,
+    star_rotation: mat4x4<f32>,
+    zenith_color: vec3<f32>,
+    star_visibility: f32,
+    horizon_color: vec3<f32>,
+    sun_glow: f32,
+    sun_direction: vec3<f32>,
+    moon_direction: vec3<f32>,
+}
+
+struct FogUniform {
+    color: vec3<f32>,
+    // 1 blends into the sky in the view direction instead of `color`.
+    sky_blend: f32,
+    start: f32,
+    end: f32,
+    // 0 for linear fog.
+    density: f32,
+    // 0 when fog is turned off.
+    amount: f32,
+}
+
+struct WaterUniform {
+    inverse_view_projection: mat4x4<f32>,
+    // the light deep water scatters back.
+    color: vec3<f32>,
+    // seconds since the game started, moves the waves.
+    time: f32,
+    // how quickly red, green and blue fade per block of water.
+    absorption: vec3<f32>,
+    wave_strength: f32,
+    underwater_distortion: f32,
+}
+
+// the sky in a direction without the stars, the sun and the moon.
+fn sky_color(sky: SkyUniform, direction: vec3<f32>) -> vec3<f32> {
+    let height = max(direction.y, 0.0);
+    let color = mix(sky.horizon_color, sky.zenith_color, sqrt(height));
+    let glow = pow(max(dot(direction, sky.sun_direction), 0.0), 8.0);
+    return color + glow * sky.sun_glow * vec3<f32>(1.0, 0.45, 0.15);
+}
+
+#endif
@@ -0,0 +1,26 @@
+#ifndef FULLSCREEN_WGSL
+#define FULLSCREEN_WGSL
+
+// The vertex shader of passes that run over the whole screen, drawn with
+// three vertices and no vertex buffer.
+
+struct FullscreenOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> FullscreenOutput {
+    // a single clockwise triangle covering the whole screen.
+    var positions = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(-1.0, 3.0),
+        vec2<f32>(3.0, -1.0),
+    );
+    var out: FullscreenOutput;
+    out.clip_position = vec4<f32>(positions[index], 0.0, 1.0);
+    out.tex_coords = positions[index] * vec2<f32>(0.5, -0.5) + 0.5;
+    return out;
+}
+
+#endif
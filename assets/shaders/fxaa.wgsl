@@ -0,0 +1,145 @@
+// Fast approximate anti-aliasing after FXAA 3.11 by Timothy Lottes: finds
+// edges by their contrast in luma, searches how far they run and blends
+// each pixel with its neighbour across the edge by how close it is to the
+// edge's end.
+
+#include "fullscreen.wgsl"
+
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+
+// contrast below this fraction of the brightest neighbour isn't an edge.
+const EDGE_THRESHOLD: f32 = 0.125;
+// and neither is contrast below this in dark parts of the image.
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+// how much single pixel details like thin lines are smoothed.
+const SUBPIXEL_QUALITY: f32 = 0.75;
+// texels each step of the edge search moves, longer further out.
+const SEARCH_STEPS = array<f32, 10>(1.0, 1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 4.0);
+
+// samples without implicit derivatives, which aren't allowed after the
+// early return.
+fn sample_color(tex_coords: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(input_texture, input_sampler, tex_coords, 0.0).rgb;
+}
+
+// the square root of the linear luma is close to the perceived brightness.
+fn luma(color: vec3<f32>) -> f32 {
+    return sqrt(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn sample_luma(tex_coords: vec2<f32>) -> f32 {
+    return luma(sample_color(tex_coords));
+}
+
+@fragment
+fn fs_fxaa(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(input_texture));
+    let uv = in.tex_coords;
+    let color = sample_color(uv);
+    let luma_center = luma(color);
+    // texture coordinates grow downwards.
+    let luma_down = sample_luma(uv + vec2<f32>(0.0, texel.y));
+    let luma_up = sample_luma(uv - vec2<f32>(0.0, texel.y));
+    let luma_left = sample_luma(uv - vec2<f32>(texel.x, 0.0));
+    let luma_right = sample_luma(uv + vec2<f32>(texel.x, 0.0));
+    let luma_min = min(luma_center, min(min(luma_down, luma_up), min(luma_left, luma_right)));
+    let luma_max = max(luma_center, max(max(luma_down, luma_up), max(luma_left, luma_right)));
+    let luma_range = luma_max - luma_min;
+    if luma_range < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD) {
+        return vec4<f32>(color, 1.0);
+    }
+
+    let luma_down_left = sample_luma(uv + vec2<f32>(-texel.x, texel.y));
+    let luma_up_right = sample_luma(uv + vec2<f32>(texel.x, -texel.y));
+    let luma_up_left = sample_luma(uv - texel);
+    let luma_down_right = sample_luma(uv + texel);
+    let luma_down_up = luma_down + luma_up;
+    let luma_left_right = luma_left + luma_right;
+    let luma_left_corners = luma_down_left + luma_up_left;
+    let luma_down_corners = luma_down_left + luma_down_right;
+    let luma_right_corners = luma_down_right + luma_up_right;
+    let luma_up_corners = luma_up_right + luma_up_left;
+
+    // a horizontal edge changes the most going up or down.
+    let edge_horizontal = abs(luma_left_corners - 2.0 * luma_left)
+        + 2.0 * abs(luma_down_up - 2.0 * luma_center)
+        + abs(luma_right_corners - 2.0 * luma_right);
+    let edge_vertical = abs(luma_up_corners - 2.0 * luma_up)
+        + 2.0 * abs(luma_left_right - 2.0 * luma_center)
+        + abs(luma_down_corners - 2.0 * luma_down);
+    let is_horizontal = edge_horizontal >= edge_vertical;
+
+    // the neighbours on both sides of the edge, the pixel is blended
+    // towards the one with the larger difference.
+    let luma_1 = select(luma_left, luma_up, is_horizontal);
+    let luma_2 = select(luma_right, luma_down, is_horizontal);
+    let gradient_1 = luma_1 - luma_center;
+    let gradient_2 = luma_2 - luma_center;
+    let is_1_steepest = abs(gradient_1) >= abs(gradient_2);
+    let gradient_scaled = 0.25 * max(abs(gradient_1), abs(gradient_2));
+    var step_length = select(texel.x, texel.y, is_horizontal);
+    var luma_local_average = 0.5 * (luma_2 + luma_center);
+    if is_1_steepest {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_1 + luma_center);
+    }
+
+    // searches both ways along the middle of the edge until the luma
+    // differs from it.
+    var edge_uv = uv;
+    let along = select(vec2<f32>(0.0, texel.y), vec2<f32>(texel.x, 0.0), is_horizontal);
+    if is_horizontal {
+        edge_uv.y += step_length * 0.5;
+    } else {
+        edge_uv.x += step_length * 0.5;
+    }
+    // constant arrays can only be indexed by constants.
+    var search_steps = SEARCH_STEPS;
+    var uv_1 = edge_uv - along;
+    var uv_2 = edge_uv + along;
+    var luma_end_1 = sample_luma(uv_1) - luma_local_average;
+    var luma_end_2 = sample_luma(uv_2) - luma_local_average;
+    var reached_1 = abs(luma_end_1) >= gradient_scaled;
+    var reached_2 = abs(luma_end_2) >= gradient_scaled;
+    for (var i = 1; i < 10 && !(reached_1 && reached_2); i++) {
+        if !reached_1 {
+            uv_1 -= along * search_steps[i];
+            luma_end_1 = sample_luma(uv_1) - luma_local_average;
+            reached_1 = abs(luma_end_1) >= gradient_scaled;
+        }
+        if !reached_2 {
+            uv_2 += along * search_steps[i];
+            luma_end_2 = sample_luma(uv_2) - luma_local_average;
+            reached_2 = abs(luma_end_2) >= gradient_scaled;
+        }
+    }
+
+    let distance_1 = select(uv.y - uv_1.y, uv.x - uv_1.x, is_horizontal);
+    let distance_2 = select(uv_2.y - uv.y, uv_2.x - uv.x, is_horizontal);
+    let is_direction_1 = distance_1 < distance_2;
+    let distance_final = min(distance_1, distance_2);
+    let edge_length = distance_1 + distance_2;
+    // only blends if the closer end goes the other way than the center.
+    let luma_end = select(luma_end_2, luma_end_1, is_direction_1);
+    let is_luma_center_smaller = luma_center < luma_local_average;
+    let correct_variation = (luma_end < 0.0) != is_luma_center_smaller;
+    let pixel_offset = select(0.0, 0.5 - distance_final / edge_length, correct_variation);
+
+    // pixels standing out from all their neighbours are blended as well.
+    let luma_average = (2.0 * (luma_down_up + luma_left_right) + luma_left_corners + luma_right_corners) / 12.0;
+    let subpixel_offset_1 = saturate(abs(luma_average - luma_center) / luma_range);
+    let subpixel_offset_2 = (-2.0 * subpixel_offset_1 + 3.0) * subpixel_offset_1 * subpixel_offset_1;
+    let subpixel_offset = subpixel_offset_2 * subpixel_offset_2 * SUBPIXEL_QUALITY;
+
+    let offset = max(pixel_offset, subpixel_offset) * step_length;
+    var final_uv = uv;
+    if is_horizontal {
+        final_uv.y += offset;
+    } else {
+        final_uv.x += offset;
+    }
+    return vec4<f32>(sample_color(final_uv), 1.0);
+}
@@ -0,0 +1,94 @@
+#ifndef LIGHTING_WGSL
+#define LIGHTING_WGSL
+
+// The camera in group 0, the light and fog in groups 2 and 3 and the
+// lighting shared by the blocks and water.
+
+#include "common.wgsl"
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(2) @binding(0)
+var<uniform> light: LightUniform;
+@group(2) @binding(1)
+var<uniform> shadows: ShadowUniform;
+@group(2) @binding(2)
+var shadow_map: texture_depth_2d_array;
+@group(2) @binding(3)
+var shadow_sampler: sampler_comparison;
+@group(2) @binding(4)
+var ambient_occlusion_texture: texture_2d<f32>;
+@group(3) @binding(0)
+var<uniform> sky: SkyUniform;
+@group(3) @binding(1)
+var<uniform> fog: FogUniform;
+
+// averages 3x3 filtered comparisons, softening the shadow edges.
+fn sample_shadow_map(position: vec2<f32>, depth: f32, cascade: u32) -> f32 {
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+    var lit = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, position + offset, cascade, depth);
+        }
+    }
+    return lit / 9.0;
+}
+
+// 1 where the light reaches `world_position`, 0 in full shadow.
+fn shadow_factor(world_position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let distance = length(world_position - camera.position);
+    for (var cascade = 0u; cascade < shadows.cascade_count; cascade++) {
+        // moves off the surface, so it doesn't shadow itself.
+        let position = world_position + normal * shadows.texel_sizes[cascade] * 1.5;
+        let clip = shadows.cascades[cascade] * vec4<f32>(position, 1.0);
+        let shadow_position = clip.xy * vec2<f32>(0.5, -0.5) + 0.5;
+        // the closest cascade containing the position is the sharpest.
+        if all(shadow_position >= vec2<f32>(0.0)) && all(shadow_position <= vec2<f32>(1.0)) && clip.z <= 1.0 {
+            let lit = sample_shadow_map(shadow_position, clip.z, cascade);
+            let fade = smoothstep(shadows.distance * 0.9, shadows.distance, distance);
+            return mix(lit, 1.0, fade);
+        }
+    }
+    return 1.0;
+}
+
+// 1 where nothing around the pixel at `position` occludes the ambient
+// light, the texture is a single white texel with SSAO turned off.
+fn ambient_occlusion(position: vec2<f32>) -> f32 {
+    let coords = min(vec2<u32>(position), textureDimensions(ambient_occlusion_texture) - 1u);
+    return textureLoad(ambient_occlusion_texture, coords, 0).r;
+}
+
+// torches and other light sources are a little warmer than daylight.
+const BLOCK_LIGHT_COLOR: vec3<f32> = vec3<f32>(1.0, 0.85, 0.6);
+// how much brighter than white light emitting blocks glow.
+const EMISSIVE_STRENGTH: f32 = 1.5;
+// keeps caves without any light from being pitch black.
+const MIN_AMBIENT: f32 = 0.02;
+
+// the brightness of a light level, falling off faster in the dark.
+fn light_brightness(level: u32) -> f32 {
+    let x = f32(level) / 15.0;
+    return x / (4.0 - 3.0 * x);
+}
+
+// how much of the color at `distance` is hidden, reaching 1 at the end.
+fn fog_factor(distance: f32) -> f32 {
+    let t = saturate((distance - fog.start) / (fog.end - fog.start));
+    if fog.density == 0.0 {
+        return t * fog.amount;
+    }
+    // scaled to reach 1 at the end as well.
+    let exponential = (1.0 - exp(-fog.density * t)) / (1.0 - exp(-fog.density));
+    return exponential * fog.amount;
+}
+
+fn apply_fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let offset = world_position - camera.position;
+    let fog_color = mix(fog.color, sky_color(sky, normalize(offset)), fog.sky_blend);
+    return mix(color, fog_color, fog_factor(length(offset)));
+}
+
+#endif
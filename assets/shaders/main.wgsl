@@ -1,18 +1,13 @@
-struct CameraUniform {
-    view_projection: mat4x4<f32>,
-}
-@group(0) @binding(0)
-var<uniform> camera: CameraUniform;
-
-struct LightUniform {
-    direction: vec3<f32>,
-    color: vec3<f32>,
-}
+#include "common.wgsl"
+#include "block_texture.wgsl"
+#include "lighting.wgsl"
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) normal: vec3<f32>,
+    @location(3) layer: u32,
+    @location(4) light: u32,
 };
 
 struct VertexOutput {
@@ -20,6 +15,8 @@ struct VertexOutput {
     @location(0) tex_coords: vec2<f32>,
     @location(1) world_position: vec3<f32>,
     @location(2) world_normal: vec3<f32>,
+    @location(3) @interpolate(flat) layer: u32,
+    @location(4) @interpolate(flat) light: u32,
 }
 
 @vertex
@@ -29,24 +26,28 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
     out.clip_position = camera.view_projection * vec4<f32>(vertex.position, 1.0);
     out.world_position = vertex.position;
     out.world_normal = vertex.normal;
+    out.layer = vertex.layer;
+    out.light = vertex.light;
     return out;
 }
 
-@group(1) @binding(0)
-var atlas: texture_2d<f32>;
-@group(1) @binding(1)
-var atlas_sampler: sampler;
-@group(2) @binding(0)
-var<uniform> light: LightUniform;
-
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    let object_color = textureSample(atlas, atlas_sampler, vertex.tex_coords);
+    let object_color = sample_block_texture(vertex.tex_coords, vertex.layer);
     if object_color.a == 0.0 {
         discard;
     }
-    let ambient_strength = 0.1;
-    let diffuse_strength = max(dot(vertex.world_normal, light.direction), 0.0);
-    let result = (ambient_strength + diffuse_strength) * light.color * object_color.xyz;
-    return vec4<f32>(result, object_color.a);
+    // skylight holds how much of the sky a block sees, the daylight its
+    // color at the current time.
+    let sky_light = light_brightness((vertex.light >> 4u) & 15u);
+    let block_light = BLOCK_LIGHT_COLOR * light_brightness(vertex.light & 15u);
+    var diffuse_strength = max(dot(vertex.world_normal, light.direction), 0.0) * sky_light;
+    if diffuse_strength > 0.0 {
+        diffuse_strength *= shadow_factor(vertex.world_position, vertex.world_normal);
+    }
+    let occlusion = ambient_occlusion(vertex.clip_position.xy);
+    let ambient = (max(light.ambient * sky_light, MIN_AMBIENT) + block_light) * occlusion;
+    let emission = f32((vertex.light >> 8u) & 15u) / 15.0 * EMISSIVE_STRENGTH;
+    let result = (ambient + diffuse_strength * light.color + emission) * object_color.xyz;
+    return vec4<f32>(apply_fog(result, vertex.world_position), object_color.a);
 }
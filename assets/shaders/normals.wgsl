@@ -0,0 +1,42 @@
+// Renders the normals and depth of the terrain for the ambient occlusion,
+// before the scene itself is drawn. The depth goes into the alpha channel,
+// as depth textures can't be read on every backend.
+
+#include "common.wgsl"
+#include "block_texture.wgsl"
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) layer: u32,
+};
+
+struct NormalsOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) @interpolate(flat) layer: u32,
+}
+
+@vertex
+fn vs_normals(vertex: VertexInput) -> NormalsOutput {
+    var out: NormalsOutput;
+    out.clip_position = camera.view_projection * vec4<f32>(vertex.position, 1.0);
+    out.tex_coords = vertex.tex_coords;
+    out.world_normal = vertex.normal;
+    out.layer = vertex.layer;
+    return out;
+}
+
+@fragment
+fn fs_normals(in: NormalsOutput) -> @location(0) vec4<f32> {
+    // the holes of cutout textures like flowers don't occlude anything.
+    if sample_block_texture(in.tex_coords, in.layer).a == 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.world_normal, in.clip_position.z);
+}
@@ -0,0 +1,80 @@
+// Turns the HDR scene into the final image: bright parts are extracted and
+// blurred into a glow at half resolution, then added back to the scene,
+// which is exposed and tonemapped onto the screen.
+
+#include "fullscreen.wgsl"
+
+struct PostProcessingUniform {
+    exposure: f32,
+    // 0 for none, 1 for Reinhard and 2 for ACES.
+    tonemapping: u32,
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> post_processing: PostProcessingUniform;
+@group(0) @binding(1)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var second_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var input_sampler: sampler;
+
+// gaussian weights of the center texel and the four on each side.
+const BLUR_WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+@fragment
+fn fs_bright(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    // the linear sampler averages the four scene texels of each bloom texel.
+    let color = textureSample(input_texture, input_sampler, in.tex_coords).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    // keeps the hue, only the part above the threshold glows.
+    let glow = max(brightness - post_processing.bloom_threshold, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * glow, 1.0);
+}
+
+fn blur(tex_coords: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let step = direction / vec2<f32>(textureDimensions(input_texture));
+    // constant arrays can only be indexed by constants.
+    var weights = BLUR_WEIGHTS;
+    var color = textureSample(input_texture, input_sampler, tex_coords).rgb * weights[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        color += textureSample(input_texture, input_sampler, tex_coords + offset).rgb * weights[i];
+        color += textureSample(input_texture, input_sampler, tex_coords - offset).rgb * weights[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_blur_horizontal(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return blur(in.tex_coords, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return blur(in.tex_coords, vec2<f32>(0.0, 1.0));
+}
+
+// fitted curve by Krzysztof Narkowicz.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    return saturate((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14));
+}
+
+@fragment
+fn fs_composite(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(input_texture, input_sampler, in.tex_coords).rgb;
+    let bloom = textureSample(second_texture, input_sampler, in.tex_coords).rgb;
+    var color = (scene + bloom * post_processing.bloom_intensity) * post_processing.exposure;
+    switch post_processing.tonemapping {
+        case 1u: {
+            color = color / (1.0 + color);
+        }
+        case 2u: {
+            color = aces(color);
+        }
+        default: {}
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,37 @@
+// Renders the depth of the terrain as the sun or moon sees it into one
+// cascade of the shadow map.
+
+#include "block_texture.wgsl"
+
+@group(0) @binding(0)
+var<uniform> cascade_view_projection: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) layer: u32,
+};
+
+struct ShadowOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) layer: u32,
+}
+
+@vertex
+fn vs_shadow(vertex: VertexInput) -> ShadowOutput {
+    var out: ShadowOutput;
+    out.clip_position = cascade_view_projection * vec4<f32>(vertex.position, 1.0);
+    out.tex_coords = vertex.tex_coords;
+    out.layer = vertex.layer;
+    return out;
+}
+
+@fragment
+fn fs_shadow(in: ShadowOutput) {
+    // light passes through the holes of cutout textures like flowers.
+    if sample_block_texture(in.tex_coords, in.layer).a == 0.0 {
+        discard;
+    }
+}
@@ -0,0 +1,129 @@
+// The sky behind the terrain: a gradient with stars drawn over the whole
+// screen, then the sun and moon as quads infinitely far away.
+
+#include "common.wgsl"
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(1) @binding(0)
+var<uniform> sky: SkyUniform;
+@group(2) @binding(0)
+var sun_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var moon_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var celestial_sampler: sampler;
+
+// half the width of the sun and moon quads, one unit away from the camera.
+const SUN_SIZE: f32 = 0.08;
+const MOON_SIZE: f32 = 0.06;
+// the sun is brighter than white, so it glows with bloom.
+const SUN_INTENSITY: f32 = 4.0;
+// stars are scattered over a grid of cells this many cells from the center.
+const STAR_GRID: f32 = 180.0;
+// the fraction of cells without a star.
+const STAR_THRESHOLD: f32 = 0.9975;
+
+struct SkyOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) screen_position: vec2<f32>,
+}
+
+@vertex
+fn vs_sky(@builtin(vertex_index) index: u32) -> SkyOutput {
+    // a single clockwise triangle covering the whole screen.
+    var positions = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(-1.0, 3.0),
+        vec2<f32>(3.0, -1.0),
+    );
+    var out: SkyOutput;
+    out.clip_position = vec4<f32>(positions[index], 0.0, 1.0);
+    out.screen_position = positions[index];
+    return out;
+}
+
+fn view_direction(screen_position: vec2<f32>) -> vec3<f32> {
+    let near = sky.inverse_view_projection * vec4<f32>(screen_position, 0.0, 1.0);
+    let far = sky.inverse_view_projection * vec4<f32>(screen_position, 1.0, 1.0);
+    return normalize(far.xyz / far.w - near.xyz / near.w);
+}
+
+fn hash(cell: vec3<f32>) -> f32 {
+    return fract(sin(dot(cell, vec3<f32>(127.1, 311.7, 74.7))) * 43758.5453);
+}
+
+fn stars(direction: vec3<f32>) -> f32 {
+    let position = (sky.star_rotation * vec4<f32>(direction, 0.0)).xyz * STAR_GRID;
+    let cell = floor(position);
+    if hash(cell) < STAR_THRESHOLD {
+        return 0.0;
+    }
+    // somewhere inside the cell, so the stars don't line up.
+    let offset = vec3<f32>(hash(cell + 1.0), hash(cell + 2.0), hash(cell + 3.0));
+    let center = cell + 0.2 + offset * 0.6;
+    let brightness = 0.3 + 0.7 * hash(cell + 4.0);
+    return (1.0 - smoothstep(0.0, 0.4, distance(position, center))) * brightness;
+}
+
+@fragment
+fn fs_sky(in: SkyOutput) -> @location(0) vec4<f32> {
+    let direction = view_direction(in.screen_position);
+    var color = sky_color(sky, direction);
+    let star_fade = smoothstep(0.0, 0.15, direction.y) * sky.star_visibility;
+    color += vec3<f32>(stars(direction) * star_fade);
+    return vec4<f32>(color, 1.0);
+}
+
+struct CelestialOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    // 0 for the sun, 1 for the moon.
+    @location(1) @interpolate(flat) body: u32,
+}
+
+@vertex
+fn vs_celestial(
+    @builtin(vertex_index) index: u32,
+    @builtin(instance_index) body: u32,
+) -> CelestialOutput {
+    // two clockwise triangles.
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+    );
+    let corner = corners[index];
+    var direction = sky.sun_direction;
+    var size = SUN_SIZE;
+    if body == 1u {
+        direction = sky.moon_direction;
+        size = MOON_SIZE;
+    }
+    // the sun never passes the poles, so this doesn't degenerate and the
+    // texture keeps its orientation across the sky.
+    let right = normalize(cross(vec3<f32>(0.0, 0.0, 1.0), direction));
+    let up = cross(direction, right);
+    let position = direction + (right * corner.x + up * corner.y) * size;
+
+    var out: CelestialOutput;
+    // w = 0 places it infinitely far away, ignoring the camera position.
+    out.clip_position = camera.view_projection * vec4<f32>(position, 0.0);
+    out.clip_position.z = 0.0;
+    out.tex_coords = vec2<f32>(corner.x, -corner.y) * 0.5 + 0.5;
+    out.body = body;
+    return out;
+}
+
+@fragment
+fn fs_celestial(in: CelestialOutput) -> @location(0) vec4<f32> {
+    let sun = textureSample(sun_texture, celestial_sampler, in.tex_coords);
+    let moon = textureSample(moon_texture, celestial_sampler, in.tex_coords);
+    let color = select(sun, moon, in.body == 1u);
+    let intensity = select(SUN_INTENSITY, 1.0, in.body == 1u);
+    // blended additively, a black background disappears.
+    return vec4<f32>(color.rgb * color.a * intensity, 1.0);
+}
@@ -0,0 +1,119 @@
+// Screen space ambient occlusion: samples a hemisphere around the normal of
+// each point on screen and counts how much of it lies behind the terrain,
+// then blurs away the noise of the rotated samples.
+
+#include "fullscreen.wgsl"
+
+struct SsaoUniform {
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+    radius: f32,
+    intensity: f32,
+    sample_count: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> ssao: SsaoUniform;
+// the normals in rgb, the depth in a.
+@group(0) @binding(1)
+var normal_depth_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var occlusion_texture: texture_2d<f32>;
+
+const GOLDEN_ANGLE: f32 = 2.3999632;
+// view depth an occluder has to be in front of a sample, so flat surfaces
+// don't occlude themselves.
+const BIAS: f32 = 0.03;
+// a 4x4 ordered dither, the blur averages each block of rotations away.
+const DITHER = array<f32, 16>(0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+
+fn load_depth(coords: vec2<i32>) -> f32 {
+    return textureLoad(normal_depth_texture, coords, 0).a;
+}
+
+// the world position seen at `coords` of the depth, with its view depth
+// in w.
+fn world_position(coords: vec2<i32>) -> vec4<f32> {
+    let uv = (vec2<f32>(coords) + 0.5) / vec2<f32>(textureDimensions(normal_depth_texture));
+    let depth = load_depth(coords);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world = ssao.inverse_view_projection * ndc;
+    // the w of the unprojected point is the reciprocal of the view depth.
+    return vec4<f32>(world.xyz / world.w, 1.0 / world.w);
+}
+
+@fragment
+fn fs_ssao(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let depth_size = vec2<f32>(textureDimensions(normal_depth_texture));
+    let coords = vec2<i32>(in.tex_coords * depth_size);
+    // nothing occludes the sky.
+    if load_depth(coords) == 1.0 {
+        return vec4<f32>(1.0);
+    }
+    let center = world_position(coords);
+    let normal = normalize(textureLoad(normal_depth_texture, coords, 0).xyz);
+
+    // rotates the samples around the normal differently in each pixel of a
+    // 4x4 block, the z keeps it from lining up with the normals of blocks.
+    var dither = DITHER;
+    let pixel = vec2<u32>(in.clip_position.xy) % 4u;
+    let angle = dither[pixel.y * 4u + pixel.x] / 16.0 * 6.2831853;
+    let random = vec3<f32>(cos(angle), sin(angle), 0.5);
+    let tangent = normalize(random - normal * dot(random, normal));
+    let bitangent = cross(normal, tangent);
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < ssao.sample_count; i++) {
+        // cosine weighted directions spiralling out from the normal.
+        let t = (f32(i) + 0.5) / f32(ssao.sample_count);
+        let phi = f32(i) * GOLDEN_ANGLE;
+        let r = sqrt(t);
+        let direction = (tangent * cos(phi) + bitangent * sin(phi)) * r + normal * sqrt(1.0 - t);
+        // more samples close to the point, where occluders matter most.
+        let scale = fract(f32(i) * 0.618034 + 0.5);
+        let position = center.xyz + direction * mix(0.1, 1.0, scale * scale) * ssao.radius;
+        let clip = ssao.view_projection * vec4<f32>(position, 1.0);
+        let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+        if clip.w <= 0.0 || any(uv < vec2<f32>(0.0)) || any(uv >= vec2<f32>(1.0)) {
+            continue;
+        }
+        let scene = world_position(vec2<i32>(uv * depth_size));
+        // terrain far in front of the point, like a wall behind an edge,
+        // doesn't occlude it.
+        let range = smoothstep(0.0, 1.0, ssao.radius / abs(center.w - scene.w));
+        occlusion += select(0.0, range, scene.w < clip.w - BIAS);
+    }
+    let visibility = 1.0 - occlusion / f32(ssao.sample_count) * ssao.intensity;
+    return vec4<f32>(saturate(visibility), 0.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_blur(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let depth_size = vec2<f32>(textureDimensions(normal_depth_texture));
+    let coords = vec2<i32>(in.tex_coords * depth_size);
+    if load_depth(coords) == 1.0 {
+        return vec4<f32>(1.0);
+    }
+    let center_depth = world_position(coords).w;
+    // the occlusion may be at a lower resolution than the depth.
+    let occlusion_size = vec2<i32>(textureDimensions(occlusion_texture));
+    let occlusion_center = vec2<i32>(in.tex_coords * vec2<f32>(occlusion_size));
+    var sum = 0.0;
+    var weight_sum = 0.0;
+    for (var x = -2; x < 2; x++) {
+        for (var y = -2; y < 2; y++) {
+            let occlusion_coords = clamp(occlusion_center + vec2<i32>(x, y), vec2<i32>(0), occlusion_size - 1);
+            let uv = (vec2<f32>(occlusion_coords) + 0.5) / vec2<f32>(occlusion_size);
+            let depth = world_position(vec2<i32>(uv * depth_size)).w;
+            // leaves out texels across depth edges, so dark corners don't
+            // bleed onto the terrain in front of them.
+            let weight = max(1.0 - abs(depth - center_depth) / (0.1 * center_depth), 0.0);
+            sum += textureLoad(occlusion_texture, occlusion_coords, 0).r * weight;
+            weight_sum += weight;
+        }
+    }
+    if weight_sum == 0.0 {
+        return vec4<f32>(1.0);
+    }
+    return vec4<f32>(sum / weight_sum, 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,34 @@
+// The view from inside water: the finished image wobbles and is tinted by
+// the water around the camera, the underwater fog hides what is further
+// away.
+
+#include "common.wgsl"
+#include "fullscreen.wgsl"
+
+@group(0) @binding(0)
+var<uniform> water: WaterUniform;
+@group(0) @binding(1)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var input_sampler: sampler;
+
+// blocks of water the light reaching the camera has passed at least.
+const TINT_DISTANCE: f32 = 2.0;
+// the furthest the image is moved, as a fraction of the screen.
+const DISTORTION: f32 = 0.004;
+
+@fragment
+fn fs_underwater(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let uv = in.tex_coords;
+    let t = water.time;
+    // slow ripples across the screen, fading out at its edges so nothing
+    // from outside of it is pulled in.
+    let ripple = vec2<f32>(sin(uv.y * 23.0 + t * 1.9), cos(uv.x * 17.0 + t * 1.4));
+    let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+    let offset = ripple * DISTORTION * water.underwater_distortion * smoothstep(0.0, 0.05, edge);
+    let color = textureSample(input_texture, input_sampler, uv + offset).rgb;
+    let tint = exp(-water.absorption * TINT_DISTANCE);
+    // darker towards the corners, like looking through a diving mask.
+    let vignette = 1.0 - 0.4 * dot(uv - 0.5, uv - 0.5);
+    return vec4<f32>(color * tint * vignette, 1.0);
+}
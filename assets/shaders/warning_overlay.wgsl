@@ -0,0 +1,27 @@
+// Striped bar along the top of the screen, shown when assets failed to load.
+
+const BAR_HEIGHT: f32 = 12.0;
+const STRIPE_WIDTH: f32 = 16.0;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // a single clockwise triangle covering the whole screen.
+    var positions = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(-1.0, 3.0),
+        vec2<f32>(3.0, -1.0),
+    );
+    return vec4<f32>(positions[index], 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    if position.y > BAR_HEIGHT {
+        discard;
+    }
+    let stripe = floor((position.x + position.y) / STRIPE_WIDTH);
+    if stripe % 2.0 == 0.0 {
+        return vec4<f32>(1.0, 0.8, 0.0, 1.0);
+    }
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
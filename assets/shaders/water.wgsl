@@ -0,0 +1,145 @@
+// Water surfaces: a few sine waves running in different directions bend
+// the normal, which reflects the sky and the sun. The light coming through
+// the surface is absorbed by the water between it and the terrain behind,
+// found in the depth of the SSAO normals pass.
+
+#include "common.wgsl"
+#include "lighting.wgsl"
+
+@group(1) @binding(0)
+var<uniform> water: WaterUniform;
+// the normals in rgb and the depth in a of the terrain without the water.
+@group(1) @binding(1)
+var terrain_texture: texture_2d<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) layer: u32,
+    @location(4) light: u32,
+};
+
+struct WaterOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) @interpolate(flat) light: u32,
+}
+
+// the direction, the wavelength in blocks and the speed in radians per
+// second of each wave.
+const WAVES = array<vec4<f32>, 4>(
+    vec4<f32>(1.0, 0.3, 5.0, 1.3),
+    vec4<f32>(-0.4, 1.0, 3.1, 1.7),
+    vec4<f32>(0.7, -0.8, 1.7, 2.3),
+    vec4<f32>(-1.0, -0.2, 1.1, 2.9),
+);
+// how much light the surface reflects when looked at straight on.
+const BASE_REFLECTANCE: f32 = 0.02;
+// seen from below, light hitting the surface at a flatter angle than
+// about 49 degrees from the normal is reflected back into the water.
+const CRITICAL_COSINE: f32 = 0.66;
+const SHININESS: f32 = 400.0;
+// the sun glinting on the waves is brighter than white, so it glows with
+// bloom.
+const GLINT_STRENGTH: f32 = 8.0;
+// how far the view passes through water with no terrain behind it.
+const MAX_THICKNESS: f32 = 64.0;
+
+@vertex
+fn vs_water(vertex: VertexInput) -> WaterOutput {
+    var out: WaterOutput;
+    out.clip_position = camera.view_projection * vec4<f32>(vertex.position, 1.0);
+    out.world_position = vertex.position;
+    out.world_normal = vertex.normal;
+    out.light = vertex.light;
+    return out;
+}
+
+// the slope of the waves at a horizontal position, at most 1.
+fn wave_slope(position: vec2<f32>) -> vec2<f32> {
+    // constant arrays can only be indexed by constants.
+    var waves = WAVES;
+    var slope = vec2<f32>(0.0);
+    for (var i = 0; i < 4; i++) {
+        let wave = waves[i];
+        let direction = normalize(wave.xy);
+        let frequency = 6.2831853 / wave.z;
+        let phase = dot(direction, position) * frequency + water.time * wave.w;
+        // each wave is sin(phase) / frequency high, so short waves are as
+        // steep as long ones.
+        slope += direction * cos(phase);
+    }
+    return slope / 4.0;
+}
+
+// the blocks of water the view passes through behind the surface at the
+// pixel `position` until it reaches the terrain.
+fn water_thickness(position: vec4<f32>, world_position: vec3<f32>) -> f32 {
+    let size = textureDimensions(terrain_texture);
+    let coords = min(vec2<u32>(position.xy), size - 1u);
+    let depth = textureLoad(terrain_texture, coords, 0).a;
+    if depth == 1.0 {
+        return MAX_THICKNESS;
+    }
+    let uv = position.xy / vec2<f32>(size);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let terrain = water.inverse_view_projection * ndc;
+    return min(distance(terrain.xyz / terrain.w, world_position), MAX_THICKNESS);
+}
+
+@fragment
+fn fs_water(in: WaterOutput) -> @location(0) vec4<f32> {
+    let offset = in.world_position - camera.position;
+    let view = normalize(offset);
+    // far away, waves smaller than a pixel would only flicker.
+    let strength = water.wave_strength / (1.0 + length(offset) / 32.0);
+    let slope = wave_slope(in.world_position.xz) * strength;
+    // the waves move the surface, which is seen from above and below.
+    var normal = in.world_normal;
+    if abs(normal.y) > 0.5 {
+        normal = normalize(vec3<f32>(-slope.x, 1.0, -slope.y)) * normal.y;
+    }
+
+    let sky_light = light_brightness((in.light >> 4u) & 15u);
+    let block_light = BLOCK_LIGHT_COLOR * light_brightness(in.light & 15u);
+    let lit = shadow_factor(in.world_position, in.world_normal) * sky_light;
+    // the light is scattered inside the water, no matter which way the
+    // surface faces.
+    let diffuse = max(light.direction.y, 0.0) * lit;
+    let ambient = max(light.ambient * sky_light, MIN_AMBIENT) + block_light;
+    let scattered = (ambient + diffuse * light.color) * water.color;
+
+    let cosine = max(dot(-view, normal), 0.0);
+    var fresnel: f32;
+    var reflection: vec3<f32>;
+    var transmittance: vec3<f32>;
+    if in.world_normal.y < -0.5 {
+        // the sky is only seen through a window above the camera, the
+        // rest of the surface reflects the water. the underwater fog
+        // absorbs the light on its way to the camera.
+        fresnel = 1.0 - smoothstep(CRITICAL_COSINE - 0.05, CRITICAL_COSINE + 0.05, cosine);
+        reflection = scattered;
+        transmittance = vec3<f32>(1.0);
+    } else {
+        fresnel = BASE_REFLECTANCE + (1.0 - BASE_REFLECTANCE) * pow(1.0 - cosine, 5.0);
+        let reflected = reflect(view, normal);
+        // the terrain isn't reflected, the horizon stands in for anything
+        // below it.
+        let sky_direction = vec3<f32>(reflected.x, max(reflected.y, 0.0), reflected.z);
+        let glint = pow(max(dot(reflected, light.direction), 0.0), SHININESS) * GLINT_STRENGTH * lit;
+        reflection = sky_color(sky, sky_direction) * sky_light + glint * light.color;
+        transmittance = exp(-water.absorption * water_thickness(in.clip_position, in.world_position));
+    }
+
+    // of what isn't reflected, the light from behind the surface is let
+    // through as far as the water doesn't absorb it, and the water
+    // scatters light in place of what it absorbed. blending lets the same
+    // fraction of each channel through, so the average is used for it and
+    // the color of the absorption comes from the scattered light.
+    let through = (1.0 - fresnel) * transmittance;
+    let alpha = 1.0 - (through.r + through.g + through.b) / 3.0;
+    let color = fresnel * reflection + (1.0 - fresnel) * (1.0 - transmittance) * scattered;
+    return vec4<f32>(apply_fog(color / max(alpha, 0.001), in.world_position), alpha);
+}
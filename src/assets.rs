@@ -0,0 +1,278 @@
+mod watcher;
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use color_eyre::eyre::{eyre, Context};
+use color_eyre::Result;
+use once_cell::sync::Lazy;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+pub use self::watcher::AssetWatcher;
+use crate::settings::SETTINGS;
+
+const BUILTIN_ASSETS: &str = "assets";
+
+/// A source of asset files, laid out like the `assets` directory.
+trait AssetSource: Send + Sync {
+    fn name(&self) -> &Path;
+
+    /// Reads a file, returning `None` if this source doesn't contain it.
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>>;
+
+    /// Lists the files below `directory`, relative to the asset root.
+    fn list(&self, directory: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Picks up changes on disk that reads don't see by themselves.
+    fn reopen(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct DirectorySource {
+    root: PathBuf,
+}
+
+impl AssetSource for DirectorySource {
+    fn name(&self) -> &Path {
+        &self.root
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let path = self.root.join(path);
+        match std::fs::read(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => {
+                Err(error).wrap_err_with(|| format!("Failed to read {path:?}"))
+            }
+        }
+    }
+
+    fn list(&self, directory: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut directories = vec![directory.to_path_buf()];
+        while let Some(directory) = directories.pop() {
+            let full_path = self.root.join(&directory);
+            let entries = match std::fs::read_dir(&full_path) {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => {
+                    return Err(error).wrap_err_with(|| {
+                        format!("Failed to read {full_path:?}")
+                    })
+                }
+            };
+            for entry in entries {
+                let entry = entry?;
+                let path = directory.join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                    directories.push(path);
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+struct ZipSource {
+    path: PathBuf,
+    archive: Mutex<ZipArchive<File>>,
+}
+
+impl ZipSource {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            archive: Mutex::new(Self::open_archive(path)?),
+        })
+    }
+
+    fn open_archive(path: &Path) -> Result<ZipArchive<File>> {
+        let file = File::open(path)
+            .wrap_err_with(|| format!("Failed to open {path:?}"))?;
+        ZipArchive::new(file)
+            .wrap_err_with(|| format!("Failed to open {path:?}"))
+    }
+}
+
+impl AssetSource for ZipSource {
+    fn name(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        // zip archives always use forward slashes.
+        let name = path
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = match archive.by_name(&name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(error) => {
+                return Err(error).wrap_err_with(|| {
+                    format!("Failed to read {name} from {:?}", self.path)
+                })
+            }
+        };
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).wrap_err_with(|| {
+            format!("Failed to read {name} from {:?}", self.path)
+        })?;
+        Ok(Some(content))
+    }
+
+    fn list(&self, directory: &Path) -> Result<Vec<PathBuf>> {
+        let archive = self.archive.lock().unwrap();
+        Ok(archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(PathBuf::from)
+            .filter(|path| path.starts_with(directory))
+            .collect())
+    }
+
+    /// The central directory is read when the archive is opened, it is
+    /// stale once the file is rewritten.
+    fn reopen(&self) -> Result<()> {
+        *self.archive.lock().unwrap() = Self::open_archive(&self.path)?;
+        Ok(())
+    }
+}
+
+fn open_resource_pack(pack: &Path) -> Result<Box<dyn AssetSource>> {
+    if !pack.exists() {
+        Err(eyre!("Resource pack {pack:?} does not exist"))
+    } else if pack.is_dir() {
+        Ok(Box::new(DirectorySource {
+            root: pack.to_path_buf(),
+        }))
+    } else if pack.extension().is_some_and(|x| x == "zip") {
+        Ok(Box::new(ZipSource::open(pack)?))
+    } else {
+        Err(eyre!(
+            "Resource pack {pack:?} is neither a directory nor a zip file"
+        ))
+    }
+}
+
+/// Layers the resource packs from the settings over the built-in assets.
+///
+/// All paths are relative to the asset root, e.g.
+/// `textures/blocks/dirt.png`.
+pub struct AssetFileSystem {
+    /// Ordered from the highest to the lowest priority.
+    sources: Vec<Box<dyn AssetSource>>,
+    /// Assets that failed to load and were replaced by a fallback.
+    errors: Mutex<Vec<String>>,
+}
+
+impl AssetFileSystem {
+    /// Resource packs that can't be opened are logged and skipped, so the
+    /// game still starts with the remaining ones.
+    pub fn new(resource_packs: &[PathBuf]) -> Self {
+        let mut sources: Vec<Box<dyn AssetSource>> = Vec::new();
+        for pack in resource_packs {
+            log::info!("Loading resource pack {pack:?}");
+            match open_resource_pack(pack) {
+                Ok(source) => sources.push(source),
+                Err(error) => {
+                    log::error!("Skipping resource pack: {error:#}")
+                }
+            }
+        }
+        sources.push(Box::new(DirectorySource {
+            root: PathBuf::from(BUILTIN_ASSETS),
+        }));
+        Self {
+            sources,
+            errors: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The directories and zip files assets are read from.
+    pub fn roots(&self) -> impl Iterator<Item = &Path> {
+        self.sources.iter().map(|source| source.name())
+    }
+
+    /// Reopens the zip resource packs after they changed on disk. A pack
+    /// that can't be opened keeps its old contents.
+    pub fn reopen_archives(&self) {
+        for source in &self.sources {
+            if let Err(error) = source.reopen() {
+                log::warn!("Failed to reload resource pack: {error:#}");
+            }
+        }
+    }
+
+    /// Reads a file from the first source that contains it, or `None` if no
+    /// source does.
+    pub fn read_optional(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        for source in &self.sources {
+            if let Some(content) = source.read(path)? {
+                log::debug!("Loaded {path:?} from {:?}", source.name());
+                return Ok(Some(content));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lists the files below `directory` in all sources, sorted and
+    /// without duplicates.
+    pub fn list(&self, directory: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = BTreeSet::new();
+        for source in &self.sources {
+            files.extend(source.list(directory)?);
+        }
+        Ok(files.into_iter().collect())
+    }
+
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.read_optional(path)?
+            .ok_or_else(|| eyre!("Asset {path:?} not found"))
+    }
+
+    pub fn read_to_string(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.read(path)?)
+            .wrap_err_with(|| format!("Asset {path:?} is not valid UTF-8"))
+    }
+
+    /// Records an asset that was replaced by a fallback, so loading can go
+    /// on and all errors are reported together.
+    pub fn report_error(&self, error: color_eyre::Report) {
+        self.errors.lock().unwrap().push(format!("{error:#}"));
+    }
+
+    pub fn clear_errors(&self) {
+        self.errors.lock().unwrap().clear();
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.lock().unwrap().len()
+    }
+
+    /// Logs all errors reported so far.
+    pub fn log_errors(&self) {
+        let errors = self.errors.lock().unwrap();
+        if errors.is_empty() {
+            return;
+        }
+        log::warn!(
+            "{} assets failed to load and were replaced:\n{}",
+            errors.len(),
+            errors.join("\n")
+        );
+    }
+}
+
+pub static ASSETS: Lazy<AssetFileSystem> =
+    Lazy::new(|| AssetFileSystem::new(&SETTINGS.assets.resource_packs));
@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use super::ASSETS;
+
+/// Editors often write a file in several steps, changes are only reported
+/// once no further events arrived for this long.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// What needs to be reloaded after assets changed on disk.
+#[derive(Default, Clone, Copy)]
+pub struct AssetChanges {
+    pub shaders: bool,
+    /// Textures and block models.
+    pub block_assets: bool,
+    /// Zip resource packs, reopened before anything is reloaded.
+    archives: bool,
+}
+
+impl AssetChanges {
+    fn any(&self) -> bool {
+        self.shaders || self.block_assets
+    }
+
+    fn add_path(&mut self, path: &Path) {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("wgsl") => self.shaders = true,
+            Some("png" | "json" | "mcmeta") => self.block_assets = true,
+            // a zip resource pack can contain anything.
+            Some("zip") => {
+                self.shaders = true;
+                self.block_assets = true;
+                self.archives = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Watches the built-in assets and all resource packs for changes.
+pub struct AssetWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    pending: AssetChanges,
+    last_event: Instant,
+}
+
+impl AssetWatcher {
+    pub fn new() -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)
+            .wrap_err("Failed to create asset watcher")?;
+        for root in ASSETS.roots() {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .wrap_err_with(|| format!("Failed to watch {root:?}"))?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            pending: AssetChanges::default(),
+            last_event: Instant::now(),
+        })
+    }
+
+    /// Returns the changes since the last call once the files have settled.
+    pub fn changes(&mut self) -> Option<AssetChanges> {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) => {
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_)
+                            | EventKind::Modify(_)
+                            | EventKind::Remove(_)
+                    ) {
+                        event
+                            .paths
+                            .iter()
+                            .for_each(|path| self.pending.add_path(path));
+                        self.last_event = Instant::now();
+                    }
+                }
+                Err(error) => log::warn!("Asset watcher error: {error}"),
+            }
+        }
+        if !self.pending.any() || self.last_event.elapsed() < DEBOUNCE {
+            return None;
+        }
+        let changes = std::mem::take(&mut self.pending);
+        if changes.archives {
+            ASSETS.reopen_archives();
+        }
+        Some(changes)
+    }
+}
@@ -2,54 +2,152 @@ use std::path::Path;
 
 use color_eyre::Result;
 
+use crate::assets::ASSETS;
 use crate::renderer::model::{
+    ConnectedBlock,
+    CrossBlock,
+    CrossOffset,
     CubeBlock,
     InitBlockMeshGenerator,
     InvisibleBlock,
+    JsonBlock,
+    RenderLayer,
+    WaterBlock,
 };
-use crate::renderer::GameRenderData;
+use crate::renderer::{GameRenderData, SkyTextures};
+use crate::settings::SETTINGS;
 use crate::world::{BlockType, GameData};
 
 mod init_texture_provider;
 
+use init_texture_provider::load_texture;
 pub use init_texture_provider::InitTextureProvider;
 
+/// Loads a block model, falling back to a missing texture cube so one broken
+/// model doesn't stop the game from starting.
+fn json_block(
+    model_path: &Path,
+    texture_provider: &mut InitTextureProvider,
+) -> Box<dyn InitBlockMeshGenerator> {
+    match JsonBlock::new(model_path, texture_provider) {
+        Ok(block) => Box::new(block),
+        Err(error) => {
+            ASSETS.report_error(error);
+            Box::new(CubeBlock::missing(texture_provider))
+        }
+    }
+}
+
 pub fn initialize() -> Result<(GameData, GameRenderData)> {
+    ASSETS.clear_errors();
     let mut block_types = Vec::new();
     let mut mesh_generators: Vec<Box<dyn InitBlockMeshGenerator>> = Vec::new();
 
     let mut texture_provider = InitTextureProvider::new();
 
-    block_types.push(BlockType::new("air"));
+    block_types.push(BlockType::new("air").passable());
     mesh_generators.push(Box::new(InvisibleBlock::new()));
 
-    block_types.push(BlockType::new("cobblestone"));
+    block_types.push(BlockType::new("cobblestone").opaque());
     mesh_generators.push(Box::new(CubeBlock::new(
-        Path::new("assets/textures/blocks/cobblestone.png"),
+        Path::new("textures/blocks/cobblestone.png"),
+        RenderLayer::Opaque,
         &mut texture_provider,
     )));
 
-    block_types.push(BlockType::new("dirt"));
+    block_types.push(BlockType::new("dirt").opaque());
     mesh_generators.push(Box::new(CubeBlock::new(
-        Path::new("assets/textures/blocks/dirt.png"),
+        Path::new("textures/blocks/dirt.png"),
+        RenderLayer::Opaque,
+        &mut texture_provider,
+    )));
+
+    block_types.push(BlockType::new("stone").opaque());
+    mesh_generators.push(Box::new(CubeBlock::new(
+        Path::new("textures/blocks/stone.png"),
+        RenderLayer::Opaque,
+        &mut texture_provider,
+    )));
+
+    block_types.push(BlockType::new("glass"));
+    mesh_generators.push(Box::new(ConnectedBlock::new(
+        Path::new("textures/blocks/glass_ctm.png"),
+        RenderLayer::Cutout,
         &mut texture_provider,
     )));
 
-    block_types.push(BlockType::new("stone"));
+    block_types.push(BlockType::new("ice"));
     mesh_generators.push(Box::new(CubeBlock::new(
-        Path::new("assets/textures/blocks/stone.png"),
+        Path::new("textures/blocks/ice.png"),
+        RenderLayer::Translucent,
+        &mut texture_provider,
+    )));
+
+    block_types.push(BlockType::new("bookshelf").opaque());
+    mesh_generators.push(Box::new(ConnectedBlock::new(
+        Path::new("textures/blocks/bookshelf_ctm.png"),
+        RenderLayer::Opaque,
+        &mut texture_provider,
+    )));
+
+    block_types.push(BlockType::new("cobblestone_slab"));
+    mesh_generators.push(json_block(
+        Path::new("models/blocks/cobblestone_slab.json"),
+        &mut texture_provider,
+    ));
+
+    block_types.push(BlockType::new("cobblestone_stairs"));
+    mesh_generators.push(json_block(
+        Path::new("models/blocks/cobblestone_stairs.json"),
+        &mut texture_provider,
+    ));
+
+    block_types.push(BlockType::new("poppy").passable());
+    mesh_generators.push(Box::new(CrossBlock::new(
+        Path::new("textures/blocks/poppy.png"),
+        CrossOffset::Xz,
+        &mut texture_provider,
+    )));
+
+    block_types.push(BlockType::new("short_grass").passable());
+    mesh_generators.push(Box::new(CrossBlock::new(
+        Path::new("textures/blocks/short_grass.png"),
+        CrossOffset::Xyz,
+        &mut texture_provider,
+    )));
+
+    block_types.push(BlockType::new("oak_sapling").passable());
+    mesh_generators.push(Box::new(CrossBlock::new(
+        Path::new("textures/blocks/oak_sapling.png"),
+        CrossOffset::None,
         &mut texture_provider,
     )));
 
+    block_types
+        .push(BlockType::new("torch").passable().with_light_emission(14));
+    mesh_generators.push(json_block(
+        Path::new("models/blocks/standing_torch.json"),
+        &mut texture_provider,
+    ));
+
+    block_types.push(BlockType::new("water").passable());
+    mesh_generators.push(Box::new(WaterBlock::new()));
+
     assert!(block_types.len() == mesh_generators.len());
 
-    let atlas = texture_provider.atlas(16)?;
+    let block_textures =
+        texture_provider.block_textures(SETTINGS.graphics.texture_backend)?;
     let mesh_generators = mesh_generators
         .into_iter()
-        .map(|generator| generator.build(&atlas))
+        .map(|generator| generator.build(&block_textures))
         .collect();
+    let sky_textures = SkyTextures {
+        sun: load_texture(Path::new("textures/environment/sun.png")),
+        moon: load_texture(Path::new("textures/environment/moon.png")),
+    };
+    ASSETS.log_errors();
     Ok((
         GameData::new(block_types),
-        GameRenderData::new(mesh_generators, atlas),
+        GameRenderData::new(mesh_generators, block_textures, sky_textures),
     ))
 }
@@ -1,41 +1,247 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use color_eyre::eyre::Context;
+use color_eyre::eyre::{bail, Context};
 use color_eyre::Result;
+use image::RgbaImage;
 
-use crate::renderer::TextureAtlas;
+use crate::assets::ASSETS;
+use crate::renderer::block_textures::BlockTextures;
+use crate::renderer::{TextureArray, TextureAtlas};
+use crate::settings::TextureBackend;
+
+/// Size of the checkerboard that replaces textures which failed to load.
+const MISSING_TEXTURE_SIZE: u32 = 16;
+
+/// A magenta and black checkerboard, so broken textures stand out.
+fn missing_texture() -> RgbaImage {
+    let half = MISSING_TEXTURE_SIZE / 2;
+    RgbaImage::from_fn(MISSING_TEXTURE_SIZE, MISSING_TEXTURE_SIZE, |x, y| {
+        match (x / half + y / half) % 2 {
+            0 => image::Rgba([255, 0, 255, 255]),
+            _ => image::Rgba([0, 0, 0, 255]),
+        }
+    })
+}
+
+/// Loads a texture that isn't a block texture, replacing it with the
+/// placeholder if it fails.
+pub fn load_texture(path: &Path) -> RgbaImage {
+    ASSETS
+        .read(path)
+        .and_then(|content| {
+            Ok(image::load_from_memory_with_format(
+                &content,
+                image::ImageFormat::Png,
+            )?)
+        })
+        .map(|image| image.to_rgba8())
+        .unwrap_or_else(|error| {
+            ASSETS.report_error(
+                error.wrap_err(format!("Failed to load texture {path:?}")),
+            );
+            missing_texture()
+        })
+}
+
+#[derive(PartialEq)]
+enum TextureSource {
+    File(PathBuf),
+    Missing,
+    /// One tile of a sheet, counted row by row from the top left.
+    SheetTile {
+        path: PathBuf,
+        columns: u32,
+        rows: u32,
+        index: u32,
+    },
+}
+
+/// Loads a sheet of `columns` x `rows` equally sized tiles.
+fn load_sheet(path: &Path, columns: u32, rows: u32) -> Result<RgbaImage> {
+    let sheet = ASSETS
+        .read(path)
+        .and_then(|content| Ok(image::load_from_memory(&content)?))
+        .wrap_err_with(|| format!("Failed to load texture sheet {path:?}"))?
+        .to_rgba8();
+    if !sheet.width().is_multiple_of(columns)
+        || !sheet.height().is_multiple_of(rows)
+    {
+        bail!(
+            "Texture sheet {path:?} of {}x{} pixels can't be split into \
+             {columns}x{rows} tiles!",
+            sheet.width(),
+            sheet.height()
+        );
+    }
+    Ok(sheet)
+}
 
 pub struct InitTextureProvider {
-    texture_names: Vec<PathBuf>,
+    textures: Vec<TextureSource>,
 }
 
 impl InitTextureProvider {
     pub fn new() -> Self {
         Self {
-            texture_names: Vec::new(),
+            textures: Vec::new(),
         }
     }
 
-    pub fn texture(&mut self, texture_path: &Path) -> u32 {
-        if let Some(index) =
-            self.texture_names.iter().position(|x| x == texture_path)
-        {
+    fn insert(&mut self, source: TextureSource) -> u32 {
+        if let Some(index) = self.textures.iter().position(|x| *x == source) {
             index as u32
         } else {
-            let index = self.texture_names.len() as u32;
-            self.texture_names.push(texture_path.to_path_buf());
+            let index = self.textures.len() as u32;
+            self.textures.push(source);
             index
         }
     }
 
-    pub fn atlas(&self, texture_size: u32) -> Result<TextureAtlas> {
-        let mut atlas =
-            TextureAtlas::new(texture_size, self.texture_names.len() as u32);
-        for texture in &self.texture_names {
-            atlas
-                .add_path(texture)
-                .wrap_err("Failed populating texture atlas!")?;
+    pub fn texture(&mut self, texture_path: &Path) -> u32 {
+        self.insert(TextureSource::File(texture_path.to_path_buf()))
+    }
+
+    /// The placeholder used for textures that failed to load, for generators
+    /// that have no texture to load at all.
+    pub fn missing_texture(&mut self) -> u32 {
+        self.insert(TextureSource::Missing)
+    }
+
+    /// Slices a sheet of `columns` x `rows` equally sized tiles into
+    /// separate textures, returned row by row from the top left.
+    pub fn texture_sheet(
+        &mut self,
+        sheet_path: &Path,
+        columns: u32,
+        rows: u32,
+    ) -> Vec<u32> {
+        (0..columns * rows)
+            .map(|index| {
+                self.insert(TextureSource::SheetTile {
+                    path: sheet_path.to_path_buf(),
+                    columns,
+                    rows,
+                    index,
+                })
+            })
+            .collect()
+    }
+
+    /// Loads the frames of a texture, animated textures are a vertical strip
+    /// of square frames.
+    fn load_frames(
+        &self,
+        texture: &TextureSource,
+        sheets: &mut HashMap<PathBuf, Option<RgbaImage>>,
+    ) -> Result<Vec<RgbaImage>> {
+        match texture {
+            TextureSource::Missing => Ok(vec![missing_texture()]),
+            TextureSource::File(path) => {
+                let image = ASSETS
+                    .read(path)
+                    .and_then(|content| {
+                        Ok(image::load_from_memory_with_format(
+                            &content,
+                            image::ImageFormat::Png,
+                        )?)
+                    })
+                    .wrap_err_with(|| {
+                        format!("Failed to load texture {path:?}")
+                    })?
+                    .to_rgba8();
+                let size = image.width();
+                if image.height() <= size || image.height() % size != 0 {
+                    return Ok(vec![image]);
+                }
+                Ok((0..image.height() / size)
+                    .map(|frame| {
+                        image::imageops::crop_imm(
+                            &image,
+                            0,
+                            frame * size,
+                            size,
+                            size,
+                        )
+                        .to_image()
+                    })
+                    .collect())
+            }
+            TextureSource::SheetTile {
+                path,
+                columns,
+                rows,
+                index,
+            } => {
+                // a broken sheet is reported once, not for every tile.
+                let sheet = sheets.entry(path.clone()).or_insert_with(|| {
+                    load_sheet(path, *columns, *rows)
+                        .map_err(|error| ASSETS.report_error(error))
+                        .ok()
+                });
+                let Some(sheet) = sheet else {
+                    return Ok(vec![missing_texture()]);
+                };
+                let tile_width = sheet.width() / columns;
+                let tile_height = sheet.height() / rows;
+                Ok(vec![image::imageops::crop_imm(
+                    sheet,
+                    index % columns * tile_width,
+                    index / columns * tile_height,
+                    tile_width,
+                    tile_height,
+                )
+                .to_image()])
+            }
+        }
+    }
+
+    pub fn block_textures(
+        &self,
+        backend: TextureBackend,
+    ) -> Result<BlockTextures> {
+        let mut sheets = HashMap::new();
+        let textures = self
+            .textures
+            .iter()
+            .map(|texture| {
+                let path = match texture {
+                    TextureSource::File(path) => path.as_path(),
+                    TextureSource::Missing => Path::new("missing"),
+                    TextureSource::SheetTile { path, .. } => path,
+                };
+                let frames = self
+                    .load_frames(texture, &mut sheets)
+                    .unwrap_or_else(|error| {
+                        ASSETS.report_error(error);
+                        vec![missing_texture()]
+                    });
+                (path, frames)
+            })
+            .collect::<Vec<_>>();
+        match backend {
+            TextureBackend::Atlas => {
+                let mut atlas = TextureAtlas::new();
+                for (path, frames) in &textures {
+                    atlas.add_frames(path, frames)?;
+                }
+                Ok(BlockTextures::Atlas(atlas))
+            }
+            TextureBackend::Array => {
+                let layer_size = textures
+                    .iter()
+                    .map(|(_, frames)| {
+                        frames[0].width().max(frames[0].height())
+                    })
+                    .max()
+                    .unwrap_or(1)
+                    .next_power_of_two();
+                let mut array = TextureArray::new(layer_size);
+                for (path, frames) in &textures {
+                    array.add_frames(path, frames)?;
+                }
+                Ok(BlockTextures::Array(array))
+            }
         }
-        Ok(atlas)
     }
 }
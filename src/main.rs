@@ -3,6 +3,7 @@
 extern crate nalgebra as na;
 extern crate nalgebra_glm as glm;
 
+mod assets;
 mod init;
 mod logger;
 mod renderer;
@@ -11,53 +12,107 @@ mod test_world_generator;
 mod util;
 mod world;
 
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
+use color_eyre::eyre::bail;
 use color_eyre::Result;
 use settings::SETTINGS;
-use world::{ChunkEvent, GameData};
+use world::{ChunkEvent, GameData, Level, WorldClock, TICK_DURATION};
 
 use crate::world::World;
 
 fn main_loop(
     chunk_sender: Sender<ChunkEvent>,
-    game_data: GameData,
+    game_data: Arc<GameData>,
     world: Arc<Mutex<World>>,
 ) {
     let generator = test_world_generator::ChunkGenerator::new();
     let generate_chunks = SETTINGS.graphics.render_distance as i32 / 16;
+    let mut changed_chunks = HashSet::new();
     for y in -generate_chunks..generate_chunks {
         for x in -generate_chunks..generate_chunks {
             let position = glm::vec2(x, y);
-            world
-                .lock()
-                .unwrap()
-                .set_chunk(generator.generate_chunk(position, &game_data));
+            let mut world = world.lock().unwrap();
+            world.set_chunk(generator.generate_chunk(position, &game_data));
+            changed_chunks.insert(position);
+            // the chunks are sent for meshing below, after all are lit.
+            changed_chunks.extend(world.light_chunk(position, &game_data));
         }
     }
-    for y in -generate_chunks..generate_chunks {
-        for x in -generate_chunks..generate_chunks {
-            let position = glm::vec2(x, y);
-            chunk_sender.send(ChunkEvent::Update(position)).unwrap();
-        }
+    for position in changed_chunks {
+        chunk_sender.send(ChunkEvent::Update(position)).unwrap();
+    }
+}
+
+/// Advances the clock at `TICKS_PER_SECOND` until `running` is cleared.
+fn run_clock(clock: &WorldClock, running: &AtomicBool) {
+    let mut next_tick = Instant::now();
+    while running.load(Ordering::Relaxed) {
+        clock.tick();
+        next_tick += TICK_DURATION;
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
     }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
     logger::initialize_logger()?;
+    let headless = match std::env::args().nth(1).as_deref() {
+        None => None,
+        Some("validate-shaders") => return renderer::validate_shaders(),
+        Some("render") => {
+            Some(renderer::HeadlessOptions::parse(std::env::args().skip(2))?)
+        }
+        Some(command) => bail!(
+            "Unknown command \"{command}\", the commands are \
+             \"validate-shaders\" and \"render\""
+        ),
+    };
     log::info!("Hello, World!");
 
     let (game_data, game_render_data) = init::initialize()?;
+    let game_data = Arc::new(game_data);
 
     let (chunk_sender, chunk_receiver) = mpsc::channel();
     let world = Arc::new(Mutex::new(World::new()));
+    let clock = Arc::new(WorldClock::new(Level::load()?.ticks));
+    let running = AtomicBool::new(true);
     thread::scope(|s| -> Result<()> {
         let clone_world = world.clone();
-        s.spawn(move || main_loop(chunk_sender, game_data, clone_world));
-        renderer::run(game_render_data, chunk_receiver, world)
+        let clone_game_data = game_data.clone();
+        s.spawn(move || main_loop(chunk_sender, clone_game_data, clone_world));
+        match headless {
+            // the time stays where it is to render exactly what was asked.
+            Some(options) => renderer::run_headless(
+                options,
+                game_render_data,
+                game_data,
+                chunk_receiver,
+                world,
+                clock.clone(),
+            ),
+            None => {
+                s.spawn(|| run_clock(&clock, &running));
+                let result = renderer::run(
+                    game_render_data,
+                    game_data,
+                    chunk_receiver,
+                    world,
+                    clock.clone(),
+                );
+                running.store(false, Ordering::Relaxed);
+                result?;
+                Level {
+                    ticks: clock.ticks(),
+                }
+                .save()
+            }
+        }
     })?;
     Ok(())
 }
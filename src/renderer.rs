@@ -1,29 +1,63 @@
+mod anti_aliasing;
+pub mod block_textures;
 mod camera;
 mod camera_controller;
+mod capture;
+mod daylight;
+mod fog;
 mod game_render_data;
+mod headless;
+mod layouts;
 mod mesh;
 pub mod model;
+mod post_processing;
 mod shader;
+mod shadows;
+mod sky;
+mod ssao;
 mod texture;
+mod texture_animation;
+mod texture_array;
 pub mod texture_atlas;
+mod water;
 mod world;
 
 use std::collections::{HashMap, VecDeque};
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::{self};
 
+use anti_aliasing::{
+    create_multisampled_target,
+    next_mode,
+    supported_sample_counts,
+    Fxaa,
+};
 use camera::Camera;
 use camera_controller::CameraController;
+use capture::{save_screenshot, TextureCopy};
 use color_eyre::eyre::ContextCompat;
 use color_eyre::Result;
+use daylight::Daylight;
+use fog::Fog;
 pub use game_render_data::GameRenderData;
+pub use headless::{run_headless, HeadlessOptions};
+use layouts::{block_texture_layout, CAMERA_LAYOUT, FOG_LAYOUT, LIGHT_LAYOUT};
 use mesh::{Mesh, Vertex};
+use post_processing::{PostProcessing, HDR_FORMAT};
 use shader::load_shader_module;
+pub use shader::validate_shaders;
+use shadows::Shadows;
+use sky::Sky;
+pub use sky::SkyTextures;
+use ssao::Ssao;
 use texture::Texture;
+use texture_animation::TextureAnimator;
+use water::{Water, WaterScene};
 use wgpu::util::DeviceExt;
-use winit::event::WindowEvent;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
 use winit::window::{Window, WindowBuilder};
 
 use self::camera::{
@@ -31,21 +65,37 @@ use self::camera::{
     OrthographicCamera,
     PerspectiveCamera,
 };
+pub use self::texture_array::TextureArray;
 pub use self::texture_atlas::TextureAtlas;
-use self::world::{ChunkMeshEvent, RenderWorld};
-use crate::settings::SETTINGS;
-use crate::world::{ChunkEvent, World};
+use self::world::{ChunkMesh, ChunkMeshEvent, RenderWorld, RenderWorldEvent};
+use crate::assets::{AssetWatcher, ASSETS};
+use crate::settings::{AntiAliasing, SETTINGS};
+use crate::world::{Chunk, ChunkEvent, GameData, World, WorldClock};
+
+/// How far the camera may move before the translucent geometry is sorted
+/// again.
+const TRANSLUCENT_SORT_DISTANCE: f32 = 1.0;
+/// Limits `ScreenshotSettings::tile_scale`.
+const MAX_TILE_SCALE: u32 = 16;
+/// Tiled screenshots are made smaller than asked for if they would have
+/// more pixels than this, 512 MiB of RGBA. The tiles take as much again
+/// until they are copied into the image.
+const MAX_SCREENSHOT_PIXELS: u64 = 1 << 27;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
     view_projection: glm::Mat4,
+    position: glm::Vec3,
+    _pad_0: u32,
 }
 
 impl CameraUniform {
     fn new() -> Self {
         Self {
             view_projection: glm::identity(),
+            position: glm::vec3(0.0, 0.0, 0.0),
+            _pad_0: 0,
         }
     }
 
@@ -60,23 +110,54 @@ pub struct LightUniform {
     direction: nalgebra::UnitVector3<f32>,
     _pad_0: u32,
     color: glm::Vec3,
-    _pad_1: u32,
+    ambient: f32,
 }
 
-fn create_render_pipeline(
-    device: &wgpu::Device,
-    layout: &wgpu::PipelineLayout,
+/// What the renderer draws, handed on from the windowed and headless
+/// constructors to `Renderer::with_target`.
+struct GameState {
+    render_data: GameRenderData,
+    game_data: Arc<GameData>,
+    world: Arc<Mutex<World>>,
+    clock: Arc<WorldClock>,
+}
+
+/// How `create_render_pipeline` sets up a pipeline.
+struct PipelineOptions<'a> {
+    layout: &'a wgpu::PipelineLayout,
+    shader: &'a wgpu::ShaderModule,
+    vertex_entry: &'a str,
+    fragment_entry: &'a str,
+    vertex_layouts: &'a [wgpu::VertexBufferLayout<'a>],
     color_format: Option<wgpu::TextureFormat>,
     depth_format: Option<wgpu::TextureFormat>,
-    vertex_layouts: &[wgpu::VertexBufferLayout],
-    shader: &wgpu::ShaderModule,
-    vertex_entry: &str,
-    fragment_entry: &str,
+    sample_count: u32,
+    blend: wgpu::BlendState,
+    depth_write_enabled: bool,
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    options: &PipelineOptions,
 ) -> wgpu::RenderPipeline {
+    let PipelineOptions {
+        layout,
+        shader,
+        vertex_entry,
+        fragment_entry,
+        vertex_layouts,
+        color_format,
+        depth_format,
+        sample_count,
+        blend,
+        depth_write_enabled,
+    } = *options;
     let fragment_targets = match color_format {
         Some(format) => vec![Some(wgpu::ColorTargetState {
             format: format,
-            blend: Some(wgpu::BlendState::REPLACE),
+            // the same as replacing, but allowed for formats that can't be
+            // blended.
+            blend: (blend != wgpu::BlendState::REPLACE).then_some(blend),
             write_mask: wgpu::ColorWrites::all(),
         })],
         None => vec![],
@@ -105,13 +186,13 @@ fn create_render_pipeline(
         },
         depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
             format: format,
-            depth_write_enabled: true,
+            depth_write_enabled,
             depth_compare: wgpu::CompareFunction::Less,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
         multisample: wgpu::MultisampleState {
-            count: 1,
+            count: sample_count,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
@@ -119,20 +200,166 @@ fn create_render_pipeline(
     })
 }
 
-pub fn calc_light_direction(frame: u64) -> na::UnitVector3<f32> {
-    na::Unit::new_normalize(glm::rotate_vec3(
-        &glm::vec3(0.0, 1.0, 0.0),
-        10000 as f32 / 500.0,
-        &glm::vec3(1.0, 0.5, 0.0).normalize(),
-    ))
+fn draw_mesh<'a>(
+    render_pass: &mut wgpu::RenderPass<'a>,
+    mesh: &'a Mesh,
+) -> Result<()> {
+    if mesh.is_empty() {
+        return Ok(());
+    }
+    render_pass.set_vertex_buffer(0, mesh.vertex_buffer()?.slice(..));
+    render_pass.set_index_buffer(
+        mesh.index_buffer()?.slice(..),
+        wgpu::IndexFormat::Uint32,
+    );
+    render_pass.draw_indexed(0..mesh.index_count(), 0, 0..1);
+    Ok(())
+}
+
+fn create_block_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Block texture bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(texture.sampler()),
+            },
+        ],
+    })
+}
+
+fn create_light_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    light_buffer: &wgpu::Buffer,
+    shadows: &Shadows,
+    ssao: &Ssao,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Light bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: shadows.uniform_buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(shadows.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(shadows.sampler()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(ssao.view()),
+            },
+        ],
+    })
 }
+
+/// Creates the opaque and translucent block pipelines from `main.wgsl`.
+fn create_block_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    sample_count: u32,
+    shader_defines: &[(&str, &str)],
+) -> Result<(wgpu::RenderPipeline, wgpu::RenderPipeline)> {
+    let shader = load_shader_module(device, "main", shader_defines)?;
+    let pipeline = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout,
+            shader: &shader,
+            vertex_entry: "vs_main",
+            fragment_entry: "fs_main",
+            vertex_layouts: &[Vertex::desc()],
+            color_format: Some(color_format),
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count,
+            blend: wgpu::BlendState::REPLACE,
+            depth_write_enabled: true,
+        },
+    );
+    let translucent_pipeline = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout,
+            shader: &shader,
+            vertex_entry: "vs_main",
+            fragment_entry: "fs_main",
+            vertex_layouts: &[Vertex::desc()],
+            color_format: Some(color_format),
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count,
+            blend: wgpu::BlendState::ALPHA_BLENDING,
+            depth_write_enabled: false,
+        },
+    );
+    Ok((pipeline, translucent_pipeline))
+}
+
+/// Requests a device with the features and limits the renderer needs.
+fn request_device(
+    adapter: &wgpu::Adapter,
+) -> Result<(wgpu::Device, wgpu::Queue)> {
+    // lets MSAA use every sample count the GPU supports.
+    let optional_features = adapter.features()
+        & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            required_features: wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER
+                | optional_features,
+            required_limits: wgpu::Limits::default(),
+            label: None,
+        },
+        None,
+    ))?;
+    Ok((device, queue))
+}
+
+/// Where frames are drawn to.
+enum RenderTarget<'a> {
+    Window {
+        window: &'a Window,
+        surface: wgpu::Surface<'a>,
+        config: wgpu::SurfaceConfiguration,
+    },
+    /// An offscreen texture, read back with `capture::read_texture`.
+    Texture(wgpu::Texture),
+}
+
 pub struct Renderer<'a> {
-    surface: wgpu::Surface<'a>,
+    target: RenderTarget<'a>,
+    color_format: wgpu::TextureFormat,
+    /// The format the sky and blocks are drawn in, `HDR_FORMAT` with post
+    /// processing.
+    scene_format: wgpu::TextureFormat,
+    post_processing: Option<PostProcessing>,
+    anti_aliasing: AntiAliasing,
+    /// The MSAA sample counts the scene and depth formats support.
+    sample_counts: Vec<u32>,
+    /// Where the scene is drawn with MSAA.
+    multisampled_scene: Option<wgpu::TextureView>,
+    fxaa: Option<Fxaa>,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    window: &'a Window,
     depth_texture: Texture,
     pipeline: wgpu::RenderPipeline,
     camera: Box<dyn Camera>,
@@ -142,20 +369,41 @@ pub struct Renderer<'a> {
     camera_controller: CameraController,
     light_uniform: LightUniform,
     light_buffer: wgpu::Buffer,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     light_bind_group: wgpu::BindGroup,
-    texture_atlas_image: Texture,
-    texture_atlas_bind_group: wgpu::BindGroup,
+    shadows: Shadows,
+    ssao: Ssao,
+    block_texture: Texture,
+    block_texture_bind_group: wgpu::BindGroup,
+    texture_animator: TextureAnimator,
     chunk_queue: Arc<Mutex<VecDeque<ChunkMeshEvent>>>,
-    chunk_meshes: HashMap<glm::IVec2, Mesh>,
-    frame_count: u64,
+    chunk_meshes: HashMap<glm::IVec2, ChunkMesh>,
+    translucent_pipeline: wgpu::RenderPipeline,
+    translucent_sort_position: glm::Vec3,
+    warning_overlay_pipeline: wgpu::RenderPipeline,
+    sky: Sky,
+    fog: Fog,
+    water: Water,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    block_texture_bind_group_layout: wgpu::BindGroupLayout,
+    shader_defines: &'static [(&'static str, &'static str)],
+    asset_watcher: Option<AssetWatcher>,
+    render_world_sender: Sender<RenderWorldEvent>,
+    world: Arc<Mutex<World>>,
+    clock: Arc<WorldClock>,
+    modifiers: ModifiersState,
+    /// Copy the next frame into a screenshot.
+    screenshot_requested: bool,
 }
 
 impl<'a> Renderer<'a> {
     fn new(
         window: &'a Window,
         render_data: GameRenderData,
+        game_data: Arc<GameData>,
         world: Arc<Mutex<World>>,
-    ) -> Result<(Self, RenderWorld)> {
+        clock: Arc<WorldClock>,
+    ) -> Result<(Self, RenderWorld, Receiver<RenderWorldEvent>)> {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -173,16 +421,7 @@ impl<'a> Renderer<'a> {
             },
         ))
         .wrap_err("Failure requesting adapter")?;
-
-        let (device, queue) = pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                required_features:
-                    wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER,
-                required_limits: wgpu::Limits::default(),
-                label: None,
-            },
-            None,
-        ))?;
+        let (device, queue) = request_device(&adapter)?;
 
         let surface_capabilities = surface.get_capabilities(&adapter);
         let surface_format = surface_capabilities
@@ -192,9 +431,12 @@ impl<'a> Renderer<'a> {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_capabilities.formats[0]);
 
+        // screenshots copy the frame, if the surface allows it.
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_capabilities.usages & wgpu::TextureUsages::COPY_SRC);
         let config = wgpu::SurfaceConfiguration {
             desired_maximum_frame_latency: 2,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width: size.width,
             height: size.height,
@@ -204,6 +446,104 @@ impl<'a> Renderer<'a> {
         };
         surface.configure(&device, &config);
 
+        Self::with_target(
+            RenderTarget::Window {
+                window,
+                surface,
+                config,
+            },
+            surface_format,
+            size,
+            &adapter,
+            device,
+            queue,
+            GameState {
+                render_data,
+                game_data,
+                world,
+                clock,
+            },
+        )
+    }
+
+    /// Renders into a texture of the given size instead of a window. Falls
+    /// back to a software adapter on machines without a GPU.
+    fn new_headless(
+        size: winit::dpi::PhysicalSize<u32>,
+        render_data: GameRenderData,
+        game_data: Arc<GameData>,
+        world: Arc<Mutex<World>>,
+        clock: Arc<WorldClock>,
+    ) -> Result<(Self, RenderWorld, Receiver<RenderWorldEvent>)> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let request_adapter = |force_fallback_adapter| {
+            pollster::block_on(instance.request_adapter(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    force_fallback_adapter,
+                    compatible_surface: None,
+                },
+            ))
+        };
+        let adapter = match request_adapter(false) {
+            Some(adapter) => adapter,
+            None => {
+                log::warn!("No GPU adapter found, trying a software adapter");
+                request_adapter(true).wrap_err("Failure requesting adapter")?
+            }
+        };
+        log::info!("Rendering with {:?}", adapter.get_info());
+        let (device, queue) = request_device(&adapter)?;
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen render target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        Self::with_target(
+            RenderTarget::Texture(texture),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            size,
+            &adapter,
+            device,
+            queue,
+            GameState {
+                render_data,
+                game_data,
+                world,
+                clock,
+            },
+        )
+    }
+
+    fn with_target(
+        target: RenderTarget<'a>,
+        color_format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+        adapter: &wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        game: GameState,
+    ) -> Result<(Self, RenderWorld, Receiver<RenderWorldEvent>)> {
+        let GameState {
+            render_data,
+            game_data,
+            world,
+            clock,
+        } = game;
         let mut camera = Box::new(PerspectiveCamera::new(
             (size.width as f32) / (size.height as f32),
             0.1,
@@ -231,16 +571,7 @@ impl<'a> Renderer<'a> {
         let camera_bind_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
                 label: Some("Camera bind group layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
+                entries: &CAMERA_LAYOUT,
             },
         );
         let camera_bind_group =
@@ -256,8 +587,8 @@ impl<'a> Renderer<'a> {
         let light_uniform = LightUniform {
             direction: nalgebra::Unit::new_normalize(glm::vec3(0.4, 0.7, 0.3)),
             color: glm::vec3(1.5, 1.5, 1.5),
+            ambient: 0.15,
             _pad_0: 0,
-            _pad_1: 0,
         };
         let light_buffer =
             device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -266,125 +597,197 @@ impl<'a> Renderer<'a> {
                     | wgpu::BufferUsages::COPY_DST,
                 contents: bytemuck::cast_slice(&[light_uniform]),
             });
-        let light_bind_group_layout = device.create_bind_group_layout(
+
+        let block_textures = render_data.block_textures();
+        let block_texture =
+            block_textures.texture(&device, &queue, Some("Block textures"));
+        let texture_animator = TextureAnimator::new(block_textures);
+
+        let block_texture_bind_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
-                label: Some("Light bind group layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
+                label: Some("Block texture bind group layout"),
+                entries: &block_texture_layout(block_textures.backend()),
             },
         );
-        let light_bind_group =
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Light bind group"),
-                layout: &light_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: light_buffer.as_entire_binding(),
-                }],
-            });
 
-        let texture_atlas_image = render_data.texture_atlas().texture(
+        let block_texture_bind_group = create_block_texture_bind_group(
             &device,
-            &queue,
-            Some("Block Atlas"),
+            &block_texture_bind_group_layout,
+            &block_texture,
         );
 
-        let atlas_bind_group_layout = device.create_bind_group_layout(
+        let shader_defines = block_textures.backend().shader_defines();
+        let shadows = Shadows::new(
+            &device,
+            &block_texture_bind_group_layout,
+            shader_defines,
+        )?;
+        let light_bind_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
-                label: Some("Atlas bind group layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float {
-                                filterable: true,
-                            },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(
-                            wgpu::SamplerBindingType::Filtering,
-                        ),
-                        count: None,
-                    },
-                ],
+                label: Some("Light bind group layout"),
+                entries: &LIGHT_LAYOUT,
             },
         );
+        let ssao = Ssao::new(
+            &device,
+            &queue,
+            &camera_bind_group_layout,
+            &block_texture_bind_group_layout,
+            shader_defines,
+            size,
+        )?;
+        let light_bind_group = create_light_bind_group(
+            &device,
+            &light_bind_group_layout,
+            &light_buffer,
+            &shadows,
+            &ssao,
+        );
 
-        let texture_atlas_bind_group =
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Texture atlas bind group"),
-                layout: &atlas_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            texture_atlas_image.view(),
-                        ),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(
-                            texture_atlas_image.sampler(),
-                        ),
-                    },
-                ],
-            });
-
+        let post_processing = match SETTINGS.graphics.post_processing.hdr {
+            true => Some(PostProcessing::new(&device, size, color_format)?),
+            false => None,
+        };
+        let scene_format = match post_processing {
+            Some(_) => HDR_FORMAT,
+            None => color_format,
+        };
+        let sample_counts = supported_sample_counts(
+            adapter,
+            &device,
+            &[scene_format, Texture::DEPTH_FORMAT],
+        );
+        let anti_aliasing = anti_aliasing::validate(
+            SETTINGS.graphics.anti_aliasing,
+            &sample_counts,
+        );
+        let sample_count = anti_aliasing.sample_count();
         let depth_texture = Texture::create_depth_texture(
             &device,
-            config.width,
-            config.height,
+            size.width,
+            size.height,
+            sample_count,
             Some("Depth Texture"),
         );
+        let multisampled_scene = create_multisampled_target(
+            &device,
+            size,
+            scene_format,
+            sample_count,
+        );
+        let fxaa = match anti_aliasing {
+            AntiAliasing::Fxaa => {
+                Some(Fxaa::new(&device, size, color_format)?)
+            }
+            _ => None,
+        };
 
-        let shader = load_shader_module(&device, "main", &[])?;
+        let sky = Sky::new(
+            &device,
+            &queue,
+            &camera_bind_group_layout,
+            scene_format,
+            sample_count,
+            render_data.sky_textures(),
+        )?;
+
+        let fog_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Fog bind group layout"),
+                entries: &FOG_LAYOUT,
+            },
+        );
+        let fog = Fog::new(
+            &device,
+            &fog_bind_group_layout,
+            sky.uniform_buffer(),
+            &game_data,
+        );
+        let water = Water::new(
+            &device,
+            &WaterScene {
+                camera_bind_group_layout: &camera_bind_group_layout,
+                light_bind_group_layout: &light_bind_group_layout,
+                fog_bind_group_layout: &fog_bind_group_layout,
+                scene_format,
+                color_format,
+                sample_count,
+            },
+            ssao.normal_depth_view(),
+            size,
+        )?;
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render pipeline layout"),
                 bind_group_layouts: &[
                     &camera_bind_group_layout,
-                    &atlas_bind_group_layout,
+                    &block_texture_bind_group_layout,
                     &light_bind_group_layout,
+                    &fog_bind_group_layout,
                 ],
                 push_constant_ranges: &[],
             });
-        let pipeline = create_render_pipeline(
+        let (pipeline, translucent_pipeline) = create_block_pipelines(
             &device,
             &render_pipeline_layout,
-            Some(config.format),
-            Some(Texture::DEPTH_FORMAT),
-            &[Vertex::desc()],
-            &shader,
-            "vs_main",
-            "fs_main",
+            scene_format,
+            sample_count,
+            shader_defines,
+        )?;
+
+        // shown in place of a message, the failed assets are in the log.
+        let warning_overlay_shader =
+            load_shader_module(&device, "warning_overlay", &[])?;
+        let warning_overlay_pipeline = create_render_pipeline(
+            &device,
+            &PipelineOptions {
+                layout: &device.create_pipeline_layout(
+                    &wgpu::PipelineLayoutDescriptor {
+                        label: Some("Warning overlay pipeline layout"),
+                        bind_group_layouts: &[],
+                        push_constant_ranges: &[],
+                    },
+                ),
+                shader: &warning_overlay_shader,
+                vertex_entry: "vs_main",
+                fragment_entry: "fs_main",
+                vertex_layouts: &[],
+                color_format: Some(color_format),
+                depth_format: None,
+                sample_count: 1,
+                blend: wgpu::BlendState::REPLACE,
+                depth_write_enabled: false,
+            },
         );
 
-        let (render_world, chunk_queue) = RenderWorld::new(world, render_data);
+        // there is nothing to reload into when rendering a single image.
+        let watch_assets = matches!(target, RenderTarget::Window { .. });
+        let asset_watcher = match SETTINGS.assets.hot_reload && watch_assets {
+            true => AssetWatcher::new()
+                .map_err(|error| log::warn!("Hot reload disabled: {error:#}"))
+                .ok(),
+            false => None,
+        };
+        let (render_world_sender, render_world_receiver) = mpsc::channel();
+
+        let translucent_sort_position = camera_controller.position();
+        let (render_world, chunk_queue) =
+            RenderWorld::new(world.clone(), game_data, render_data);
 
         Ok((
             Self {
-                window,
+                target,
+                color_format,
+                scene_format,
+                post_processing,
+                anti_aliasing,
+                sample_counts,
+                multisampled_scene,
+                fxaa,
                 size,
-                surface,
                 device,
                 queue,
-                config,
                 pipeline,
                 camera,
                 camera_uniform,
@@ -393,29 +796,68 @@ impl<'a> Renderer<'a> {
                 camera_controller,
                 light_uniform,
                 light_buffer,
+                light_bind_group_layout,
                 light_bind_group,
+                shadows,
+                ssao,
                 depth_texture,
-                texture_atlas_image,
-                texture_atlas_bind_group,
+                block_texture,
+                block_texture_bind_group,
+                texture_animator,
                 chunk_queue,
                 chunk_meshes: HashMap::new(),
-                frame_count: 0,
+                translucent_pipeline,
+                translucent_sort_position,
+                warning_overlay_pipeline,
+                sky,
+                fog,
+                water,
+                render_pipeline_layout,
+                block_texture_bind_group_layout,
+                shader_defines,
+                asset_watcher,
+                render_world_sender,
+                world,
+                clock,
+                modifiers: ModifiersState::empty(),
+                screenshot_requested: false,
             },
             render_world,
+            render_world_receiver,
         ))
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        let RenderTarget::Window {
+            surface, config, ..
+        } = &mut self.target
+        else {
+            return;
+        };
         if new_size.width > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture = Texture::create_depth_texture(
+            config.width = new_size.width;
+            config.height = new_size.height;
+            surface.configure(&self.device, config);
+            self.create_scene_targets();
+            if let Some(post_processing) = &mut self.post_processing {
+                post_processing.resize(&self.device, new_size);
+            }
+            if let Some(fxaa) = &mut self.fxaa {
+                fxaa.resize(&self.device, new_size);
+            }
+            self.ssao.resize(&self.device, &self.queue, new_size);
+            self.water.resize(
+                &self.device,
+                new_size,
+                self.ssao.normal_depth_view(),
+            );
+            self.light_bind_group = create_light_bind_group(
                 &self.device,
-                self.config.width,
-                self.config.height,
-                Some("Depth Texture"),
+                &self.light_bind_group_layout,
+                &self.light_buffer,
+                &self.shadows,
+                &self.ssao,
             );
             self.camera.set_aspect_ratio(
                 (new_size.width as f32) / (new_size.height as f32),
@@ -423,8 +865,242 @@ impl<'a> Renderer<'a> {
         }
     }
 
+    /// Recreates the depth texture and the multisampled scene for the
+    /// window size and sample count.
+    fn create_scene_targets(&mut self) {
+        let sample_count = self.anti_aliasing.sample_count();
+        self.depth_texture = Texture::create_depth_texture(
+            &self.device,
+            self.size.width,
+            self.size.height,
+            sample_count,
+            Some("Depth Texture"),
+        );
+        self.multisampled_scene = create_multisampled_target(
+            &self.device,
+            self.size,
+            self.scene_format,
+            sample_count,
+        );
+    }
+
+    /// Switches to `mode`, which must be supported, recreating the
+    /// pipelines and images that depend on it. Keeps the current mode if
+    /// that fails.
+    fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+        let sample_count = mode.sample_count();
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = create_block_pipelines(
+            &self.device,
+            &self.render_pipeline_layout,
+            self.scene_format,
+            sample_count,
+            self.shader_defines,
+        )
+        .and_then(|block_pipelines| {
+            let sky_pipelines = self.sky.create_pipelines(
+                &self.device,
+                self.scene_format,
+                sample_count,
+            )?;
+            let water_pipelines = self.water.create_pipelines(
+                &self.device,
+                self.scene_format,
+                sample_count,
+            )?;
+            let new_fxaa =
+                match mode {
+                    AntiAliasing::Fxaa if self.fxaa.is_none() => Some(
+                        Fxaa::new(&self.device, self.size, self.color_format)?,
+                    ),
+                    _ => None,
+                };
+            Ok((block_pipelines, sky_pipelines, water_pipelines, new_fxaa))
+        });
+        let validation_error =
+            pollster::block_on(self.device.pop_error_scope());
+        match (result, validation_error) {
+            (
+                Ok((
+                    block_pipelines,
+                    sky_pipelines,
+                    water_pipelines,
+                    new_fxaa,
+                )),
+                None,
+            ) => {
+                (self.pipeline, self.translucent_pipeline) = block_pipelines;
+                self.sky.set_pipelines(sky_pipelines);
+                self.water.set_pipelines(water_pipelines);
+                self.fxaa = match mode {
+                    AntiAliasing::Fxaa => new_fxaa.or(self.fxaa.take()),
+                    _ => None,
+                };
+                self.anti_aliasing = mode;
+                self.create_scene_targets();
+                log::info!("Anti-aliasing: {mode:?}");
+            }
+            (Err(error), _) => {
+                log::error!("Failed to switch anti-aliasing:\n{error:?}")
+            }
+            (_, Some(error)) => {
+                log::error!("Failed to switch anti-aliasing:\n{error}")
+            }
+        }
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
-        self.camera_controller.handle_event(event)
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(KeyCode::F2),
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if self.modifiers.shift_key() {
+                    let scale = self.tile_scale();
+                    if let Err(error) = self.take_tiled_screenshot(scale) {
+                        log::error!("Failed to take screenshot:\n{error:?}");
+                    }
+                } else {
+                    self.screenshot_requested = true;
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(KeyCode::F4),
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.set_anti_aliasing(next_mode(
+                    self.anti_aliasing,
+                    &self.sample_counts,
+                ));
+                true
+            }
+            _ => self.camera_controller.handle_event(event),
+        }
+    }
+
+    /// `ScreenshotSettings::tile_scale`, lowered until the screenshot fits
+    /// into `MAX_SCREENSHOT_PIXELS`.
+    fn tile_scale(&self) -> u32 {
+        let requested =
+            SETTINGS.screenshots.tile_scale.clamp(1, MAX_TILE_SCALE);
+        let window_pixels = self.size.width as u64 * self.size.height as u64;
+        let scale = (1..=requested)
+            .rev()
+            .find(|scale| {
+                window_pixels * (scale * scale) as u64 <= MAX_SCREENSHOT_PIXELS
+            })
+            .unwrap_or(1);
+        if scale < requested {
+            log::warn!(
+                "A {requested}x{requested} tiled screenshot of this window \
+                 would be too large, taking a {scale}x{scale} one instead"
+            );
+        }
+        scale
+    }
+
+    /// Renders the view in `scale` x `scale` tiles of the window size and
+    /// saves them as one screenshot. Each tile stretches its part of the
+    /// view over the whole clip space, so the image has `scale` times the
+    /// resolution of the window in both directions.
+    fn take_tiled_screenshot(&mut self, scale: u32) -> Result<()> {
+        log::info!("Taking a {scale}x{scale} tiled screenshot");
+        let (width, height) = (self.size.width, self.size.height);
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot tile"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.color_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view =
+            texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let view_projection = self.camera.get_view_projection();
+        let scale_f = scale as f32;
+        let mut tiles = Vec::new();
+        for y in 0..scale {
+            for x in 0..scale {
+                // maps the tile's part of clip space to -1..1, tiles are
+                // counted from the top left like image pixels.
+                let offset =
+                    glm::translation(&glm::vec3(
+                        scale_f - 2.0 * x as f32 - 1.0,
+                        2.0 * y as f32 + 1.0 - scale_f,
+                        0.0,
+                    )) * glm::scaling(&glm::vec3(scale_f, scale_f, 1.0));
+                self.camera_uniform.view_projection = offset * view_projection;
+                self.queue.write_buffer(
+                    &self.camera_buffer,
+                    0,
+                    bytemuck::cast_slice(&[self.camera_uniform]),
+                );
+                self.sky.set_view_projection(
+                    &self.queue,
+                    &self.camera_uniform.view_projection,
+                );
+                self.ssao.set_view_projection(
+                    &self.queue,
+                    &self.camera_uniform.view_projection,
+                );
+                self.water
+                    .update(&self.queue, &self.camera_uniform.view_projection);
+                self.draw(&view, false)?;
+                let mut encoder = self.device.create_command_encoder(
+                    &wgpu::CommandEncoderDescriptor {
+                        label: Some("Screenshot encoder"),
+                    },
+                );
+                let copy =
+                    TextureCopy::new(&self.device, &mut encoder, &texture)?;
+                self.queue.submit(std::iter::once(encoder.finish()));
+                tiles.push((glm::vec2(x * width, y * height), copy.map()));
+            }
+        }
+        // the next update restores the camera, sky, SSAO and water uniforms.
+        save_screenshot(width * scale, height * scale, tiles);
+        Ok(())
+    }
+
+    /// Copies a frame that was just drawn into a screenshot.
+    fn capture_frame(&self, texture: &wgpu::Texture) -> Result<()> {
+        let mut encoder = self.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Screenshot encoder"),
+            },
+        );
+        let copy = TextureCopy::new(&self.device, &mut encoder, texture)?;
+        self.queue.submit(std::iter::once(encoder.finish()));
+        save_screenshot(
+            texture.width(),
+            texture.height(),
+            vec![(glm::vec2(0, 0), copy.map())],
+        );
+        Ok(())
     }
 
     fn mouse_event(&mut self, delta: glm::Vec2) -> Result<()> {
@@ -432,130 +1108,503 @@ impl<'a> Renderer<'a> {
             x: self.size.width / 2,
             y: self.size.height / 2,
         };
-        self.window.set_cursor_position(position)?;
+        if let RenderTarget::Window { window, .. } = &self.target {
+            window.set_cursor_position(position)?;
+        }
         self.camera_controller.handle_mouse_move(delta);
         Ok(())
     }
 
+    /// Recompiles `main.wgsl`, `sky.wgsl`, `shadow.wgsl`, `normals.wgsl`,
+    /// `ssao.wgsl`, `water.wgsl`, `underwater.wgsl`, `post_processing.wgsl`
+    /// and `fxaa.wgsl`, keeping the current pipelines if that fails.
+    fn reload_shaders(&mut self) {
+        log::info!("Reloading shaders");
+        let sample_count = self.anti_aliasing.sample_count();
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipelines = create_block_pipelines(
+            &self.device,
+            &self.render_pipeline_layout,
+            self.scene_format,
+            sample_count,
+            self.shader_defines,
+        )
+        .and_then(|block_pipelines| {
+            let sky_pipelines = self.sky.create_pipelines(
+                &self.device,
+                self.scene_format,
+                sample_count,
+            )?;
+            let shadow_pipeline =
+                self.shadows.create_pipeline(&self.device)?;
+            let ssao_pipelines = self.ssao.create_pipelines(&self.device)?;
+            let water_pipelines = self.water.create_pipelines(
+                &self.device,
+                self.scene_format,
+                sample_count,
+            )?;
+            let post_processing_pipelines = self
+                .post_processing
+                .as_ref()
+                .map(|post_processing| {
+                    post_processing
+                        .create_pipelines(&self.device, self.color_format)
+                })
+                .transpose()?;
+            let fxaa_pipeline = self
+                .fxaa
+                .as_ref()
+                .map(|fxaa| fxaa.create_pipeline(&self.device))
+                .transpose()?;
+            Ok((
+                block_pipelines,
+                sky_pipelines,
+                shadow_pipeline,
+                ssao_pipelines,
+                water_pipelines,
+                post_processing_pipelines,
+                fxaa_pipeline,
+            ))
+        });
+        let validation_error =
+            pollster::block_on(self.device.pop_error_scope());
+        match (pipelines, validation_error) {
+            (
+                Ok((
+                    block_pipelines,
+                    sky_pipelines,
+                    shadow_pipeline,
+                    ssao_pipelines,
+                    water_pipelines,
+                    post_processing_pipelines,
+                    fxaa_pipeline,
+                )),
+                None,
+            ) => {
+                (self.pipeline, self.translucent_pipeline) = block_pipelines;
+                self.sky.set_pipelines(sky_pipelines);
+                self.shadows.set_pipeline(shadow_pipeline);
+                self.ssao.set_pipelines(ssao_pipelines);
+                self.water.set_pipelines(water_pipelines);
+                if let (Some(post_processing), Some(pipelines)) =
+                    (&mut self.post_processing, post_processing_pipelines)
+                {
+                    post_processing.set_pipelines(pipelines);
+                }
+                if let (Some(fxaa), Some(pipeline)) =
+                    (&mut self.fxaa, fxaa_pipeline)
+                {
+                    fxaa.set_pipeline(pipeline);
+                }
+            }
+            (Err(error), _) => {
+                log::error!("Failed to reload shaders:\n{error:?}")
+            }
+            (_, Some(error)) => {
+                log::error!("Failed to reload shaders:\n{error}")
+            }
+        }
+    }
+
+    /// Reloads textures and block models and remeshes all chunks with them.
+    fn reload_block_assets(&mut self) {
+        log::info!("Reloading textures and block models");
+        let render_data = match crate::init::initialize() {
+            Ok((_, render_data)) => render_data,
+            Err(error) => {
+                log::error!("Failed to reload block assets:\n{error:?}");
+                return;
+            }
+        };
+        let block_textures = render_data.block_textures();
+        self.block_texture = block_textures.texture(
+            &self.device,
+            &self.queue,
+            Some("Block textures"),
+        );
+        self.block_texture_bind_group = create_block_texture_bind_group(
+            &self.device,
+            &self.block_texture_bind_group_layout,
+            &self.block_texture,
+        );
+        self.texture_animator = TextureAnimator::new(block_textures);
+        self.sky.set_textures(
+            &self.device,
+            &self.queue,
+            render_data.sky_textures(),
+        );
+        // the mesh thread is gone when the game is shutting down.
+        let _ = self
+            .render_world_sender
+            .send(RenderWorldEvent::Reload(render_data));
+    }
+
     fn update(&mut self) {
+        if let Some(changes) =
+            self.asset_watcher.as_mut().and_then(|x| x.changes())
+        {
+            if changes.shaders {
+                self.reload_shaders();
+            }
+            if changes.block_assets {
+                self.reload_block_assets();
+            }
+        }
         self.camera_controller.update(self.camera.as_mut());
+        let camera_position = self.camera_controller.position();
+        if glm::distance(&camera_position, &self.translucent_sort_position)
+            > TRANSLUCENT_SORT_DISTANCE
+        {
+            self.translucent_sort_position = camera_position;
+            for mesh in self.chunk_meshes.values_mut() {
+                if !mesh.translucent.is_empty() {
+                    mesh.translucent.sort_back_to_front(camera_position);
+                    mesh.translucent
+                        .update_index_buffer(&self.device, &self.queue);
+                }
+            }
+        }
         self.camera_uniform
             .update_view_projection(self.camera.as_ref());
+        self.camera_uniform.position = camera_position;
         self.queue.write_buffer(
             &self.camera_buffer,
             0,
             bytemuck::cast_slice(&[self.camera_uniform]),
         );
-        let mut light_direction = calc_light_direction(self.frame_count);
-        if light_direction.y < 0.0 {
-            light_direction = -light_direction
-        }
-        self.light_uniform.direction = light_direction;
+        self.texture_animator.update(
+            &self.clock,
+            &self.queue,
+            self.block_texture.texture(),
+        );
+        // finishes mapping the buffers of screenshots being saved.
+        self.device.poll(wgpu::Maintain::Poll);
+        let daylight = Daylight::new(self.clock.time_of_day());
+        self.light_uniform.direction =
+            na::Unit::new_normalize(daylight.light_direction);
+        self.light_uniform.color = daylight.light_color;
+        self.light_uniform.ambient = daylight.ambient;
         self.queue.write_buffer(
             &self.light_buffer,
             0,
             bytemuck::cast_slice(&[self.light_uniform]),
         );
+        self.sky.update(
+            &self.queue,
+            &self.camera_uniform.view_projection,
+            &daylight,
+        );
+        self.ssao.set_view_projection(
+            &self.queue,
+            &self.camera_uniform.view_projection,
+        );
+        self.water
+            .update(&self.queue, &self.camera_uniform.view_projection);
+        self.shadows.update(
+            &self.queue,
+            &self.camera_uniform.view_projection,
+            camera_position,
+            daylight.light_direction,
+        );
+        // keeps the last fog while the mesh thread holds the world.
+        if let Ok(world) = self.world.try_lock() {
+            self.fog.update(&self.queue, &world, camera_position);
+        }
     }
 
-    fn render(&mut self) -> Result<()> {
-        self.frame_count += 1;
-        match self.surface.get_current_texture() {
-            Ok(output) => {
-                let view = output
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
-                let mut encoder = self.device.create_command_encoder(
-                    &wgpu::CommandEncoderDescriptor {
-                        label: Some("Render encoder"),
-                    },
+    /// Sets where the camera is and where it looks, in degrees. A yaw of 0
+    /// looks north and 90 east, positive pitch looks up.
+    fn set_view(&mut self, position: glm::Vec3, yaw: f32, pitch: f32) {
+        self.camera_controller.set_position(position);
+        self.camera_controller
+            .set_angles(yaw.to_radians(), -pitch.to_radians());
+    }
+
+    /// Draws the world into `view`, and the overlays if `overlays` is set.
+    fn draw(&self, view: &wgpu::TextureView, overlays: bool) -> Result<()> {
+        let mut encoder = self.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render encoder"),
+            },
+        );
+        let opaque_meshes = self
+            .chunk_meshes
+            .iter()
+            .map(|(position, mesh)| (*position, &mesh.opaque))
+            .collect::<Vec<_>>();
+        self.shadows.draw(
+            &mut encoder,
+            &opaque_meshes,
+            &self.block_texture_bind_group,
+        )?;
+        self.ssao.draw(
+            &mut encoder,
+            &opaque_meshes,
+            &self.camera_bind_group,
+            &self.block_texture_bind_group,
+        )?;
+        // with FXAA, the finished image is only smoothed onto `view` at the
+        // end, underwater it is distorted before that, and with post
+        // processing the scene only reaches it after tonemapping.
+        let output_view =
+            self.fxaa.as_ref().map_or(view, |fxaa| fxaa.input_view());
+        let underwater = self.fog.underwater();
+        let composed_view = match underwater {
+            true => self.water.underwater_input_view(),
+            false => output_view,
+        };
+        let scene_view = self
+            .post_processing
+            .as_ref()
+            .map_or(composed_view, |post_processing| {
+                post_processing.scene_view()
+            });
+        // with MSAA, the translucent pass resolves the samples into
+        // `scene_view`.
+        let (draw_view, resolve_target) = match &self.multisampled_scene {
+            Some(multisampled_scene) => (multisampled_scene, Some(scene_view)),
+            None => (scene_view, None),
+        };
+        self.sky
+            .draw(&mut encoder, draw_view, &self.camera_bind_group);
+        {
+            {
+                let mut render_pass =
+                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Render pass"),
+                        color_attachments: &[Some(
+                            wgpu::RenderPassColorAttachment {
+                                view: draw_view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
+                                },
+                            },
+                        )],
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachment {
+                                view: self.depth_texture.view(),
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(1.0),
+                                    store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                        ..Default::default()
+                    });
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_bind_group(
+                    1,
+                    &self.block_texture_bind_group,
+                    &[],
                 );
-                {
-                    {
-                        let mut render_pass = encoder.begin_render_pass(
-                            &wgpu::RenderPassDescriptor {
-                                label: Some("Render pass"),
-                                color_attachments: &[Some(
-                                    wgpu::RenderPassColorAttachment {
-                                        view: &view,
-                                        resolve_target: None,
-                                        ops: wgpu::Operations {
-                                            load: wgpu::LoadOp::Clear(
-                                                wgpu::Color {
-                                                    r: 0.0,
-                                                    g: 0.0,
-                                                    b: 0.0,
-                                                    a: 1.0,
-                                                },
-                                            ),
-                                            store: wgpu::StoreOp::Store,
-                                        },
-                                    },
-                                )],
-                                depth_stencil_attachment: Some(
-                                    wgpu::RenderPassDepthStencilAttachment {
-                                        view: self.depth_texture.view(),
-                                        depth_ops: Some(wgpu::Operations {
-                                            load: wgpu::LoadOp::Clear(1.0),
-                                            store: wgpu::StoreOp::Store,
-                                        }),
-                                        stencil_ops: None,
-                                    },
-                                ),
-                                ..Default::default()
+                render_pass.set_bind_group(2, &self.light_bind_group, &[]);
+                render_pass.set_bind_group(3, self.fog.bind_group(), &[]);
+                for mesh in self.chunk_meshes.values() {
+                    draw_mesh(&mut render_pass, &mesh.opaque)?;
+                }
+            }
+            {
+                let mut render_pass =
+                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Water render pass"),
+                        color_attachments: &[Some(
+                            wgpu::RenderPassColorAttachment {
+                                view: draw_view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
+                                },
                             },
-                        );
-                        render_pass.set_pipeline(&self.pipeline);
-                        render_pass.set_bind_group(
-                            0,
-                            &self.camera_bind_group,
-                            &[],
-                        );
-                        render_pass.set_bind_group(
-                            1,
-                            &self.texture_atlas_bind_group,
-                            &[],
-                        );
-                        render_pass.set_bind_group(
-                            2,
-                            &self.light_bind_group,
-                            &[],
-                        );
-                        for mesh in self.chunk_meshes.values() {
-                            render_pass.set_vertex_buffer(
-                                0,
-                                mesh.vertex_buffer()?.slice(..),
-                            );
-                            render_pass.set_index_buffer(
-                                mesh.index_buffer()?.slice(..),
-                                wgpu::IndexFormat::Uint32,
-                            );
-                            render_pass.draw_indexed(
-                                0..mesh.index_count(),
-                                0,
-                                0..1,
-                            );
-                        }
-                    }
+                        )],
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachment {
+                                view: self.depth_texture.view(),
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                        ..Default::default()
+                    });
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_bind_group(2, &self.light_bind_group, &[]);
+                render_pass.set_bind_group(3, self.fog.bind_group(), &[]);
+                self.water.draw(
+                    &mut render_pass,
+                    self.chunk_meshes.values().map(|mesh| &mesh.water),
+                )?;
+            }
+            {
+                let camera_position = self.camera_controller.position();
+                let mut translucent_meshes = self
+                    .chunk_meshes
+                    .iter()
+                    .filter(|(_, mesh)| !mesh.translucent.is_empty())
+                    .map(|(position, mesh)| {
+                        let center = glm::vec2(
+                            position.x as f32 + 0.5,
+                            position.y as f32 + 0.5,
+                        ) * Chunk::SIZE_X as f32;
+                        (
+                            glm::distance2(&center, &camera_position.xz()),
+                            &mesh.translucent,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                translucent_meshes.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+                let mut render_pass =
+                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Translucent render pass"),
+                        color_attachments: &[Some(
+                            wgpu::RenderPassColorAttachment {
+                                view: draw_view,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
+                                },
+                            },
+                        )],
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachment {
+                                view: self.depth_texture.view(),
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                        ..Default::default()
+                    });
+                render_pass.set_pipeline(&self.translucent_pipeline);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_bind_group(
+                    1,
+                    &self.block_texture_bind_group,
+                    &[],
+                );
+                render_pass.set_bind_group(2, &self.light_bind_group, &[]);
+                render_pass.set_bind_group(3, self.fog.bind_group(), &[]);
+                for (_, mesh) in translucent_meshes {
+                    draw_mesh(&mut render_pass, mesh)?;
                 }
-                self.queue.submit(std::iter::once(encoder.finish()));
-                output.present();
             }
-            Err(wgpu::SurfaceError::Outdated) => {
-                log::info!("Outdated surface texture");
-                self.surface.configure(&self.device, &self.config)
+            if let Some(post_processing) = &self.post_processing {
+                post_processing.draw(&mut encoder, composed_view);
+            }
+            if underwater {
+                self.water.draw_underwater(&mut encoder, output_view);
             }
-            Err(wgpu::SurfaceError::Lost) => {
-                log::info!("Swapchain lost");
-                self.resize(self.window.inner_size())
+            if let Some(fxaa) = &self.fxaa {
+                fxaa.draw(&mut encoder, view);
             }
-            Err(e) => {
-                log::error!("Error: {e}")
+            if overlays && ASSETS.error_count() > 0 {
+                let mut render_pass =
+                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Warning overlay render pass"),
+                        color_attachments: &[Some(
+                            wgpu::RenderPassColorAttachment {
+                                view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: wgpu::StoreOp::Store,
+                                },
+                            },
+                        )],
+                        ..Default::default()
+                    });
+                render_pass.set_pipeline(&self.warning_overlay_pipeline);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let screenshot_requested =
+            std::mem::take(&mut self.screenshot_requested);
+        let lost_size = match &self.target {
+            RenderTarget::Window {
+                window,
+                surface,
+                config,
+            } => {
+                match surface.get_current_texture() {
+                    Ok(output) => {
+                        self.draw(
+                            &output.texture.create_view(
+                                &wgpu::TextureViewDescriptor::default(),
+                            ),
+                            true,
+                        )?;
+                        if screenshot_requested {
+                            let result = match config
+                                .usage
+                                .contains(wgpu::TextureUsages::COPY_SRC)
+                            {
+                                true => self.capture_frame(&output.texture),
+                                // drawing the frame again looks the same.
+                                false => self.take_tiled_screenshot(1),
+                            };
+                            if let Err(error) = result {
+                                log::error!(
+                                    "Failed to take screenshot:\n{error:?}"
+                                );
+                            }
+                        }
+                        output.present();
+                        None
+                    }
+                    Err(wgpu::SurfaceError::Outdated) => {
+                        log::info!("Outdated surface texture");
+                        surface.configure(&self.device, config);
+                        None
+                    }
+                    Err(wgpu::SurfaceError::Lost) => {
+                        log::info!("Swapchain lost");
+                        Some(window.inner_size())
+                    }
+                    Err(e) => {
+                        log::error!("Error: {e}");
+                        None
+                    }
+                }
+            }
+            // headless frames are saved as they are, without the overlays.
+            RenderTarget::Texture(texture) => {
+                self.draw(
+                    &texture
+                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                    false,
+                )?;
+                None
             }
         };
+        if let Some(size) = lost_size {
+            self.resize(size);
+        }
+        self.receive_chunk_meshes();
+        Ok(())
+    }
+
+    /// Uploads the chunk meshes the mesh thread has finished.
+    fn receive_chunk_meshes(&mut self) {
         while let Some(event) = self.chunk_queue.lock().unwrap().pop_front() {
             match event {
                 ChunkMeshEvent::Update { position, mut mesh } => {
+                    mesh.translucent
+                        .sort_back_to_front(self.translucent_sort_position);
                     mesh.update_buffers(&self.device, &self.queue);
                     self.chunk_meshes.insert(position, mesh)
                 }
@@ -564,31 +1613,36 @@ impl<'a> Renderer<'a> {
                 }
             };
         }
-        Ok(())
     }
 
     fn run_mesh_thread(
         render_world: RenderWorld,
-        chunk_receiver: Receiver<ChunkEvent>,
+        receiver: Receiver<RenderWorldEvent>,
     ) {
         let mut render_world = render_world;
-        let mut chunk_receiver = chunk_receiver;
-        while let Ok(event) = chunk_receiver.recv() {
-            render_world.handle_chunk_event(event);
+        while let Ok(event) = receiver.recv() {
+            // events that arrived in the meantime are handled together, so a
+            // chunk and its neighbors loading one by one are meshed once.
+            let events = std::iter::once(event).chain(receiver.try_iter());
+            render_world.handle_events(events);
         }
     }
 }
 
 pub fn run(
     render_data: GameRenderData,
+    game_data: Arc<GameData>,
     chunk_receiver: Receiver<ChunkEvent>,
     world: Arc<Mutex<World>>,
+    clock: Arc<WorldClock>,
 ) -> Result<()> {
     let event_loop = EventLoop::new()?;
     let window = WindowBuilder::new().build(&event_loop)?;
     match SETTINGS.graphics.window {
         crate::settings::WindowMode::Default { width, height } => {
-            window.request_inner_size(winit::dpi::PhysicalSize {
+            // the renderer reads the size from the window either way, a
+            // size applied later arrives as a resize event.
+            let _ = window.request_inner_size(winit::dpi::PhysicalSize {
                 width,
                 height,
             });
@@ -602,34 +1656,44 @@ pub fn run(
             )))
         }
     }
-    let (mut renderer, render_world) =
-        Renderer::new(&window, render_data, world)?;
+    let (mut renderer, render_world, render_world_receiver) =
+        Renderer::new(&window, render_data, game_data, world, clock)?;
+    let window = &window;
+    let chunk_sender = renderer.render_world_sender.clone();
     window.set_cursor_visible(false);
 
     thread::scope(move |s| {
+        // chunk events from the game share the mesh thread with reloads.
+        s.spawn(move || {
+            for event in chunk_receiver {
+                if chunk_sender.send(RenderWorldEvent::Chunk(event)).is_err() {
+                    break;
+                }
+            }
+        });
         s.spawn(move || {
-            Renderer::run_mesh_thread(render_world, chunk_receiver)
+            Renderer::run_mesh_thread(render_world, render_world_receiver)
         });
         event_loop.run(move |event, control_flow| match event {
         winit::event::Event::WindowEvent {
             window_id,
             ref event,
         } => {
-            if window_id == renderer.window.id() && !renderer.input(event) {
+            if window_id == window.id() && !renderer.input(event) {
                 match event {
                     WindowEvent::Resized(physical_size) => {
                         renderer.resize(*physical_size)
                     }
                     WindowEvent::ScaleFactorChanged {
                         ..
-                    } => renderer.resize(renderer.window.inner_size()),
+                    } => renderer.resize(window.inner_size()),
                     WindowEvent::CloseRequested => {
                         control_flow.exit();
                     }
                     WindowEvent::RedrawRequested => {
                         renderer.update();
                         renderer.render().unwrap();
-                        renderer.window.request_redraw();
+                        window.request_redraw();
                     }
                     _ => {}
                 }
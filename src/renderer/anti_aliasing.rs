@@ -0,0 +1,256 @@
+use color_eyre::Result;
+
+use super::layouts::FXAA_LAYOUT;
+use super::post_processing::fullscreen_pass;
+use super::shader::load_shader_module;
+use super::{create_render_pipeline, PipelineOptions, Texture};
+use crate::settings::AntiAliasing;
+
+/// The sample counts MSAA may draw with.
+const SAMPLE_COUNTS: [u32; 3] = [2, 4, 8];
+
+impl AntiAliasing {
+    /// Samples per pixel of the scene, 1 without MSAA.
+    pub fn sample_count(self) -> u32 {
+        match self {
+            AntiAliasing::Msaa { samples } => samples,
+            AntiAliasing::None | AntiAliasing::Fxaa => 1,
+        }
+    }
+}
+
+/// The MSAA sample counts every one of `formats` can be drawn with.
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    formats: &[wgpu::TextureFormat],
+) -> Vec<u32> {
+    // without this feature, the device only allows what every GPU supports.
+    let adapter_specific = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&count| {
+            formats.iter().all(|&format| {
+                let features = match adapter_specific {
+                    true => adapter.get_texture_format_features(format),
+                    false => {
+                        format.guaranteed_format_features(device.features())
+                    }
+                };
+                features.flags.sample_count_supported(count)
+            })
+        })
+        .collect()
+}
+
+/// Replaces a sample count the GPU can't draw with by the closest one it
+/// can, or FXAA if it can't do MSAA at all.
+pub fn validate(mode: AntiAliasing, sample_counts: &[u32]) -> AntiAliasing {
+    let AntiAliasing::Msaa { samples } = mode else {
+        return mode;
+    };
+    if sample_counts.contains(&samples) {
+        return mode;
+    }
+    let fallback = sample_counts
+        .iter()
+        .copied()
+        .filter(|&count| count < samples)
+        .max()
+        .or(sample_counts.first().copied());
+    match fallback {
+        Some(count) => {
+            log::warn!("{samples}x MSAA isn't supported, using {count}x");
+            AntiAliasing::Msaa { samples: count }
+        }
+        None => {
+            log::warn!("MSAA isn't supported, using FXAA");
+            AntiAliasing::Fxaa
+        }
+    }
+}
+
+/// The mode after `mode` when switching through none, FXAA and MSAA with
+/// each supported sample count.
+pub fn next_mode(mode: AntiAliasing, sample_counts: &[u32]) -> AntiAliasing {
+    let mut modes = vec![AntiAliasing::None, AntiAliasing::Fxaa];
+    modes.extend(
+        sample_counts
+            .iter()
+            .map(|&samples| AntiAliasing::Msaa { samples }),
+    );
+    let index = modes
+        .iter()
+        .position(|&other| other == mode)
+        .map_or(0, |index| (index + 1) % modes.len());
+    modes[index]
+}
+
+/// The image the scene is drawn into with MSAA, resolved into the scene
+/// view by the last pass. `None` for a single sample.
+pub fn create_multisampled_target(
+    device: &wgpu::Device,
+    size: winit::dpi::PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count == 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled scene"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Smooths the finished image onto the screen.
+pub struct Fxaa {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    /// What the scene is drawn or tonemapped into before `draw`, recreated
+    /// with the window size.
+    input: Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Fxaa {
+    pub fn new(
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        color_format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("FXAA bind group layout"),
+                entries: &FXAA_LAYOUT,
+            },
+        );
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("FXAA pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let pipeline =
+            create_pipeline(device, &pipeline_layout, color_format)?;
+        let (input, bind_group) =
+            create_input(device, &bind_group_layout, size, color_format);
+        Ok(Self {
+            bind_group_layout,
+            pipeline_layout,
+            pipeline,
+            input,
+            bind_group,
+        })
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        (self.input, self.bind_group) = create_input(
+            device,
+            &self.bind_group_layout,
+            size,
+            self.input.texture().format(),
+        );
+    }
+
+    /// Where the image is drawn into before `draw`.
+    pub fn input_view(&self) -> &wgpu::TextureView {
+        self.input.view()
+    }
+
+    /// Recompiles `fxaa.wgsl`, see `set_pipeline`.
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+    ) -> Result<wgpu::RenderPipeline> {
+        create_pipeline(
+            device,
+            &self.pipeline_layout,
+            self.input.texture().format(),
+        )
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Draws the smoothed image into `view`.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        fullscreen_pass(encoder, view, &self.pipeline, &self.bind_group);
+    }
+}
+
+fn create_input(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    size: winit::dpi::PhysicalSize<u32>,
+    color_format: wgpu::TextureFormat,
+) -> (Texture, wgpu::BindGroup) {
+    let input = Texture::create_render_target(
+        device,
+        size.width,
+        size.height,
+        color_format,
+        Some("FXAA input"),
+    );
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("FXAA bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(input.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(input.sampler()),
+            },
+        ],
+    });
+    (input, bind_group)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+) -> Result<wgpu::RenderPipeline> {
+    let shader = load_shader_module(device, "fxaa", &[])?;
+    Ok(create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout,
+            shader: &shader,
+            vertex_entry: "vs_fullscreen",
+            fragment_entry: "fs_fxaa",
+            vertex_layouts: &[],
+            color_format: Some(color_format),
+            depth_format: None,
+            sample_count: 1,
+            blend: wgpu::BlendState::REPLACE,
+            depth_write_enabled: false,
+        },
+    ))
+}
@@ -0,0 +1,97 @@
+use super::texture_animation::TextureAnimation;
+use super::{Texture, TextureArray, TextureAtlas};
+use crate::settings::TextureBackend;
+
+/// Where a texture is found: the corners of its rectangle and the layer of
+/// the texture it is stored in.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureCoordinates {
+    pub start: glm::Vec2,
+    pub end: glm::Vec2,
+    pub layer: u32,
+}
+
+/// The block textures, either packed into an atlas or stored as layers of a
+/// texture array depending on `GraphicsSettings::texture_backend`.
+pub enum BlockTextures {
+    Atlas(TextureAtlas),
+    Array(TextureArray),
+}
+
+impl BlockTextures {
+    pub fn get_texture_coordinates(&self, index: u32) -> TextureCoordinates {
+        match self {
+            Self::Atlas(atlas) => {
+                let (start, end) = atlas.get_texture_coordinates(index);
+                TextureCoordinates {
+                    start,
+                    end,
+                    layer: 0,
+                }
+            }
+            Self::Array(_) => TextureCoordinates {
+                start: glm::vec2(0.0, 0.0),
+                end: glm::vec2(1.0, 1.0),
+                layer: index,
+            },
+        }
+    }
+
+    pub fn animations(&self) -> &[TextureAnimation] {
+        match self {
+            Self::Atlas(atlas) => atlas.animations(),
+            Self::Array(array) => array.animations(),
+        }
+    }
+
+    /// Pixel position and layer of the region a texture occupies, and its
+    /// size including any padding.
+    pub fn slot(&self, index: u32) -> (glm::UVec3, glm::UVec2) {
+        match self {
+            Self::Atlas(atlas) => {
+                let (slot, slot_size) = atlas.slot(index);
+                (glm::vec3(slot.x, slot.y, 0), slot_size)
+            }
+            Self::Array(array) => {
+                let size = array.layer_size();
+                (glm::vec3(0, 0, index), glm::vec2(size, size))
+            }
+        }
+    }
+
+    pub fn backend(&self) -> TextureBackend {
+        match self {
+            Self::Atlas(_) => TextureBackend::Atlas,
+            Self::Array(_) => TextureBackend::Array,
+        }
+    }
+
+    pub fn texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        label: Option<&str>,
+    ) -> Texture {
+        match self {
+            Self::Atlas(atlas) => atlas.texture(device, queue, label),
+            Self::Array(array) => array.texture(device, queue, label),
+        }
+    }
+}
+
+impl TextureBackend {
+    pub fn view_dimension(self) -> wgpu::TextureViewDimension {
+        match self {
+            Self::Atlas => wgpu::TextureViewDimension::D2,
+            Self::Array => wgpu::TextureViewDimension::D2Array,
+        }
+    }
+
+    /// Defines selecting how `main.wgsl` samples block textures.
+    pub fn shader_defines(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Atlas => &[],
+            Self::Array => &[("TEXTURE_ARRAY", "")],
+        }
+    }
+}
@@ -37,12 +37,23 @@ impl CameraController {
         self.position = postion
     }
 
+    pub fn position(&self) -> glm::Vec3 {
+        self.position
+    }
+
     pub fn set_rotation(&mut self, rotation: na::UnitQuaternion<f32>) {
         let angles = glm::quat_euler_angles(&rotation);
         self.pitch = angles.z;
         self.yaw = angles.x;
     }
 
+    /// Sets the yaw and pitch in radians, a positive pitch looks down.
+    pub fn set_angles(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch
+            .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+    }
+
     pub fn update(&mut self, camera: &mut dyn Camera) {
         let rotation =
             na::UnitQuaternion::from_euler_angles(self.pitch, self.yaw, 0.0);
@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+
+use chrono::Local;
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use image::RgbaImage;
+
+use crate::settings::SETTINGS;
+
+/// A copy of a texture into a buffer, recorded into an encoder that isn't
+/// submitted yet.
+pub struct TextureCopy {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row_length: u32,
+    bgra: bool,
+}
+
+impl TextureCopy {
+    /// Records copying an 8 bit RGBA or BGRA texture with `COPY_SRC` usage.
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Result<Self> {
+        let bgra = match texture.format() {
+            wgpu::TextureFormat::Rgba8Unorm
+            | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm
+            | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => bail!("Can't read back textures of format {format:?}"),
+        };
+        let (width, height) = (texture.width(), texture.height());
+        // rows of a buffer copy have to be aligned.
+        let padded_row_length = (width * 4)
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture readback buffer"),
+            size: (padded_row_length * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_length),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        Ok(Self {
+            buffer,
+            width,
+            height,
+            padded_row_length,
+            bgra,
+        })
+    }
+
+    /// Starts mapping the buffer, the encoder with the copy has to be
+    /// submitted before.
+    pub fn map(self) -> PendingCapture {
+        let (sender, receiver) = mpsc::channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        PendingCapture {
+            copy: self,
+            mapped: receiver,
+        }
+    }
+}
+
+/// A texture copy waiting for its buffer to be mapped.
+pub struct PendingCapture {
+    copy: TextureCopy,
+    mapped: Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl PendingCapture {
+    /// Blocks until the copy is done. The mapping only finishes while the
+    /// device is polled, so this has to run on another thread than the
+    /// renderer or after `wgpu::Maintain::Wait`.
+    pub fn wait(self) -> Result<RgbaImage> {
+        let copy = self.copy;
+        self.mapped
+            .recv()?
+            .wrap_err("Failure mapping the readback buffer")?;
+        let row_length = (copy.width * 4) as usize;
+        let mut data = Vec::with_capacity(row_length * copy.height as usize);
+        for row in copy
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(copy.padded_row_length as usize)
+        {
+            data.extend_from_slice(&row[..row_length]);
+        }
+        copy.buffer.unmap();
+        if copy.bgra {
+            data.chunks_exact_mut(4).for_each(|x| x.swap(0, 2));
+        }
+        Ok(RgbaImage::from_raw(copy.width, copy.height, data).unwrap())
+    }
+}
+
+/// Copies a texture back to the CPU, waiting for the GPU to finish.
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<RgbaImage> {
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture readback encoder"),
+        });
+    let copy = TextureCopy::new(device, &mut encoder, texture)?;
+    queue.submit(std::iter::once(encoder.finish()));
+    let capture = copy.map();
+    device.poll(wgpu::Maintain::Wait);
+    capture.wait()
+}
+
+/// A free path like `screenshots/2024-01-31_18.30.00.png`.
+fn screenshot_path() -> Result<PathBuf> {
+    let directory = &SETTINGS.screenshots.directory;
+    std::fs::create_dir_all(directory)
+        .wrap_err_with(|| format!("Failure creating {directory:?}"))?;
+    let name = Local::now().format("%Y-%m-%d_%H.%M.%S").to_string();
+    let mut path = directory.join(format!("{name}.png"));
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = directory.join(format!("{name}_{count}.png"));
+    }
+    Ok(path)
+}
+
+fn stitch_and_save(
+    width: u32,
+    height: u32,
+    tiles: Vec<(glm::UVec2, PendingCapture)>,
+) -> Result<PathBuf> {
+    let mut image = RgbaImage::new(width, height);
+    for (position, tile) in tiles {
+        image::imageops::replace(
+            &mut image,
+            &tile.wait()?,
+            position.x as i64,
+            position.y as i64,
+        );
+    }
+    let path = screenshot_path()?;
+    image
+        .save(&path)
+        .wrap_err_with(|| format!("Failure saving {path:?}"))?;
+    Ok(path)
+}
+
+/// Waits for the captured tiles on a background thread, puts them together
+/// at their pixel positions in an image of `width` x `height` and saves it
+/// as a timestamped screenshot.
+pub fn save_screenshot(
+    width: u32,
+    height: u32,
+    tiles: Vec<(glm::UVec2, PendingCapture)>,
+) {
+    std::thread::spawn(move || match stitch_and_save(width, height, tiles) {
+        Ok(path) => log::info!("Saved screenshot to {path:?}"),
+        Err(error) => log::error!("Failed to save screenshot:\n{error:?}"),
+    });
+}
@@ -0,0 +1,87 @@
+/// How far the sun's path is tilted south, in radians.
+const SUN_TILT: f32 = 0.4;
+const DAY_AMBIENT: f32 = 0.15;
+const NIGHT_AMBIENT: f32 = 0.04;
+
+fn smoothstep(edge_0: f32, edge_1: f32, x: f32) -> f32 {
+    let t = ((x - edge_0) / (edge_1 - edge_0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Sun, moon, sky and the light they cast at a time of day.
+pub struct Daylight {
+    pub sun_direction: glm::Vec3,
+    pub moon_direction: glm::Vec3,
+    /// Towards the sun during the day and the moon at night.
+    pub light_direction: glm::Vec3,
+    pub light_color: glm::Vec3,
+    pub ambient: f32,
+    pub zenith_color: glm::Vec3,
+    pub horizon_color: glm::Vec3,
+    /// How strongly the sky around the sun glows at sunrise and sunset.
+    pub sun_glow: f32,
+    pub star_visibility: f32,
+    /// Turns a direction in the sky into the rotating frame of the stars,
+    /// so they move across the sky with the sun.
+    pub star_rotation: glm::Mat4,
+}
+
+impl Daylight {
+    /// `time_of_day` goes from 0 to 1, see `WorldClock::time_of_day`.
+    pub fn new(time_of_day: f32) -> Self {
+        let angle = time_of_day * std::f32::consts::TAU;
+        // rises in the east, sets in the west and passes south of the
+        // zenith. The moon is always opposite.
+        let axis = glm::vec3(0.0, SUN_TILT.sin(), SUN_TILT.cos());
+        let sun_direction =
+            glm::rotate_vec3(&glm::vec3(1.0, 0.0, 0.0), angle, &axis);
+        let moon_direction = -sun_direction;
+        let sun_height = sun_direction.y;
+
+        // light fades out while the sun or moon sinks below the horizon
+        // and turns orange around sunrise and sunset.
+        let sun_strength = smoothstep(-0.05, 0.15, sun_height);
+        let moon_strength = smoothstep(-0.05, 0.15, -sun_height);
+        let dawn = 1.0 - smoothstep(0.0, 0.4, sun_height.abs());
+        let sun_color = glm::mix(
+            &glm::vec3(1.5, 1.5, 1.5),
+            &glm::vec3(1.4, 0.7, 0.35),
+            dawn,
+        ) * sun_strength;
+        let moon_color = glm::vec3(0.25, 0.3, 0.45) * moon_strength;
+        let (light_direction, light_color) = match sun_height >= 0.0 {
+            true => (sun_direction, sun_color),
+            false => (moon_direction, moon_color),
+        };
+        let day = smoothstep(-0.2, 0.3, sun_height);
+        let ambient = NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * day;
+
+        let zenith_color = glm::mix(
+            &glm::vec3(0.005, 0.008, 0.02),
+            &glm::vec3(0.25, 0.45, 0.85),
+            day,
+        );
+        let horizon_color = glm::mix(
+            &glm::mix(
+                &glm::vec3(0.02, 0.03, 0.06),
+                &glm::vec3(0.65, 0.8, 0.95),
+                day,
+            ),
+            &glm::vec3(0.9, 0.5, 0.25),
+            dawn * smoothstep(-0.3, 0.0, sun_height),
+        );
+
+        Self {
+            sun_direction,
+            moon_direction,
+            light_direction,
+            light_color,
+            ambient,
+            zenith_color,
+            horizon_color,
+            sun_glow: dawn * smoothstep(-0.2, 0.0, sun_height),
+            star_visibility: 1.0 - smoothstep(-0.25, 0.05, sun_height),
+            star_rotation: glm::rotation(-angle, &axis),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use wgpu::util::DeviceExt;
+
+use crate::settings::{FogMode, FogParameters, SETTINGS};
+use crate::world::{GameData, HeightmapType, World};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FogUniform {
+    color: glm::Vec3,
+    /// 1 blends into the sky in the view direction instead of `color`.
+    sky_blend: f32,
+    start: f32,
+    end: f32,
+    /// 0 for linear fog.
+    density: f32,
+    /// 0 when fog is turned off.
+    amount: f32,
+}
+
+impl FogUniform {
+    fn new(parameters: &FogParameters) -> Self {
+        let render_distance = SETTINGS.graphics.render_distance as f32;
+        let start = parameters.start * render_distance;
+        Self {
+            color: parameters.color.map_or(glm::vec3(0.0, 0.0, 0.0), |x| {
+                glm::vec3(x[0], x[1], x[2])
+            }),
+            sky_blend: match parameters.color {
+                Some(_) => 0.0,
+                None => 1.0,
+            },
+            start,
+            // keeps the shader from dividing by 0.
+            end: (parameters.end * render_distance).max(start + 0.01),
+            density: match parameters.mode {
+                FogMode::Linear => 0.0,
+                FogMode::Exponential { density } => density.max(0.0),
+            },
+            amount: match SETTINGS.graphics.fog.enabled {
+                true => 1.0,
+                false => 0.0,
+            },
+        }
+    }
+}
+
+/// Where the camera is, each has its own `FogParameters`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Surroundings {
+    Surface,
+    Cave,
+    Underwater,
+}
+
+impl Surroundings {
+    fn fog_parameters(self) -> &'static FogParameters {
+        let fog = &SETTINGS.graphics.fog;
+        match self {
+            Self::Surface => &fog.surface,
+            Self::Cave => &fog.cave,
+            Self::Underwater => &fog.underwater,
+        }
+    }
+}
+
+/// Distance fog hiding the end of the loaded terrain, bound together with
+/// the sky uniform it blends into.
+pub struct Fog {
+    surroundings: Surroundings,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// The block type of water, if the game has one.
+    water: Option<u16>,
+}
+
+impl Fog {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sky_uniform_buffer: &wgpu::Buffer,
+        game_data: &GameData,
+    ) -> Self {
+        let surroundings = Surroundings::Surface;
+        let uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Fog uniform"),
+                usage: wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(&[FogUniform::new(
+                    surroundings.fog_parameters(),
+                )]),
+            });
+        let bind_group =
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Fog bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: sky_uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+        Self {
+            surroundings,
+            uniform_buffer,
+            bind_group,
+            water: game_data.block_id("water").ok(),
+        }
+    }
+
+    /// Switches to the fog of the surroundings at `camera_position`.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        world: &World,
+        camera_position: glm::Vec3,
+    ) {
+        let surroundings = self.surroundings_at(world, camera_position);
+        if surroundings != self.surroundings {
+            self.surroundings = surroundings;
+            queue.write_buffer(
+                &self.uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[FogUniform::new(
+                    surroundings.fog_parameters(),
+                )]),
+            );
+        }
+    }
+
+    fn surroundings_at(
+        &self,
+        world: &World,
+        position: glm::Vec3,
+    ) -> Surroundings {
+        let position = position.map(|x| x.floor() as i32);
+        let in_water = self.water.is_some_and(|water| {
+            World::position_in_world(position)
+                && world
+                    .get_block(position)
+                    .is_some_and(|block| block.block_type() == water)
+        });
+        if in_water {
+            return Surroundings::Underwater;
+        }
+        // a roof above the camera blocks out the sky, a torch or flowers on
+        // a ledge above it don't.
+        let covered = world
+            .get_height(HeightmapType::MotionBlocking, position.x, position.z)
+            .is_some_and(|height| position.y + 1 < height as i32);
+        match covered {
+            true => Surroundings::Cave,
+            false => Surroundings::Surface,
+        }
+    }
+
+    /// Whether the camera was inside water at the last `update`.
+    pub fn underwater(&self) -> bool {
+        self.surroundings == Surroundings::Underwater
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
@@ -1,19 +1,23 @@
+use super::block_textures::BlockTextures;
 use super::model::BlockMeshGenerator;
-use super::TextureAtlas;
+use super::SkyTextures;
 
 pub struct GameRenderData {
     mesh_generators: Vec<Box<dyn BlockMeshGenerator>>,
-    texture_atlas: TextureAtlas,
+    block_textures: BlockTextures,
+    sky_textures: SkyTextures,
 }
 
 impl GameRenderData {
     pub fn new(
         mesh_generators: Vec<Box<dyn BlockMeshGenerator>>,
-        texture_atlas: TextureAtlas,
+        block_textures: BlockTextures,
+        sky_textures: SkyTextures,
     ) -> Self {
         Self {
             mesh_generators,
-            texture_atlas,
+            block_textures,
+            sky_textures,
         }
     }
 
@@ -21,7 +25,11 @@ impl GameRenderData {
         self.mesh_generators[block_type as usize].as_ref()
     }
 
-    pub fn texture_atlas(&self) -> &TextureAtlas {
-        &self.texture_atlas
+    pub fn block_textures(&self) -> &BlockTextures {
+        &self.block_textures
+    }
+
+    pub fn sky_textures(&self) -> &SkyTextures {
+        &self.sky_textures
     }
 }
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+use color_eyre::eyre::{bail, eyre, Context};
+use color_eyre::Result;
+
+use super::capture::read_texture;
+use super::{GameRenderData, RenderTarget, RenderWorldEvent, Renderer};
+use crate::world::{ChunkEvent, GameData, World, WorldClock};
+
+/// What the `render` command draws and where it is saved.
+pub struct HeadlessOptions {
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub position: glm::Vec3,
+    /// Degrees, 0 looks north and 90 east.
+    pub yaw: f32,
+    /// Degrees, positive looks up.
+    pub pitch: f32,
+    /// `WorldClock` ticks, the saved time is used if this isn't set.
+    pub time: Option<u64>,
+}
+
+/// Parses a list of numbers like `1,2.5,3`.
+fn parse_numbers<const N: usize>(
+    value: &str,
+    separator: char,
+) -> Result<[f32; N]> {
+    let numbers = value
+        .split(separator)
+        .map(|x| x.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err_with(|| format!("Invalid number in \"{value}\""))?;
+    numbers.try_into().map_err(|_| {
+        eyre!("Expected {N} values separated by '{separator}': \"{value}\"")
+    })
+}
+
+impl HeadlessOptions {
+    /// Parses `<output.png> [--size WxH] [--position X,Y,Z]
+    /// [--rotation YAW,PITCH] [--time TICKS]`.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut options = Self {
+            output: args
+                .next()
+                .ok_or_else(|| eyre!("Missing the output path"))?
+                .into(),
+            width: 1280,
+            height: 720,
+            position: glm::vec3(0.0, 80.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            time: None,
+        };
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| eyre!("Missing the value of {flag}"))?;
+            match flag.as_str() {
+                "--size" => {
+                    let [width, height] = parse_numbers(&value, 'x')?;
+                    if width < 1.0 || height < 1.0 {
+                        bail!("The image has to be at least 1x1 pixels");
+                    }
+                    (options.width, options.height) =
+                        (width as u32, height as u32);
+                }
+                "--position" => {
+                    options.position = parse_numbers(&value, ',')?.into();
+                }
+                "--rotation" => {
+                    [options.yaw, options.pitch] = parse_numbers(&value, ',')?;
+                }
+                "--time" => {
+                    options.time =
+                        Some(value.parse().wrap_err_with(|| {
+                            format!("Invalid number of ticks \"{value}\"")
+                        })?);
+                }
+                _ => bail!("Unknown option {flag}"),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Renders a single frame into a PNG without opening a window, once every
+/// chunk the game sends before closing `chunk_receiver` is meshed.
+pub fn run_headless(
+    options: HeadlessOptions,
+    render_data: GameRenderData,
+    game_data: Arc<GameData>,
+    chunk_receiver: Receiver<ChunkEvent>,
+    world: Arc<Mutex<World>>,
+    clock: Arc<WorldClock>,
+) -> Result<()> {
+    if let Some(time) = options.time {
+        clock.set_ticks(time);
+    }
+    let size = winit::dpi::PhysicalSize::new(options.width, options.height);
+    let (mut renderer, mut render_world, _) =
+        Renderer::new_headless(size, render_data, game_data, world, clock)?;
+    render_world
+        .handle_events(chunk_receiver.iter().map(RenderWorldEvent::Chunk));
+    renderer.receive_chunk_meshes();
+
+    renderer.set_view(options.position, options.yaw, options.pitch);
+    renderer.update();
+    renderer.render()?;
+    let RenderTarget::Texture(texture) = &renderer.target else {
+        unreachable!("headless renderers draw into a texture");
+    };
+    let image = read_texture(&renderer.device, &renderer.queue, texture)?;
+    image.save(&options.output).wrap_err_with(|| {
+        format!("Failure saving the image to {:?}", options.output)
+    })?;
+    log::info!("Saved the rendered image to {:?}", options.output);
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use strum::IntoEnumIterator;
+
+use crate::settings::TextureBackend;
+
+pub const CAMERA_LAYOUT: [wgpu::BindGroupLayoutEntry; 1] =
+    [wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }];
+
+/// The light uniform, the shadow uniform, the shadow map and its sampler,
+/// and the ambient occlusion.
+pub const LIGHT_LAYOUT: [wgpu::BindGroupLayoutEntry; 5] = [
+    fragment_uniform_entry(0),
+    fragment_uniform_entry(1),
+    wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            multisampled: false,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+        count: None,
+    },
+    texture_2d_entry(4),
+];
+
+/// The view projection of one shadow cascade.
+pub const SHADOW_CASCADE_LAYOUT: [wgpu::BindGroupLayoutEntry; 1] =
+    [wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }];
+
+pub const SKY_LAYOUT: [wgpu::BindGroupLayoutEntry; 1] =
+    [wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }];
+
+const fn fragment_uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// The sky uniform the fog blends into and the fog uniform.
+pub const FOG_LAYOUT: [wgpu::BindGroupLayoutEntry; 2] =
+    [fragment_uniform_entry(0), fragment_uniform_entry(1)];
+
+const fn texture_2d_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+/// The sun and moon textures and their sampler.
+pub const SKY_TEXTURE_LAYOUT: [wgpu::BindGroupLayoutEntry; 3] = [
+    texture_2d_entry(0),
+    texture_2d_entry(1),
+    wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+/// The post processing uniform, the image a pass reads, a second image
+/// like the bloom and their sampler.
+pub const POST_PROCESSING_LAYOUT: [wgpu::BindGroupLayoutEntry; 4] = [
+    fragment_uniform_entry(0),
+    texture_2d_entry(1),
+    texture_2d_entry(2),
+    wgpu::BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+/// The image FXAA smooths and its sampler.
+pub const FXAA_LAYOUT: [wgpu::BindGroupLayoutEntry; 2] = [
+    texture_2d_entry(0),
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+/// The SSAO uniform, the normals and depth of the terrain and the
+/// occlusion a pass reads.
+pub const SSAO_LAYOUT: [wgpu::BindGroupLayoutEntry; 3] = [
+    fragment_uniform_entry(0),
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    texture_2d_entry(2),
+];
+
+/// The water uniform and the normals and depth of the terrain the water
+/// absorbs the light from.
+pub const WATER_LAYOUT: [wgpu::BindGroupLayoutEntry; 2] = [
+    fragment_uniform_entry(0),
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+];
+
+/// The water uniform, the image seen from underwater and its sampler.
+pub const UNDERWATER_LAYOUT: [wgpu::BindGroupLayoutEntry; 3] = [
+    fragment_uniform_entry(0),
+    texture_2d_entry(1),
+    wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+pub fn block_texture_layout(
+    backend: TextureBackend,
+) -> [wgpu::BindGroupLayoutEntry; 2] {
+    [
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float {
+                    filterable: true,
+                },
+                view_dimension: backend.view_dimension(),
+                multisampled: false,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(
+                wgpu::SamplerBindingType::Filtering,
+            ),
+            count: None,
+        },
+    ]
+}
+
+/// A shader as the renderer loads it, with the defines and the bind group
+/// layouts of its pipeline.
+pub struct ShaderPipeline {
+    pub shader_name: &'static str,
+    pub defines: &'static [(&'static str, &'static str)],
+    pub bind_groups: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+}
+
+/// Every way the renderer uses a shader, so they can be checked without a
+/// device. Keep this in sync when adding pipelines.
+pub fn shader_pipelines() -> Vec<ShaderPipeline> {
+    let mut pipelines = TextureBackend::iter()
+        .map(|backend| ShaderPipeline {
+            shader_name: "main",
+            defines: backend.shader_defines(),
+            bind_groups: vec![
+                CAMERA_LAYOUT.to_vec(),
+                block_texture_layout(backend).to_vec(),
+                LIGHT_LAYOUT.to_vec(),
+                FOG_LAYOUT.to_vec(),
+            ],
+        })
+        .collect::<Vec<_>>();
+    pipelines.extend(TextureBackend::iter().map(|backend| ShaderPipeline {
+        shader_name: "shadow",
+        defines: backend.shader_defines(),
+        bind_groups: vec![
+            SHADOW_CASCADE_LAYOUT.to_vec(),
+            block_texture_layout(backend).to_vec(),
+        ],
+    }));
+    pipelines.extend(TextureBackend::iter().map(|backend| ShaderPipeline {
+        shader_name: "normals",
+        defines: backend.shader_defines(),
+        bind_groups: vec![
+            CAMERA_LAYOUT.to_vec(),
+            block_texture_layout(backend).to_vec(),
+        ],
+    }));
+    pipelines.push(ShaderPipeline {
+        shader_name: "ssao",
+        defines: &[],
+        bind_groups: vec![SSAO_LAYOUT.to_vec()],
+    });
+    pipelines.push(ShaderPipeline {
+        shader_name: "water",
+        defines: &[],
+        bind_groups: vec![
+            CAMERA_LAYOUT.to_vec(),
+            WATER_LAYOUT.to_vec(),
+            LIGHT_LAYOUT.to_vec(),
+            FOG_LAYOUT.to_vec(),
+        ],
+    });
+    pipelines.push(ShaderPipeline {
+        shader_name: "underwater",
+        defines: &[],
+        bind_groups: vec![UNDERWATER_LAYOUT.to_vec()],
+    });
+    pipelines.push(ShaderPipeline {
+        shader_name: "sky",
+        defines: &[],
+        bind_groups: vec![
+            CAMERA_LAYOUT.to_vec(),
+            SKY_LAYOUT.to_vec(),
+            SKY_TEXTURE_LAYOUT.to_vec(),
+        ],
+    });
+    pipelines.push(ShaderPipeline {
+        shader_name: "post_processing",
+        defines: &[],
+        bind_groups: vec![POST_PROCESSING_LAYOUT.to_vec()],
+    });
+    pipelines.push(ShaderPipeline {
+        shader_name: "fxaa",
+        defines: &[],
+        bind_groups: vec![FXAA_LAYOUT.to_vec()],
+    });
+    pipelines.push(ShaderPipeline {
+        shader_name: "warning_overlay",
+        defines: &[],
+        bind_groups: vec![],
+    });
+    pipelines
+}
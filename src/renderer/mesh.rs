@@ -8,10 +8,15 @@ pub struct Vertex {
     pub position: glm::Vec3,
     pub tex_coords: glm::Vec2,
     pub normal: glm::Vec3,
+    /// Layer of the block texture array, always 0 with the atlas.
+    pub layer: u32,
+    /// The block light level in the lowest 4 bits, followed by 4 bits
+    /// each of skylight and the light the block emits itself.
+    pub light: u32,
 }
 
 impl Vertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3];
+    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 3 => Uint32, 4 => Uint32];
 
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
@@ -85,6 +90,43 @@ impl Mesh {
         self.indices.push(base_index + 3);
     }
 
+    /// Sets the light of the vertices added since there were `start`
+    /// vertices.
+    pub fn set_light(&mut self, start: usize, light: u32) {
+        for vertex in &mut self.vertices[start..] {
+            vertex.light = light;
+        }
+    }
+
+    /// Orders the triangles from the furthest to the closest one as seen
+    /// from `position`, so blended geometry is composed correctly.
+    pub fn sort_back_to_front(&mut self, position: glm::Vec3) {
+        let mut triangles = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let center = triangle
+                    .iter()
+                    .map(|index| self.vertices[*index as usize].position)
+                    .sum::<glm::Vec3>()
+                    / 3.0;
+                (
+                    glm::distance2(&center, &position),
+                    [triangle[0], triangle[1], triangle[2]],
+                )
+            })
+            .collect::<Vec<_>>();
+        triangles.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.indices = triangles
+            .into_iter()
+            .flat_map(|(_, triangle)| triangle)
+            .collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
     pub fn update_buffers(
         &mut self,
         device: &wgpu::Device,
@@ -97,6 +139,14 @@ impl Mesh {
             queue,
             wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
         );
+        self.update_index_buffer(device, queue);
+    }
+
+    pub fn update_index_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
         update_buffer(
             &mut self.index_buffer,
             bytemuck::cast_slice(&self.indices),
@@ -118,6 +168,10 @@ impl Mesh {
             .wrap_err("No index buffer, call update_buffers() first!")
     }
 
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
     pub fn index_count(&self) -> u32 {
         self.indices.len() as u32
     }
@@ -1,16 +1,46 @@
+mod connected_block;
+mod cross_block;
 mod cube_block;
 mod invisible_block;
+mod json_block;
+mod mesh_context;
+mod water_block;
 
+pub use connected_block::ConnectedBlock;
+pub use cross_block::{CrossBlock, CrossOffset};
 pub use cube_block::CubeBlock;
 pub use invisible_block::InvisibleBlock;
+pub use json_block::JsonBlock;
+pub use mesh_context::MeshContext;
+pub use water_block::WaterBlock;
 
-use super::{Mesh, TextureAtlas};
+use super::block_textures::BlockTextures;
+use super::Mesh;
 use crate::util::BlockSide;
 use crate::world::Block;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderLayer {
+    /// Fully opaque geometry.
+    Opaque,
+    /// Opaque geometry with fully transparent holes, drawn together with
+    /// the opaque layer.
+    Cutout,
+    /// Blended geometry, drawn after the opaque layers back to front.
+    Translucent,
+    /// Water surfaces, drawn with their own material between the opaque
+    /// and translucent layers.
+    Water,
+}
+
 pub trait BlockMeshGenerator: Sync + Send {
+    fn render_layer(&self) -> RenderLayer {
+        RenderLayer::Opaque
+    }
+
     fn mesh_always(
         &self,
+        _context: &MeshContext,
         _position: glm::IVec3,
         _block: Block,
         _mesh: &mut Mesh,
@@ -19,6 +49,7 @@ pub trait BlockMeshGenerator: Sync + Send {
 
     fn mesh_side(
         &self,
+        context: &MeshContext,
         position: glm::IVec3,
         block: Block,
         side: BlockSide,
@@ -28,5 +59,5 @@ pub trait BlockMeshGenerator: Sync + Send {
 }
 
 pub trait InitBlockMeshGenerator {
-    fn build(&self, atlas: &TextureAtlas) -> Box<dyn BlockMeshGenerator>;
+    fn build(&self, textures: &BlockTextures) -> Box<dyn BlockMeshGenerator>;
 }
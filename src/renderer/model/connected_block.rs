@@ -0,0 +1,313 @@
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+
+use super::cube_block::{add_cube_face, same_block_adjacent};
+use super::{
+    BlockMeshGenerator,
+    InitBlockMeshGenerator,
+    Mesh,
+    MeshContext,
+    RenderLayer,
+};
+use crate::init::InitTextureProvider;
+use crate::renderer::block_textures::{BlockTextures, TextureCoordinates};
+use crate::util::BlockSide;
+use crate::world::Block;
+
+const SHEET_COLUMNS: u32 = 12;
+const SHEET_ROWS: u32 = 4;
+const TILE_COUNT: usize = 47;
+
+const UP: u8 = 1;
+const RIGHT: u8 = 1 << 1;
+const DOWN: u8 = 1 << 2;
+const LEFT: u8 = 1 << 3;
+const UP_RIGHT: u8 = 1 << 4;
+const DOWN_RIGHT: u8 = 1 << 5;
+const DOWN_LEFT: u8 = 1 << 6;
+const UP_LEFT: u8 = 1 << 7;
+
+/// A corner only matters if both edges next to it are connected, which
+/// leaves 47 distinct tiles out of the 256 possible neighbor combinations.
+fn reduce_mask(mask: u8) -> u8 {
+    let mut reduced = mask & (UP | RIGHT | DOWN | LEFT);
+    for (corner, a, b) in [
+        (UP_RIGHT, UP, RIGHT),
+        (DOWN_RIGHT, DOWN, RIGHT),
+        (DOWN_LEFT, DOWN, LEFT),
+        (UP_LEFT, UP, LEFT),
+    ] {
+        if mask & corner != 0 && mask & a != 0 && mask & b != 0 {
+            reduced |= corner;
+        }
+    }
+    reduced
+}
+
+/// The tile of OptiFine's `ctm` method for a reduced mask.
+fn ctm_tile(mask: u8) -> u8 {
+    let has = |bit: u8| mask & bit != 0;
+    match (has(UP), has(RIGHT), has(DOWN), has(LEFT)) {
+        (false, false, false, false) => 0,
+        (false, true, false, false) => 1,
+        (false, true, false, true) => 2,
+        (false, false, false, true) => 3,
+        (false, false, true, false) => 12,
+        (true, false, true, false) => 24,
+        (true, false, false, false) => 36,
+        (false, true, true, false) => match has(DOWN_RIGHT) {
+            true => 13,
+            false => 4,
+        },
+        (false, false, true, true) => match has(DOWN_LEFT) {
+            true => 15,
+            false => 5,
+        },
+        (true, true, false, false) => match has(UP_RIGHT) {
+            true => 37,
+            false => 16,
+        },
+        (true, false, false, true) => match has(UP_LEFT) {
+            true => 39,
+            false => 17,
+        },
+        (false, true, true, true) => match (has(DOWN_RIGHT), has(DOWN_LEFT)) {
+            (true, true) => 14,
+            (true, false) => 31,
+            (false, true) => 29,
+            (false, false) => 7,
+        },
+        (true, true, true, false) => match (has(DOWN_RIGHT), has(UP_RIGHT)) {
+            (true, true) => 25,
+            (true, false) => 28,
+            (false, true) => 30,
+            (false, false) => 6,
+        },
+        (true, false, true, true) => match (has(DOWN_LEFT), has(UP_LEFT)) {
+            (true, true) => 27,
+            (true, false) => 43,
+            (false, true) => 41,
+            (false, false) => 19,
+        },
+        (true, true, false, true) => match (has(UP_RIGHT), has(UP_LEFT)) {
+            (true, true) => 38,
+            (true, false) => 40,
+            (false, true) => 42,
+            (false, false) => 18,
+        },
+        // connected on all edges, indexed by the corners as the bits of up
+        // right, down right, down left and up left.
+        (true, true, true, true) => {
+            [46, 8, 9, 23, 21, 34, 22, 45, 20, 11, 35, 33, 10, 32, 44, 26]
+                [(mask >> 4) as usize]
+        }
+    }
+}
+
+/// Maps a neighbor mask to its tile in the order of OptiFine's `ctm`
+/// method.
+static TILE_INDEX: Lazy<[u8; 256]> = Lazy::new(|| {
+    std::array::from_fn(|mask| ctm_tile(reduce_mask(mask as u8)))
+});
+
+/// The directions that are right and up on the texture of a cube side.
+fn face_axes(side: BlockSide) -> (glm::IVec3, glm::IVec3) {
+    match side {
+        BlockSide::Top => (glm::vec3(1, 0, 0), glm::vec3(0, 0, 1)),
+        BlockSide::Bottom => (glm::vec3(1, 0, 0), glm::vec3(0, 0, -1)),
+        BlockSide::North => (glm::vec3(-1, 0, 0), glm::vec3(0, 1, 0)),
+        BlockSide::East => (glm::vec3(0, 0, 1), glm::vec3(0, 1, 0)),
+        BlockSide::South => (glm::vec3(1, 0, 0), glm::vec3(0, 1, 0)),
+        BlockSide::West => (glm::vec3(0, 0, -1), glm::vec3(0, 1, 0)),
+    }
+}
+
+/// A full cube whose side textures connect to neighboring blocks of the
+/// same type.
+pub struct ConnectedBlock {
+    tiles: Vec<TextureCoordinates>,
+    render_layer: RenderLayer,
+}
+
+impl ConnectedBlock {
+    /// The sheet contains the 47 tiles of OptiFine's `ctm` method in a
+    /// grid of 12x4 tiles, numbered row by row from the top left like the
+    /// `0.png` to `46.png` of a CTM pack, the last cell is unused. A tile is
+    /// chosen by the neighbors of the same block on the face, where corners
+    /// only count if both adjacent edges are connected.
+    pub fn new(
+        sheet_path: &Path,
+        render_layer: RenderLayer,
+        texture_provider: &mut InitTextureProvider,
+    ) -> InitConnectedBlock {
+        InitConnectedBlock::new(sheet_path, render_layer, texture_provider)
+    }
+
+    fn connection_mask(
+        &self,
+        context: &MeshContext,
+        position: glm::IVec3,
+        block: Block,
+        side: BlockSide,
+    ) -> u8 {
+        let normal = side.direction::<i32>();
+        let is_same = |position| {
+            context
+                .get_block(position)
+                .is_some_and(|x| x.block_type() == block.block_type())
+        };
+        neighbor_mask(side, |offset| {
+            // a neighbor covered on this side doesn't show its face.
+            is_same(position + offset) && !is_same(position + offset + normal)
+        })
+    }
+}
+
+/// The mask of the neighbors in the plane of `side` that `connected`
+/// accepts, given their offset from the block.
+fn neighbor_mask(
+    side: BlockSide,
+    connected: impl Fn(glm::IVec3) -> bool,
+) -> u8 {
+    let (right, up) = face_axes(side);
+    [
+        (UP, up),
+        (RIGHT, right),
+        (DOWN, -up),
+        (LEFT, -right),
+        (UP_RIGHT, up + right),
+        (DOWN_RIGHT, right - up),
+        (DOWN_LEFT, -up - right),
+        (UP_LEFT, up - right),
+    ]
+    .into_iter()
+    .filter(|(_, offset)| connected(*offset))
+    .fold(0, |mask, (bit, _)| mask | bit)
+}
+
+impl BlockMeshGenerator for ConnectedBlock {
+    fn render_layer(&self) -> RenderLayer {
+        self.render_layer
+    }
+
+    fn mesh_side(
+        &self,
+        context: &MeshContext,
+        position: glm::IVec3,
+        block: Block,
+        side: BlockSide,
+        mesh: &mut Mesh,
+    ) {
+        if self.render_layer != RenderLayer::Opaque
+            && same_block_adjacent(context, position, block, side)
+        {
+            return;
+        }
+        let mask = self.connection_mask(context, position, block, side);
+        let tile = self.tiles[TILE_INDEX[mask as usize] as usize];
+        add_cube_face(mesh, position, side, tile);
+    }
+
+    fn solid_side(&self, _: Block, _: BlockSide) -> bool {
+        self.render_layer == RenderLayer::Opaque
+    }
+}
+
+pub struct InitConnectedBlock {
+    tiles: Vec<u32>,
+    render_layer: RenderLayer,
+}
+
+impl InitConnectedBlock {
+    fn new(
+        sheet_path: &Path,
+        render_layer: RenderLayer,
+        texture_provider: &mut InitTextureProvider,
+    ) -> Self {
+        let mut tiles = texture_provider.texture_sheet(
+            sheet_path,
+            SHEET_COLUMNS,
+            SHEET_ROWS,
+        );
+        tiles.truncate(TILE_COUNT);
+        Self {
+            tiles,
+            render_layer,
+        }
+    }
+}
+
+impl InitBlockMeshGenerator for InitConnectedBlock {
+    fn build(&self, textures: &BlockTextures) -> Box<dyn BlockMeshGenerator> {
+        Box::new(ConnectedBlock {
+            tiles: self
+                .tiles
+                .iter()
+                .map(|tile| textures.get_texture_coordinates(*tile))
+                .collect(),
+            render_layer: self.render_layer,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The tile of a face with same blocks at `neighbors`.
+    fn tile(side: BlockSide, neighbors: &[glm::IVec3]) -> u8 {
+        TILE_INDEX[neighbor_mask(side, |x| neighbors.contains(&x)) as usize]
+    }
+
+    #[test]
+    fn every_tile_is_used() {
+        let mut tiles = TILE_INDEX.to_vec();
+        tiles.sort_unstable();
+        tiles.dedup();
+        assert_eq!(tiles, (0..TILE_COUNT as u8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn edges_follow_the_face() {
+        let east = glm::vec3(1, 0, 0);
+        let above = glm::vec3(0, 1, 0);
+        assert_eq!(neighbor_mask(BlockSide::South, |x| x == east), RIGHT);
+        // seen from the north, east is on the left.
+        assert_eq!(neighbor_mask(BlockSide::North, |x| x == east), LEFT);
+        assert_eq!(neighbor_mask(BlockSide::East, |x| x == above), UP);
+        // on the top, north is up and east is right.
+        let north = glm::vec3(0, 0, 1);
+        assert_eq!(neighbor_mask(BlockSide::Top, |x| x == north), UP);
+        assert_eq!(neighbor_mask(BlockSide::Bottom, |x| x == north), DOWN);
+    }
+
+    #[test]
+    fn known_tiles() {
+        let east = glm::vec3(1, 0, 0);
+        let west = glm::vec3(-1, 0, 0);
+        let above = glm::vec3(0, 1, 0);
+        let below = glm::vec3(0, -1, 0);
+        assert_eq!(tile(BlockSide::South, &[]), 0);
+        // in the middle of a horizontal row: right and left.
+        assert_eq!(tile(BlockSide::South, &[east, west]), 2);
+        // the top of a pillar, connected below.
+        assert_eq!(tile(BlockSide::South, &[below]), 12);
+        // a lone diagonal neighbor doesn't connect.
+        assert_eq!(tile(BlockSide::South, &[east + above]), 0);
+        // the bottom left corner of a wall: up, right and up right.
+        assert_eq!(tile(BlockSide::South, &[east, above, east + above]), 37);
+        // the same without the up right corner.
+        assert_eq!(tile(BlockSide::South, &[east, above]), 16);
+        // a cross of edges without any corners.
+        assert_eq!(tile(BlockSide::South, &[east, west, above, below]), 46);
+        // the middle of a wall, connected everywhere.
+        let all = [east, west, above, below]
+            .into_iter()
+            .chain([east + above, east + below, west + above, west + below])
+            .collect::<Vec<_>>();
+        assert_eq!(tile(BlockSide::South, &all), 26);
+        // a hole in the down left corner only.
+        assert_eq!(tile(BlockSide::South, &all[..7]), 33);
+    }
+}
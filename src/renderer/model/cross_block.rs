@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use super::{
+    BlockMeshGenerator,
+    InitBlockMeshGenerator,
+    Mesh,
+    MeshContext,
+    RenderLayer,
+};
+use crate::init::InitTextureProvider;
+use crate::renderer::block_textures::{BlockTextures, TextureCoordinates};
+use crate::renderer::Vertex;
+use crate::util::BlockSide;
+use crate::world::Block;
+
+/// Inset of the diagonal quads, chosen so each quad is one block wide.
+const INSET: f32 = 0.5 - std::f32::consts::FRAC_1_SQRT_2 / 2.0;
+const MAX_HORIZONTAL_OFFSET: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug)]
+pub enum CrossOffset {
+    None,
+    Xz,
+    Xyz,
+}
+
+/// Same seed as vanilla uses for its random model offsets.
+fn position_seed(position: glm::IVec3) -> i64 {
+    let seed = (position.x.wrapping_mul(3129871) as i64)
+        ^ (position.z as i64).wrapping_mul(116129781)
+        ^ position.y as i64;
+    seed.wrapping_mul(seed)
+        .wrapping_mul(42317861)
+        .wrapping_add(seed.wrapping_mul(11))
+        >> 16
+}
+
+pub struct CrossBlock {
+    texture_coords: TextureCoordinates,
+    offset: CrossOffset,
+}
+
+impl CrossBlock {
+    pub fn new(
+        texture_path: &Path,
+        offset: CrossOffset,
+        texture_provider: &mut InitTextureProvider,
+    ) -> InitCrossBlock {
+        InitCrossBlock::new(texture_path, offset, texture_provider)
+    }
+
+    fn offset(&self, position: glm::IVec3) -> glm::Vec3 {
+        let seed = position_seed(glm::vec3(position.x, 0, position.z));
+        let random = |shift: i64| ((seed >> shift) & 15) as f32 / 15.0;
+        let horizontal = |shift: i64| {
+            ((random(shift) - 0.5) * 0.5)
+                .clamp(-MAX_HORIZONTAL_OFFSET, MAX_HORIZONTAL_OFFSET)
+        };
+        match self.offset {
+            CrossOffset::None => glm::vec3(0.0, 0.0, 0.0),
+            CrossOffset::Xz => glm::vec3(horizontal(0), 0.0, horizontal(8)),
+            CrossOffset::Xyz => glm::vec3(
+                horizontal(0),
+                (random(4) - 1.0) * 0.2,
+                horizontal(8),
+            ),
+        }
+    }
+}
+
+impl BlockMeshGenerator for CrossBlock {
+    fn render_layer(&self) -> RenderLayer {
+        RenderLayer::Cutout
+    }
+
+    fn mesh_always(
+        &self,
+        _: &MeshContext,
+        position: glm::IVec3,
+        _: Block,
+        mesh: &mut Mesh,
+    ) {
+        let origin =
+            glm::vec3(position.x as f32, position.y as f32, position.z as f32)
+                + self.offset(position);
+        let texture_top_right =
+            glm::vec2(self.texture_coords.end.x, self.texture_coords.start.y);
+        let texture_bottom_left =
+            glm::vec2(self.texture_coords.start.x, self.texture_coords.end.y);
+        let (near, far) = (INSET, 1.0 - INSET);
+        for (start, end) in [
+            (glm::vec2(near, near), glm::vec2(far, far)),
+            (glm::vec2(near, far), glm::vec2(far, near)),
+        ] {
+            // plants are lit like the top of a block, no matter which side
+            // of the quad is visible.
+            let vertex = |corner: glm::Vec2, y: f32, tex_coords| Vertex {
+                position: origin + glm::vec3(corner.x, y, corner.y),
+                tex_coords,
+                normal: BlockSide::Top.direction(),
+                layer: self.texture_coords.layer,
+                light: 0,
+            };
+            mesh.add_quad(
+                vertex(start, 0.0, texture_bottom_left),
+                vertex(start, 1.0, self.texture_coords.start),
+                vertex(end, 1.0, texture_top_right),
+                vertex(end, 0.0, self.texture_coords.end),
+            );
+            mesh.add_quad(
+                vertex(end, 0.0, texture_bottom_left),
+                vertex(end, 1.0, self.texture_coords.start),
+                vertex(start, 1.0, texture_top_right),
+                vertex(start, 0.0, self.texture_coords.end),
+            );
+        }
+    }
+
+    fn mesh_side(
+        &self,
+        _: &MeshContext,
+        _: glm::IVec3,
+        _: Block,
+        _: BlockSide,
+        _: &mut Mesh,
+    ) {
+    }
+
+    fn solid_side(&self, _: Block, _: BlockSide) -> bool {
+        false
+    }
+}
+
+pub struct InitCrossBlock {
+    texture: u32,
+    offset: CrossOffset,
+}
+
+impl InitCrossBlock {
+    fn new(
+        texture_path: &Path,
+        offset: CrossOffset,
+        texture_provider: &mut InitTextureProvider,
+    ) -> Self {
+        Self {
+            texture: texture_provider.texture(texture_path),
+            offset,
+        }
+    }
+}
+
+impl InitBlockMeshGenerator for InitCrossBlock {
+    fn build(&self, textures: &BlockTextures) -> Box<dyn BlockMeshGenerator> {
+        Box::new(CrossBlock {
+            texture_coords: textures.get_texture_coordinates(self.texture),
+            offset: self.offset,
+        })
+    }
+}
@@ -1,183 +1,76 @@
 use std::path::Path;
 
-use super::{BlockMeshGenerator, InitBlockMeshGenerator, Mesh};
+use super::{
+    BlockMeshGenerator,
+    InitBlockMeshGenerator,
+    Mesh,
+    MeshContext,
+    RenderLayer,
+};
 use crate::init::InitTextureProvider;
-use crate::renderer::texture_atlas::TextureAtlas;
+use crate::renderer::block_textures::{BlockTextures, TextureCoordinates};
 use crate::renderer::Vertex;
 use crate::util::BlockSide;
 use crate::world::Block;
 
-pub struct CubeBlock {
-    texture_coords: (glm::Vec2, glm::Vec2),
+/// Whether the block next to `side` has the same type as `block`.
+pub fn same_block_adjacent(
+    context: &MeshContext,
+    position: glm::IVec3,
+    block: Block,
+    side: BlockSide,
+) -> bool {
+    context
+        .get_block(position + side.direction())
+        .is_some_and(|x| x.block_type() == block.block_type())
 }
 
-impl CubeBlock {
-    pub fn new(
-        texture_path: &Path,
-        texture_provider: &mut InitTextureProvider,
-    ) -> InitCubeBlock {
-        InitCubeBlock::new(texture_path, texture_provider)
-    }
-}
-
-impl BlockMeshGenerator for CubeBlock {
-    fn mesh_side(
-        &self,
-        position: glm::IVec3,
-        _: Block,
-        side: BlockSide,
-        mesh: &mut Mesh,
-    ) {
-        let texture_top_right =
-            glm::vec2(self.texture_coords.1.x, self.texture_coords.0.y);
-        let texture_bottom_left =
-            glm::vec2(self.texture_coords.0.x, self.texture_coords.1.y);
-        match side {
-            BlockSide::Bottom => {
-                mesh.add_quad(
-                    Vertex {
-                        position: glm::vec3(
-                            0.0 + position.x as f32,
-                            0.0 + position.y as f32,
-                            1.0 + position.z as f32,
-                        ),
-                        tex_coords: texture_bottom_left,
-                        normal: BlockSide::Bottom.direction(),
-                    },
-                    Vertex {
-                        position: glm::vec3(
-                            0.0 + position.x as f32,
-                            0.0 + position.y as f32,
-                            0.0 + position.z as f32,
-                        ),
-                        tex_coords: self.texture_coords.0,
-                        normal: BlockSide::Bottom.direction(),
-                    },
-                    Vertex {
-                        position: glm::vec3(
-                            1.0 + position.x as f32,
-                            0.0 + position.y as f32,
-                            0.0 + position.z as f32,
-                        ),
-                        tex_coords: texture_top_right,
-                        normal: BlockSide::Bottom.direction(),
-                    },
-                    Vertex {
-                        position: glm::vec3(
-                            1.0 + position.x as f32,
-                            0.0 + position.y as f32,
-                            1.0 + position.z as f32,
-                        ),
-                        tex_coords: self.texture_coords.1,
-                        normal: BlockSide::Bottom.direction(),
-                    },
-                );
-            }
-            BlockSide::Top => {
-                mesh.add_quad(
-                    Vertex {
-                        position: glm::vec3(
-                            0.0 + position.x as f32,
-                            1.0 + position.y as f32,
-                            0.0 + position.z as f32,
-                        ),
-                        tex_coords: texture_bottom_left,
-                        normal: BlockSide::Top.direction(),
-                    },
-                    Vertex {
-                        position: glm::vec3(
-                            0.0 + position.x as f32,
-                            1.0 + position.y as f32,
-                            1.0 + position.z as f32,
-                        ),
-                        tex_coords: self.texture_coords.0,
-                        normal: BlockSide::Top.direction(),
-                    },
-                    Vertex {
-                        position: glm::vec3(
-                            1.0 + position.x as f32,
-                            1.0 + position.y as f32,
-                            1.0 + position.z as f32,
-                        ),
-                        tex_coords: texture_top_right,
-                        normal: BlockSide::Top.direction(),
-                    },
-                    Vertex {
-                        position: glm::vec3(
-                            1.0 + position.x as f32,
-                            1.0 + position.y as f32,
-                            0.0 + position.z as f32,
-                        ),
-                        tex_coords: self.texture_coords.1,
-                        normal: BlockSide::Top.direction(),
-                    },
-                );
-            }
-            BlockSide::North => mesh.add_quad(
+/// Adds one full side of the unit cube at `position`.
+pub fn add_cube_face(
+    mesh: &mut Mesh,
+    position: glm::IVec3,
+    side: BlockSide,
+    texture_coords: TextureCoordinates,
+) {
+    let texture_top_right =
+        glm::vec2(texture_coords.end.x, texture_coords.start.y);
+    let texture_bottom_left =
+        glm::vec2(texture_coords.start.x, texture_coords.end.y);
+    match side {
+        BlockSide::Bottom => {
+            mesh.add_quad(
                 Vertex {
                     position: glm::vec3(
-                        1.0 + position.x as f32,
+                        0.0 + position.x as f32,
                         0.0 + position.y as f32,
                         1.0 + position.z as f32,
                     ),
                     tex_coords: texture_bottom_left,
-                    normal: BlockSide::North.direction(),
-                },
-                Vertex {
-                    position: glm::vec3(
-                        1.0 + position.x as f32,
-                        1.0 + position.y as f32,
-                        1.0 + position.z as f32,
-                    ),
-                    tex_coords: self.texture_coords.0,
-                    normal: BlockSide::North.direction(),
-                },
-                Vertex {
-                    position: glm::vec3(
-                        0.0 + position.x as f32,
-                        1.0 + position.y as f32,
-                        1.0 + position.z as f32,
-                    ),
-                    tex_coords: texture_top_right,
-                    normal: BlockSide::North.direction(),
+                    normal: BlockSide::Bottom.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
                 Vertex {
                     position: glm::vec3(
                         0.0 + position.x as f32,
                         0.0 + position.y as f32,
-                        1.0 + position.z as f32,
-                    ),
-                    tex_coords: self.texture_coords.1,
-                    normal: BlockSide::North.direction(),
-                },
-            ),
-            BlockSide::East => mesh.add_quad(
-                Vertex {
-                    position: glm::vec3(
-                        1.0 + position.x as f32,
-                        0.0 + position.y as f32,
                         0.0 + position.z as f32,
                     ),
-                    tex_coords: texture_bottom_left,
-                    normal: BlockSide::East.direction(),
+                    tex_coords: texture_coords.start,
+                    normal: BlockSide::Bottom.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
                 Vertex {
                     position: glm::vec3(
                         1.0 + position.x as f32,
-                        1.0 + position.y as f32,
+                        0.0 + position.y as f32,
                         0.0 + position.z as f32,
                     ),
-                    tex_coords: self.texture_coords.0,
-                    normal: BlockSide::East.direction(),
-                },
-                Vertex {
-                    position: glm::vec3(
-                        1.0 + position.x as f32,
-                        1.0 + position.y as f32,
-                        1.0 + position.z as f32,
-                    ),
                     tex_coords: texture_top_right,
-                    normal: BlockSide::East.direction(),
+                    normal: BlockSide::Bottom.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
                 Vertex {
                     position: glm::vec3(
@@ -185,113 +78,326 @@ impl BlockMeshGenerator for CubeBlock {
                         0.0 + position.y as f32,
                         1.0 + position.z as f32,
                     ),
-                    tex_coords: self.texture_coords.1,
-                    normal: BlockSide::East.direction(),
+                    tex_coords: texture_coords.end,
+                    normal: BlockSide::Bottom.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
-            ),
-            BlockSide::South => mesh.add_quad(
+            );
+        }
+        BlockSide::Top => {
+            mesh.add_quad(
                 Vertex {
                     position: glm::vec3(
                         0.0 + position.x as f32,
-                        0.0 + position.y as f32,
+                        1.0 + position.y as f32,
                         0.0 + position.z as f32,
                     ),
                     tex_coords: texture_bottom_left,
-                    normal: BlockSide::South.direction(),
+                    normal: BlockSide::Top.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
                 Vertex {
                     position: glm::vec3(
                         0.0 + position.x as f32,
                         1.0 + position.y as f32,
-                        0.0 + position.z as f32,
+                        1.0 + position.z as f32,
                     ),
-                    tex_coords: self.texture_coords.0,
-                    normal: BlockSide::South.direction(),
+                    tex_coords: texture_coords.start,
+                    normal: BlockSide::Top.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
                 Vertex {
                     position: glm::vec3(
                         1.0 + position.x as f32,
                         1.0 + position.y as f32,
-                        0.0 + position.z as f32,
+                        1.0 + position.z as f32,
                     ),
                     tex_coords: texture_top_right,
-                    normal: BlockSide::South.direction(),
+                    normal: BlockSide::Top.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
                 Vertex {
                     position: glm::vec3(
                         1.0 + position.x as f32,
-                        0.0 + position.y as f32,
-                        0.0 + position.z as f32,
-                    ),
-                    tex_coords: self.texture_coords.1,
-                    normal: BlockSide::South.direction(),
-                },
-            ),
-            BlockSide::West => mesh.add_quad(
-                Vertex {
-                    position: glm::vec3(
-                        0.0 + position.x as f32,
-                        0.0 + position.y as f32,
-                        1.0 + position.z as f32,
-                    ),
-                    tex_coords: texture_bottom_left,
-                    normal: BlockSide::West.direction(),
-                },
-                Vertex {
-                    position: glm::vec3(
-                        0.0 + position.x as f32,
                         1.0 + position.y as f32,
-                        1.0 + position.z as f32,
-                    ),
-                    tex_coords: self.texture_coords.0,
-                    normal: BlockSide::West.direction(),
-                },
-                Vertex {
-                    position: glm::vec3(
-                        0.0 + position.x as f32,
-                        1.0 + position.y as f32,
-                        0.0 + position.z as f32,
-                    ),
-                    tex_coords: texture_top_right,
-                    normal: BlockSide::West.direction(),
-                },
-                Vertex {
-                    position: glm::vec3(
-                        0.0 + position.x as f32,
-                        0.0 + position.y as f32,
                         0.0 + position.z as f32,
                     ),
-                    tex_coords: self.texture_coords.1,
-                    normal: BlockSide::West.direction(),
+                    tex_coords: texture_coords.end,
+                    normal: BlockSide::Top.direction(),
+                    layer: texture_coords.layer,
+                    light: 0,
                 },
-            ),
+            );
+        }
+        BlockSide::North => mesh.add_quad(
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_bottom_left,
+                normal: BlockSide::North.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.start,
+                normal: BlockSide::North.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_top_right,
+                normal: BlockSide::North.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.end,
+                normal: BlockSide::North.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+        ),
+        BlockSide::East => mesh.add_quad(
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_bottom_left,
+                normal: BlockSide::East.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.start,
+                normal: BlockSide::East.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_top_right,
+                normal: BlockSide::East.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.end,
+                normal: BlockSide::East.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+        ),
+        BlockSide::South => mesh.add_quad(
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_bottom_left,
+                normal: BlockSide::South.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.start,
+                normal: BlockSide::South.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_top_right,
+                normal: BlockSide::South.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    1.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.end,
+                normal: BlockSide::South.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+        ),
+        BlockSide::West => mesh.add_quad(
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_bottom_left,
+                normal: BlockSide::West.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    1.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.start,
+                normal: BlockSide::West.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    1.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_top_right,
+                normal: BlockSide::West.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+            Vertex {
+                position: glm::vec3(
+                    0.0 + position.x as f32,
+                    0.0 + position.y as f32,
+                    0.0 + position.z as f32,
+                ),
+                tex_coords: texture_coords.end,
+                normal: BlockSide::West.direction(),
+                layer: texture_coords.layer,
+                light: 0,
+            },
+        ),
+    }
+}
+
+pub struct CubeBlock {
+    texture_coords: TextureCoordinates,
+    render_layer: RenderLayer,
+}
+
+impl CubeBlock {
+    pub fn new(
+        texture_path: &Path,
+        render_layer: RenderLayer,
+        texture_provider: &mut InitTextureProvider,
+    ) -> InitCubeBlock {
+        InitCubeBlock::new(texture_path, render_layer, texture_provider)
+    }
+
+    /// A cube showing the missing texture placeholder, used in place of
+    /// blocks whose model failed to load.
+    pub fn missing(
+        texture_provider: &mut InitTextureProvider,
+    ) -> InitCubeBlock {
+        InitCubeBlock {
+            face_texture: texture_provider.missing_texture(),
+            render_layer: RenderLayer::Opaque,
+        }
+    }
+}
+
+impl BlockMeshGenerator for CubeBlock {
+    fn render_layer(&self) -> RenderLayer {
+        self.render_layer
+    }
+
+    fn mesh_side(
+        &self,
+        context: &MeshContext,
+        position: glm::IVec3,
+        block: Block,
+        side: BlockSide,
+        mesh: &mut Mesh,
+    ) {
+        // see-through cubes hide the faces between blocks of the same type,
+        // like the inside of a glass wall.
+        if self.render_layer != RenderLayer::Opaque
+            && same_block_adjacent(context, position, block, side)
+        {
+            return;
         }
+        add_cube_face(mesh, position, side, self.texture_coords);
     }
 
     fn solid_side(&self, _: Block, _: crate::util::BlockSide) -> bool {
-        true
+        self.render_layer == RenderLayer::Opaque
     }
 }
 
 pub struct InitCubeBlock {
     face_texture: u32,
+    render_layer: RenderLayer,
 }
 
 impl InitCubeBlock {
     fn new(
         texture_path: &Path,
+        render_layer: RenderLayer,
         texture_provider: &mut InitTextureProvider,
     ) -> Self {
         Self {
             face_texture: texture_provider.texture(texture_path),
+            render_layer,
         }
     }
 }
 
 impl InitBlockMeshGenerator for InitCubeBlock {
-    fn build(&self, atlas: &TextureAtlas) -> Box<dyn BlockMeshGenerator> {
+    fn build(&self, textures: &BlockTextures) -> Box<dyn BlockMeshGenerator> {
         Box::new(CubeBlock {
-            texture_coords: atlas.get_texture_coordinates(self.face_texture),
+            texture_coords: textures
+                .get_texture_coordinates(self.face_texture),
+            render_layer: self.render_layer,
         })
     }
 }
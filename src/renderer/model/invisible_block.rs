@@ -1,4 +1,4 @@
-use super::{BlockMeshGenerator, InitBlockMeshGenerator};
+use super::{BlockMeshGenerator, InitBlockMeshGenerator, MeshContext};
 use crate::renderer::Mesh;
 use crate::util::BlockSide;
 use crate::world::Block;
@@ -12,7 +12,15 @@ impl InvisibleBlock {
 }
 
 impl BlockMeshGenerator for InvisibleBlock {
-    fn mesh_side(&self, _: glm::IVec3, _: Block, _: BlockSide, _: &mut Mesh) {}
+    fn mesh_side(
+        &self,
+        _: &MeshContext,
+        _: glm::IVec3,
+        _: Block,
+        _: BlockSide,
+        _: &mut Mesh,
+    ) {
+    }
 
     fn solid_side(&self, _: Block, _: crate::util::BlockSide) -> bool {
         false
@@ -30,7 +38,7 @@ impl InitInvisibleBlock {
 impl InitBlockMeshGenerator for InitInvisibleBlock {
     fn build(
         &self,
-        _atlas: &crate::renderer::TextureAtlas,
+        _textures: &crate::renderer::block_textures::BlockTextures,
     ) -> Box<dyn BlockMeshGenerator> {
         return Box::new(InvisibleBlock {});
     }
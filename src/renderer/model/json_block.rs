@@ -0,0 +1,619 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{bail, eyre, Context};
+use color_eyre::Result;
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use super::{
+    BlockMeshGenerator,
+    InitBlockMeshGenerator,
+    Mesh,
+    MeshContext,
+    RenderLayer,
+};
+use crate::assets::ASSETS;
+use crate::init::InitTextureProvider;
+use crate::renderer::block_textures::BlockTextures;
+use crate::renderer::Vertex;
+use crate::util::BlockSide;
+use crate::world::Block;
+
+const MODEL_DIRECTORY: &str = "models";
+const TEXTURE_DIRECTORY: &str = "textures";
+const MAX_PARENT_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FaceName {
+    #[serde(alias = "bottom")]
+    Down,
+    Up,
+    North,
+    East,
+    South,
+    West,
+}
+
+/// Face names are converted as they are, the coordinates are mirrored with
+/// `mirror_z` instead.
+impl From<FaceName> for BlockSide {
+    fn from(value: FaceName) -> Self {
+        match value {
+            FaceName::Down => BlockSide::Bottom,
+            FaceName::Up => BlockSide::Top,
+            FaceName::North => BlockSide::North,
+            FaceName::East => BlockSide::East,
+            FaceName::South => BlockSide::South,
+            FaceName::West => BlockSide::West,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    fn vector(&self) -> glm::Vec3 {
+        match self {
+            Axis::X => glm::vec3(1.0, 0.0, 0.0),
+            Axis::Y => glm::vec3(0.0, 1.0, 0.0),
+            Axis::Z => glm::vec3(0.0, 0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ModelFile {
+    parent: Option<String>,
+    render_type: Option<String>,
+    #[serde(default)]
+    textures: HashMap<String, String>,
+    elements: Option<Vec<ElementFile>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ElementFile {
+    from: [f32; 3],
+    to: [f32; 3],
+    rotation: Option<ElementRotation>,
+    #[serde(default)]
+    faces: HashMap<FaceName, FaceFile>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ElementRotation {
+    origin: [f32; 3],
+    axis: Axis,
+    angle: f32,
+    #[serde(default)]
+    rescale: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct FaceFile {
+    uv: Option<[f32; 4]>,
+    texture: String,
+    cullface: Option<FaceName>,
+    #[serde(default)]
+    rotation: u16,
+}
+
+/// A model file merged with all of its parents.
+struct ResolvedModel {
+    render_layer: RenderLayer,
+    textures: HashMap<String, String>,
+    elements: Vec<ElementFile>,
+}
+
+fn model_path(name: &str) -> PathBuf {
+    Path::new(MODEL_DIRECTORY)
+        .join(strip_namespace(name))
+        .with_extension("json")
+}
+
+fn texture_path(name: &str) -> PathBuf {
+    Path::new(TEXTURE_DIRECTORY)
+        .join(strip_namespace(name))
+        .with_extension("png")
+}
+
+/// Mirrors a point in model space between Minecraft's axes and the ones of
+/// `BlockSide`, which have north on opposite sides.
+fn mirror_z(point: glm::Vec3) -> glm::Vec3 {
+    glm::vec3(point.x, point.y, 16.0 - point.z)
+}
+
+fn strip_namespace(name: &str) -> &str {
+    name.split_once(':').map_or(name, |(_, name)| name)
+}
+
+fn read_model_file(path: &Path) -> Result<ModelFile> {
+    let content = ASSETS
+        .read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read block model {path:?}"))?;
+    serde_json::from_str(&content)
+        .wrap_err_with(|| format!("Failed to parse block model {path:?}"))
+}
+
+fn resolve_model(path: &Path) -> Result<ResolvedModel> {
+    let mut chain = vec![read_model_file(path)?];
+    while let Some(parent) = chain.last().unwrap().parent.clone() {
+        if parent.starts_with("builtin/") {
+            break;
+        }
+        if chain.len() > MAX_PARENT_DEPTH {
+            bail!(
+                "Block model {path:?} has too many parents, is there a cycle?"
+            );
+        }
+        chain.push(read_model_file(&model_path(&parent)).wrap_err_with(
+            || format!("Failed to load parent of block model {path:?}"),
+        )?);
+    }
+
+    let mut render_type = None;
+    let mut textures = HashMap::new();
+    let mut elements = None;
+    // walk from the root parent down, so children override their parents.
+    for model in chain.into_iter().rev() {
+        textures.extend(model.textures);
+        if model.elements.is_some() {
+            elements = model.elements;
+        }
+        if model.render_type.is_some() {
+            render_type = model.render_type;
+        }
+    }
+    let render_layer = match render_type.as_deref().map(strip_namespace) {
+        None | Some("solid") => RenderLayer::Opaque,
+        Some("cutout" | "cutout_mipped") => RenderLayer::Cutout,
+        Some("translucent") => RenderLayer::Translucent,
+        Some(render_type) => {
+            bail!("Unknown render type \"{render_type}\" in {path:?}")
+        }
+    };
+    Ok(ResolvedModel {
+        render_layer,
+        textures,
+        elements: elements.unwrap_or_default(),
+    })
+}
+
+fn resolve_texture<'a>(
+    textures: &'a HashMap<String, String>,
+    reference: &'a str,
+) -> Result<&'a str> {
+    let mut name = reference;
+    for _ in 0..MAX_PARENT_DEPTH {
+        match name.strip_prefix('#') {
+            Some(variable) => {
+                name = textures.get(variable).ok_or_else(|| {
+                    eyre!("Unresolved texture variable \"{reference}\"")
+                })?;
+            }
+            None => return Ok(name),
+        }
+    }
+    Err(eyre!("Texture variable \"{reference}\" references itself"))
+}
+
+/// Default uv rectangle of a face, derived from the element bounds the same
+/// way `CubeBlock` lays out its texture.
+fn default_uv(side: BlockSide, from: glm::Vec3, to: glm::Vec3) -> [f32; 4] {
+    match side {
+        BlockSide::Top => [from.x, 16.0 - to.z, to.x, 16.0 - from.z],
+        BlockSide::Bottom => [from.x, from.z, to.x, to.z],
+        BlockSide::North => {
+            [16.0 - to.x, 16.0 - to.y, 16.0 - from.x, 16.0 - from.y]
+        }
+        BlockSide::East => [from.z, 16.0 - to.y, to.z, 16.0 - from.y],
+        BlockSide::South => [from.x, 16.0 - to.y, to.x, 16.0 - from.y],
+        BlockSide::West => {
+            [16.0 - to.z, 16.0 - to.y, 16.0 - from.z, 16.0 - from.y]
+        }
+    }
+}
+
+/// Corners of a box face in the winding order used by `CubeBlock`, starting
+/// at the corner that gets the bottom left texture coordinate.
+fn face_corners(
+    side: BlockSide,
+    from: glm::Vec3,
+    to: glm::Vec3,
+) -> [glm::Vec3; 4] {
+    let (a, b) = (from, to);
+    match side {
+        BlockSide::Bottom => [
+            glm::vec3(a.x, a.y, b.z),
+            glm::vec3(a.x, a.y, a.z),
+            glm::vec3(b.x, a.y, a.z),
+            glm::vec3(b.x, a.y, b.z),
+        ],
+        BlockSide::Top => [
+            glm::vec3(a.x, b.y, a.z),
+            glm::vec3(a.x, b.y, b.z),
+            glm::vec3(b.x, b.y, b.z),
+            glm::vec3(b.x, b.y, a.z),
+        ],
+        BlockSide::North => [
+            glm::vec3(b.x, a.y, b.z),
+            glm::vec3(b.x, b.y, b.z),
+            glm::vec3(a.x, b.y, b.z),
+            glm::vec3(a.x, a.y, b.z),
+        ],
+        BlockSide::East => [
+            glm::vec3(b.x, a.y, a.z),
+            glm::vec3(b.x, b.y, a.z),
+            glm::vec3(b.x, b.y, b.z),
+            glm::vec3(b.x, a.y, b.z),
+        ],
+        BlockSide::South => [
+            glm::vec3(a.x, a.y, a.z),
+            glm::vec3(a.x, b.y, a.z),
+            glm::vec3(b.x, b.y, a.z),
+            glm::vec3(b.x, a.y, a.z),
+        ],
+        BlockSide::West => [
+            glm::vec3(a.x, a.y, b.z),
+            glm::vec3(a.x, b.y, b.z),
+            glm::vec3(a.x, b.y, a.z),
+            glm::vec3(a.x, a.y, a.z),
+        ],
+    }
+}
+
+struct InitFace {
+    side: BlockSide,
+    uv: [f32; 4],
+    texture: u32,
+    cullface: Option<BlockSide>,
+    rotation: u16,
+}
+
+struct InitElement {
+    from: glm::Vec3,
+    to: glm::Vec3,
+    rotation: Option<ElementRotation>,
+    faces: Vec<InitFace>,
+}
+
+impl InitElement {
+    fn covers_side(&self, side: BlockSide) -> bool {
+        let full = |min: f32, max: f32| min <= 0.0 && max >= 16.0;
+        let (from, to) = (self.from, self.to);
+        self.rotation.is_none()
+            && self.faces.iter().any(|face| face.side == side)
+            && match side {
+                BlockSide::Top => to.y >= 16.0,
+                BlockSide::Bottom => from.y <= 0.0,
+                BlockSide::North => to.z >= 16.0,
+                BlockSide::South => from.z <= 0.0,
+                BlockSide::East => to.x >= 16.0,
+                BlockSide::West => from.x <= 0.0,
+            }
+            && match side {
+                BlockSide::Top | BlockSide::Bottom => {
+                    full(from.x, to.x) && full(from.z, to.z)
+                }
+                BlockSide::North | BlockSide::South => {
+                    full(from.x, to.x) && full(from.y, to.y)
+                }
+                BlockSide::East | BlockSide::West => {
+                    full(from.y, to.y) && full(from.z, to.z)
+                }
+            }
+    }
+
+    /// Transforms a point in model space (0-16) into block space (0-1),
+    /// applying the element rotation.
+    fn transform(&self, point: glm::Vec3) -> glm::Vec3 {
+        let point = match &self.rotation {
+            Some(rotation) => {
+                let origin = glm::Vec3::from(rotation.origin);
+                let axis = rotation.axis.vector();
+                let angle = rotation.angle.to_radians();
+                let rotated =
+                    glm::rotate_vec3(&(point - origin), angle, &axis);
+                if rotation.rescale {
+                    // stretch the faces back to the full block on the axes
+                    // perpendicular to the rotation axis.
+                    let stretch = glm::vec3(1.0, 1.0, 1.0) - axis;
+                    origin
+                        + rotated
+                        + rotated.component_mul(&stretch)
+                            * (1.0 / angle.cos() - 1.0)
+                } else {
+                    origin + rotated
+                }
+            }
+            None => point,
+        };
+        point / 16.0
+    }
+
+    fn transform_normal(&self, normal: glm::Vec3) -> glm::Vec3 {
+        match &self.rotation {
+            Some(rotation) => glm::rotate_vec3(
+                &normal,
+                rotation.angle.to_radians(),
+                &rotation.axis.vector(),
+            ),
+            None => normal,
+        }
+    }
+}
+
+/// Converts an element from Minecraft's axes into the ones of `BlockSide`.
+fn init_element(
+    element: ElementFile,
+    textures: &HashMap<String, String>,
+    model_path: &Path,
+    texture_provider: &mut InitTextureProvider,
+) -> Result<InitElement> {
+    // mirroring swaps which end of the z range is lower.
+    let (from, to) = (
+        mirror_z(glm::vec3(element.from[0], element.from[1], element.to[2])),
+        mirror_z(glm::vec3(element.to[0], element.to[1], element.from[2])),
+    );
+    // a mirror turns rotations the other way, except around the mirrored
+    // axis itself.
+    let rotation = element.rotation.map(|rotation| ElementRotation {
+        origin: mirror_z(rotation.origin.into()).into(),
+        angle: match rotation.axis {
+            Axis::Z => rotation.angle,
+            Axis::X | Axis::Y => -rotation.angle,
+        },
+        ..rotation
+    });
+    let faces = element
+        .faces
+        .into_iter()
+        .map(|(name, face)| {
+            let side = BlockSide::from(name);
+            let texture = resolve_texture(textures, &face.texture)
+                .wrap_err_with(|| format!("In block model {model_path:?}"))?;
+            if face.rotation % 90 != 0 {
+                bail!(
+                    "Face rotation must be a multiple of 90 in \
+                     block model {model_path:?}"
+                );
+            }
+            Ok(InitFace {
+                side,
+                uv: face.uv.unwrap_or_else(|| default_uv(side, from, to)),
+                texture: texture_provider.texture(&texture_path(texture)),
+                cullface: face.cullface.map(BlockSide::from),
+                rotation: face.rotation,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(InitElement {
+        from,
+        to,
+        rotation,
+        faces,
+    })
+}
+
+struct Quad {
+    cullface: Option<BlockSide>,
+    vertices: [Vertex; 4],
+}
+
+pub struct JsonBlock {
+    quads: Vec<Quad>,
+    solid_sides: [bool; 6],
+    render_layer: RenderLayer,
+}
+
+impl JsonBlock {
+    /// Loads a Minecraft style json block model.
+    ///
+    /// Element coordinates are in pixels (0-16) on Minecraft's axes, where
+    /// `north` is the -z side, and are mirrored into the axes of `BlockSide`
+    /// when loading. Parent models are looked up in `models` and textures in
+    /// `textures`.
+    pub fn new(
+        model_path: &Path,
+        texture_provider: &mut InitTextureProvider,
+    ) -> Result<InitJsonBlock> {
+        InitJsonBlock::new(model_path, texture_provider)
+    }
+
+    fn mesh_quads(
+        &self,
+        position: glm::IVec3,
+        cullface: Option<BlockSide>,
+        mesh: &mut Mesh,
+    ) {
+        let offset =
+            glm::vec3(position.x as f32, position.y as f32, position.z as f32);
+        for quad in self.quads.iter().filter(|x| x.cullface == cullface) {
+            let [a, b, c, d] = quad.vertices.map(|vertex| Vertex {
+                position: vertex.position + offset,
+                ..vertex
+            });
+            mesh.add_quad(a, b, c, d);
+        }
+    }
+}
+
+impl BlockMeshGenerator for JsonBlock {
+    fn render_layer(&self) -> RenderLayer {
+        self.render_layer
+    }
+
+    fn mesh_always(
+        &self,
+        _: &MeshContext,
+        position: glm::IVec3,
+        _: Block,
+        mesh: &mut Mesh,
+    ) {
+        self.mesh_quads(position, None, mesh);
+    }
+
+    fn mesh_side(
+        &self,
+        _: &MeshContext,
+        position: glm::IVec3,
+        _: Block,
+        side: BlockSide,
+        mesh: &mut Mesh,
+    ) {
+        self.mesh_quads(position, Some(side), mesh);
+    }
+
+    fn solid_side(&self, _: Block, side: BlockSide) -> bool {
+        self.solid_sides[side as usize]
+    }
+}
+
+pub struct InitJsonBlock {
+    elements: Vec<InitElement>,
+    render_layer: RenderLayer,
+}
+
+impl InitJsonBlock {
+    fn new(
+        model_path: &Path,
+        texture_provider: &mut InitTextureProvider,
+    ) -> Result<Self> {
+        let model = resolve_model(model_path)?;
+        let elements = model
+            .elements
+            .into_iter()
+            .map(|element| {
+                init_element(
+                    element,
+                    &model.textures,
+                    model_path,
+                    texture_provider,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            elements,
+            render_layer: model.render_layer,
+        })
+    }
+}
+
+impl InitBlockMeshGenerator for InitJsonBlock {
+    fn build(&self, textures: &BlockTextures) -> Box<dyn BlockMeshGenerator> {
+        let mut quads = Vec::new();
+        for element in &self.elements {
+            for face in &element.faces {
+                let texture = textures.get_texture_coordinates(face.texture);
+                let texture_coordinate = |u: f32, v: f32| {
+                    texture.start
+                        + (texture.end - texture.start)
+                            .component_mul(&glm::vec2(u, v))
+                            / 16.0
+                };
+                let [u0, v0, u1, v1] = face.uv;
+                let tex_coords = [
+                    texture_coordinate(u0, v1),
+                    texture_coordinate(u0, v0),
+                    texture_coordinate(u1, v0),
+                    texture_coordinate(u1, v1),
+                ];
+                let normal = element.transform_normal(face.side.direction());
+                let corners =
+                    face_corners(face.side, element.from, element.to);
+                let steps = (face.rotation / 90) as usize;
+                let vertices = std::array::from_fn(|i| Vertex {
+                    position: element.transform(corners[i]),
+                    tex_coords: tex_coords[(i + 4 - steps % 4) % 4],
+                    normal,
+                    layer: texture.layer,
+                    light: 0,
+                });
+                quads.push(Quad {
+                    cullface: face.cullface,
+                    vertices,
+                });
+            }
+        }
+        let mut solid_sides = [false; 6];
+        if self.render_layer == RenderLayer::Opaque {
+            for side in BlockSide::iter() {
+                solid_sides[side as usize] = self
+                    .elements
+                    .iter()
+                    .any(|element| element.covers_side(side));
+            }
+        }
+        Box::new(JsonBlock {
+            quads,
+            solid_sides,
+            render_layer: self.render_layer,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The upper step of `stairs.json`, on the north half in Minecraft's axes.
+    const STEP: &str = r##"{
+        "from": [0, 8, 0],
+        "to": [16, 16, 8],
+        "faces": {
+            "north": { "texture": "#side", "cullface": "north" },
+            "south": { "texture": "#side" }
+        }
+    }"##;
+
+    fn step() -> InitElement {
+        let element = serde_json::from_str(STEP).unwrap();
+        let textures =
+            HashMap::from([("side".to_string(), "stone".to_string())]);
+        init_element(
+            element,
+            &textures,
+            Path::new("step.json"),
+            &mut InitTextureProvider::new(),
+        )
+        .unwrap()
+    }
+
+    /// The z coordinate of the plane a face lies in.
+    fn face_z(element: &InitElement, side: BlockSide) -> f32 {
+        let corners = face_corners(side, element.from, element.to);
+        assert!(corners.iter().all(|x| x.z == corners[0].z));
+        corners[0].z
+    }
+
+    fn face(element: &InitElement, side: BlockSide) -> &InitFace {
+        element.faces.iter().find(|x| x.side == side).unwrap()
+    }
+
+    #[test]
+    fn elements_are_mirrored() {
+        let step = step();
+        assert_eq!((step.from.z, step.to.z), (8.0, 16.0));
+        assert_eq!((step.from.y, step.to.y), (8.0, 16.0));
+    }
+
+    #[test]
+    fn faces_stay_on_their_side() {
+        let step = step();
+        // the outer face is on the block boundary and culled there.
+        let north = face(&step, BlockSide::North);
+        assert_eq!(face_z(&step, BlockSide::North), 16.0);
+        assert_eq!(north.cullface, Some(BlockSide::North));
+        // the riser is inside the block and never culled.
+        let south = face(&step, BlockSide::South);
+        assert_eq!(face_z(&step, BlockSide::South), 8.0);
+        assert_eq!(south.cullface, None);
+    }
+}
@@ -0,0 +1,97 @@
+use glm::IVec2;
+
+use crate::renderer::GameRenderData;
+use crate::util::BlockSide;
+use crate::world::{Block, Chunk, GameData, LightChannel, World, MAX_LIGHT};
+
+/// Read access to the world around the chunk that is currently meshed.
+///
+/// The chunk and its eight neighbors are cached, so lookups close to the
+/// chunk don't have to go through the chunk map of the `World`.
+pub struct MeshContext<'a> {
+    center: IVec2,
+    chunks: [Option<&'a Chunk>; 9],
+    world: &'a World,
+    game_data: &'a GameData,
+    render_data: &'a GameRenderData,
+}
+
+impl<'a> MeshContext<'a> {
+    pub fn new(
+        chunk: &'a Chunk,
+        world: &'a World,
+        game_data: &'a GameData,
+        render_data: &'a GameRenderData,
+    ) -> Self {
+        let center = chunk.position();
+        let chunks = std::array::from_fn(|index| {
+            let offset = glm::vec2(index as i32 % 3 - 1, index as i32 / 3 - 1);
+            world.get_chunk(center + offset)
+        });
+        Self {
+            center,
+            chunks,
+            world,
+            game_data,
+            render_data,
+        }
+    }
+
+    fn get_chunk(&self, chunk_position: IVec2) -> Option<&'a Chunk> {
+        let offset = chunk_position - self.center;
+        if offset.x.abs() <= 1 && offset.y.abs() <= 1 {
+            self.chunks[((offset.y + 1) * 3 + offset.x + 1) as usize]
+        } else {
+            self.world.get_chunk(chunk_position)
+        }
+    }
+
+    /// Returns the block at a world position, or `None` if the position is
+    /// outside the world height or its chunk isn't loaded.
+    pub fn get_block(&self, position: glm::IVec3) -> Option<Block> {
+        if !World::position_in_world(position) {
+            return None;
+        }
+        self.get_chunk(glm::vec2(
+            position.x.div_euclid(Chunk::SIZE_X as i32),
+            position.z.div_euclid(Chunk::SIZE_Z as i32),
+        ))
+        .map(|chunk| chunk.get_block(World::get_chunk_position(position)))
+    }
+
+    /// The light level of `channel` at a world position. Skylight is full
+    /// above the world, anything else where `get_block` has no block is
+    /// dark.
+    pub fn light(&self, channel: LightChannel, position: glm::IVec3) -> u8 {
+        let top = Chunk::LOWEST_HEIGHT as i32 + Chunk::SIZE_Y as i32;
+        if channel == LightChannel::Sky && position.y >= top {
+            return MAX_LIGHT;
+        }
+        if !World::position_in_world(position) {
+            return 0;
+        }
+        self.get_chunk(glm::vec2(
+            position.x.div_euclid(Chunk::SIZE_X as i32),
+            position.z.div_euclid(Chunk::SIZE_Z as i32),
+        ))
+        .map_or(0, |chunk| {
+            chunk.get_light(channel, World::get_chunk_position(position))
+        })
+    }
+
+    /// Whether the block at `position` covers its `side` completely.
+    /// Unloaded blocks are considered solid.
+    pub fn solid_side(&self, position: glm::IVec3, side: BlockSide) -> bool {
+        match self.get_block(position) {
+            Some(block) => self
+                .render_data
+                .mesh_generator(block.block_type())
+                .solid_side(block, side),
+            None => World::position_in_world(position),
+        }
+    }
+
+    pub fn game_data(&self) -> &'a GameData {
+        self.game_data
+    }
+}
@@ -0,0 +1,161 @@
+use super::cube_block::same_block_adjacent;
+use super::{
+    BlockMeshGenerator,
+    InitBlockMeshGenerator,
+    Mesh,
+    MeshContext,
+    RenderLayer,
+};
+use crate::renderer::block_textures::BlockTextures;
+use crate::renderer::Vertex;
+use crate::util::BlockSide;
+use crate::world::Block;
+
+/// Height of the surface of water that has nothing on top of it.
+const SURFACE_HEIGHT: f32 = 14.0 / 16.0;
+
+/// The corners of each side of the unit cube, in the same order as
+/// `add_cube_face`. A y of 1 is moved down to the surface and a y of 0 up
+/// to the water next to it.
+fn side_corners(side: BlockSide) -> [glm::Vec3; 4] {
+    match side {
+        BlockSide::Top => [
+            glm::vec3(0.0, 1.0, 0.0),
+            glm::vec3(0.0, 1.0, 1.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec3(1.0, 1.0, 0.0),
+        ],
+        BlockSide::Bottom => [
+            glm::vec3(0.0, 0.0, 1.0),
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(1.0, 0.0, 1.0),
+        ],
+        BlockSide::North => [
+            glm::vec3(1.0, 0.0, 1.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec3(0.0, 1.0, 1.0),
+            glm::vec3(0.0, 0.0, 1.0),
+        ],
+        BlockSide::East => [
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(1.0, 1.0, 0.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec3(1.0, 0.0, 1.0),
+        ],
+        BlockSide::South => [
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(0.0, 1.0, 0.0),
+            glm::vec3(1.0, 1.0, 0.0),
+            glm::vec3(1.0, 0.0, 0.0),
+        ],
+        BlockSide::West => [
+            glm::vec3(0.0, 0.0, 1.0),
+            glm::vec3(0.0, 1.0, 1.0),
+            glm::vec3(0.0, 1.0, 0.0),
+            glm::vec3(0.0, 0.0, 0.0),
+        ],
+    }
+}
+
+/// A block of water. It has no texture, the water shader draws the waves
+/// and reflections of its surface.
+pub struct WaterBlock;
+
+impl WaterBlock {
+    pub fn new() -> InitWaterBlock {
+        InitWaterBlock
+    }
+
+    /// How high the water at `position` reaches, it only fills the whole
+    /// block below more water or under a block.
+    fn height(
+        context: &MeshContext,
+        position: glm::IVec3,
+        block: Block,
+    ) -> f32 {
+        let covered =
+            same_block_adjacent(context, position, block, BlockSide::Top)
+                || context.solid_side(
+                    position + BlockSide::Top.direction(),
+                    BlockSide::Bottom,
+                );
+        match covered {
+            true => 1.0,
+            false => SURFACE_HEIGHT,
+        }
+    }
+}
+
+impl BlockMeshGenerator for WaterBlock {
+    fn render_layer(&self) -> RenderLayer {
+        RenderLayer::Water
+    }
+
+    fn mesh_side(
+        &self,
+        context: &MeshContext,
+        position: glm::IVec3,
+        block: Block,
+        side: BlockSide,
+        mesh: &mut Mesh,
+    ) {
+        let height = Self::height(context, position, block);
+        // only the outside of a body of water is drawn, and the part of a
+        // side that reaches above lower water next to it.
+        let mut bottom = 0.0;
+        if same_block_adjacent(context, position, block, side) {
+            if side == BlockSide::Top || side == BlockSide::Bottom {
+                return;
+            }
+            let neighbor =
+                Self::height(context, position + side.direction(), block);
+            if neighbor >= height {
+                return;
+            }
+            bottom = neighbor;
+        }
+        let origin =
+            glm::vec3(position.x as f32, position.y as f32, position.z as f32);
+        let vertex = |corner: glm::Vec3, side: BlockSide| Vertex {
+            position: origin
+                + glm::vec3(
+                    corner.x,
+                    bottom + corner.y * (height - bottom),
+                    corner.z,
+                ),
+            tex_coords: glm::vec2(0.0, 0.0),
+            normal: side.direction(),
+            layer: 0,
+            light: 0,
+        };
+        let [a, b, c, d] = side_corners(side);
+        mesh.add_quad(
+            vertex(a, side),
+            vertex(b, side),
+            vertex(c, side),
+            vertex(d, side),
+        );
+        // the surface is seen from below while swimming.
+        if side == BlockSide::Top {
+            mesh.add_quad(
+                vertex(d, BlockSide::Bottom),
+                vertex(c, BlockSide::Bottom),
+                vertex(b, BlockSide::Bottom),
+                vertex(a, BlockSide::Bottom),
+            );
+        }
+    }
+
+    fn solid_side(&self, _: Block, _: BlockSide) -> bool {
+        false
+    }
+}
+
+pub struct InitWaterBlock;
+
+impl InitBlockMeshGenerator for InitWaterBlock {
+    fn build(&self, _: &BlockTextures) -> Box<dyn BlockMeshGenerator> {
+        Box::new(WaterBlock)
+    }
+}
@@ -0,0 +1,309 @@
+use color_eyre::Result;
+use wgpu::util::DeviceExt;
+
+use super::layouts::POST_PROCESSING_LAYOUT;
+use super::shader::load_shader_module;
+use super::{create_render_pipeline, PipelineOptions, Texture};
+use crate::settings::{Tonemapping, SETTINGS};
+
+/// The scene is drawn in this format, so colors brighter than white survive
+/// until tonemapping.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostProcessingUniform {
+    exposure: f32,
+    /// 0 for none, 1 for Reinhard and 2 for ACES.
+    tonemapping: u32,
+    bloom_threshold: f32,
+    /// 0 when bloom is turned off.
+    bloom_intensity: f32,
+}
+
+impl PostProcessingUniform {
+    fn new() -> Self {
+        let settings = &SETTINGS.graphics.post_processing;
+        Self {
+            exposure: settings.exposure.max(0.0),
+            tonemapping: match settings.tonemapping {
+                Tonemapping::None => 0,
+                Tonemapping::Reinhard => 1,
+                Tonemapping::Aces => 2,
+            },
+            bloom_threshold: settings.bloom.threshold,
+            bloom_intensity: match settings.bloom.enabled {
+                true => settings.bloom.intensity,
+                false => 0.0,
+            },
+        }
+    }
+}
+
+pub struct PostProcessingPipelines {
+    bright: wgpu::RenderPipeline,
+    blur_horizontal: wgpu::RenderPipeline,
+    blur_vertical: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+}
+
+/// The images the passes draw into, recreated with the window size.
+struct Targets {
+    scene: Texture,
+    /// Blurred back and forth at half the resolution, the first one holds
+    /// the finished bloom.
+    bloom: [Texture; 2],
+    bright_bind_group: wgpu::BindGroup,
+    /// Read the first and the second bloom image.
+    blur_bind_groups: [wgpu::BindGroup; 2],
+    composite_bind_group: wgpu::BindGroup,
+}
+
+impl Targets {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let scene = Texture::create_render_target(
+            device,
+            size.width,
+            size.height,
+            HDR_FORMAT,
+            Some("HDR scene"),
+        );
+        // a single texel when it isn't drawn anyway.
+        let (bloom_width, bloom_height) =
+            match SETTINGS.graphics.post_processing.bloom.enabled {
+                true => ((size.width / 2).max(1), (size.height / 2).max(1)),
+                false => (1, 1),
+            };
+        let bloom = std::array::from_fn(|_| {
+            Texture::create_render_target(
+                device,
+                bloom_width,
+                bloom_height,
+                HDR_FORMAT,
+                Some("Bloom"),
+            )
+        });
+        let bind_group = |input: &Texture, second: &Texture| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post processing bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            input.view(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            second.view(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(
+                            input.sampler(),
+                        ),
+                    },
+                ],
+            })
+        };
+        // the second image is never the one a pass draws into.
+        let bright_bind_group = bind_group(&scene, &bloom[1]);
+        let blur_bind_groups =
+            [bind_group(&bloom[0], &scene), bind_group(&bloom[1], &scene)];
+        let composite_bind_group = bind_group(&scene, &bloom[0]);
+        Self {
+            scene,
+            bloom,
+            bright_bind_group,
+            blur_bind_groups,
+            composite_bind_group,
+        }
+    }
+}
+
+/// Draws the scene into an HDR image, adds bloom and tonemaps it onto the
+/// screen.
+pub struct PostProcessing {
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: PostProcessingPipelines,
+    targets: Targets,
+}
+
+impl PostProcessing {
+    pub fn new(
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        color_format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        let uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Post processing uniform"),
+                usage: wgpu::BufferUsages::UNIFORM,
+                contents: bytemuck::cast_slice(
+                    &[PostProcessingUniform::new()],
+                ),
+            });
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Post processing bind group layout"),
+                entries: &POST_PROCESSING_LAYOUT,
+            },
+        );
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Post processing pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let pipelines =
+            create_pipelines(device, &pipeline_layout, color_format)?;
+        let targets =
+            Targets::new(device, &bind_group_layout, &uniform_buffer, size);
+        Ok(Self {
+            uniform_buffer,
+            bind_group_layout,
+            pipeline_layout,
+            pipelines,
+            targets,
+        })
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        self.targets = Targets::new(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            size,
+        );
+    }
+
+    /// Where the scene is drawn into before `draw`.
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        self.targets.scene.view()
+    }
+
+    /// Recompiles `post_processing.wgsl`, see `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+    ) -> Result<PostProcessingPipelines> {
+        create_pipelines(device, &self.pipeline_layout, color_format)
+    }
+
+    pub fn set_pipelines(&mut self, pipelines: PostProcessingPipelines) {
+        self.pipelines = pipelines;
+    }
+
+    /// Draws the post processed scene into `view`.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let targets = &self.targets;
+        let bloom = &SETTINGS.graphics.post_processing.bloom;
+        if bloom.enabled {
+            fullscreen_pass(
+                encoder,
+                targets.bloom[0].view(),
+                &self.pipelines.bright,
+                &targets.bright_bind_group,
+            );
+            for _ in 0..bloom.blur_passes {
+                fullscreen_pass(
+                    encoder,
+                    targets.bloom[1].view(),
+                    &self.pipelines.blur_horizontal,
+                    &targets.blur_bind_groups[0],
+                );
+                fullscreen_pass(
+                    encoder,
+                    targets.bloom[0].view(),
+                    &self.pipelines.blur_vertical,
+                    &targets.blur_bind_groups[1],
+                );
+            }
+        }
+        fullscreen_pass(
+            encoder,
+            view,
+            &self.pipelines.composite,
+            &targets.composite_bind_group,
+        );
+    }
+}
+
+/// Runs `pipeline` once over the whole of `view`.
+pub fn fullscreen_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+) {
+    let mut render_pass =
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post processing render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+) -> Result<PostProcessingPipelines> {
+    let shader = load_shader_module(device, "post_processing", &[])?;
+    let pipeline = |format, fragment_entry| {
+        create_render_pipeline(
+            device,
+            &PipelineOptions {
+                layout,
+                shader: &shader,
+                vertex_entry: "vs_fullscreen",
+                fragment_entry,
+                vertex_layouts: &[],
+                color_format: Some(format),
+                depth_format: None,
+                sample_count: 1,
+                blend: wgpu::BlendState::REPLACE,
+                depth_write_enabled: false,
+            },
+        )
+    };
+    Ok(PostProcessingPipelines {
+        bright: pipeline(HDR_FORMAT, "fs_bright"),
+        blur_horizontal: pipeline(HDR_FORMAT, "fs_blur_horizontal"),
+        blur_vertical: pipeline(HDR_FORMAT, "fs_blur_vertical"),
+        composite: pipeline(color_format, "fs_composite"),
+    })
+}
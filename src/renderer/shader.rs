@@ -1,45 +1,34 @@
+mod preprocessor;
+mod validation;
+
 use std::path::Path;
 
 use color_eyre::eyre::Context;
 use color_eyre::Result;
-use lazy_static::lazy_static;
-use regex::{Captures, Regex};
 
-lazy_static! {
-    static ref FIND_INTERPOLATION: Regex = Regex::new(r"\$\{(.+?)\}").unwrap();
-}
+use self::preprocessor::preprocess;
+pub use self::validation::validate_shaders;
 
+/// Loads `shaders/<shader_name>.wgsl`, see [`preprocess`] for the supported
+/// directives.
 pub fn load_shader_module(
     device: &wgpu::Device,
     shader_name: &str,
-    interpolation: &[(&str, &str)],
+    defines: &[(&str, &str)],
 ) -> Result<wgpu::ShaderModule> {
-    let path = Path::new("assets/shaders")
+    let path = Path::new("shaders")
         .join(shader_name)
         .with_extension("wgsl");
-    let raw_shader_source =
-        std::fs::read_to_string(path).wrap_err_with(|| {
-            format!("Failure loading shader \"{shader_name}\"")
-        })?;
-    let shader_source = FIND_INTERPOLATION.replace_all(
-        &raw_shader_source,
-        |captures: &Captures| {
-            interpolation
-                .iter()
-                .filter_map(|(key, value)| {
-                    (*key == &captures[1]).then_some(*value)
-                })
-                .next()
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Missing interpolation for key \"{}\"",
-                        &captures[1]
-                    )
-                })
-        },
-    );
+    let shader = preprocess(&path, defines).wrap_err_with(|| {
+        format!("Failure loading shader \"{shader_name}\"")
+    })?;
+    // validating here turns shader errors into a readable error instead of
+    // a wgpu panic.
+    shader.validate().wrap_err_with(|| {
+        format!("Failure validating shader \"{shader_name}\"")
+    })?;
     Ok(device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some(shader_name),
-        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        source: wgpu::ShaderSource::Wgsl(shader.source.into()),
     }))
 }
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{bail, eyre, Context};
+use color_eyre::Result;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::assets::ASSETS;
+
+lazy_static! {
+    static ref FIND_INTERPOLATION: Regex = Regex::new(r"\$\{(.+?)\}").unwrap();
+}
+
+/// Includes nested deeper than this are assumed to be an include cycle.
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// An open `#ifdef` or `#ifndef` block.
+struct Conditional {
+    /// Whether the lines of the current branch are kept.
+    active: bool,
+    /// Whether the enclosing block is kept, `#else` can't enable lines
+    /// in a disabled block.
+    parent_active: bool,
+    in_else: bool,
+    line: usize,
+}
+
+/// WGSL source after preprocessing, remembering where each line came from.
+pub struct PreprocessedShader {
+    pub source: String,
+    /// The file and 1-based line number of each line of `source`.
+    lines: Vec<(PathBuf, usize)>,
+}
+
+impl PreprocessedShader {
+    /// Maps a 1-based line of the preprocessed source back to its file.
+    pub fn original_location(&self, line: u32) -> Option<(&Path, usize)> {
+        self.lines
+            .get((line as usize).checked_sub(1)?)
+            .map(|(path, line)| (path.as_path(), *line))
+    }
+
+    /// The original `file:line:column` of a position in `source`.
+    pub fn describe_location(
+        &self,
+        location: naga::SourceLocation,
+    ) -> Option<String> {
+        let (path, line) = self.original_location(location.line_number)?;
+        Some(format!(
+            "{}:{line}:{}",
+            path.display(),
+            location.line_position
+        ))
+    }
+
+    fn describe_error(
+        &self,
+        location: Option<naga::SourceLocation>,
+        message: &str,
+        emitted: String,
+    ) -> color_eyre::Report {
+        match location.and_then(|x| self.describe_location(x)) {
+            Some(position) => eyre!("{position}: {message}\n{emitted}"),
+            None => eyre!("{message}\n{emitted}"),
+        }
+    }
+
+    /// Parses and validates the shader, pointing errors at the original
+    /// file and line.
+    pub fn validate(&self) -> Result<(naga::Module, naga::valid::ModuleInfo)> {
+        let module =
+            naga::front::wgsl::parse_str(&self.source).map_err(|error| {
+                self.describe_error(
+                    error.location(&self.source),
+                    error.message(),
+                    error.emit_to_string(&self.source),
+                )
+            })?;
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .map_err(|error| {
+            self.describe_error(
+                error.location(&self.source),
+                &error.to_string(),
+                error.emit_to_string(&self.source),
+            )
+        })?;
+        Ok((module, info))
+    }
+}
+
+struct Preprocessor<'a> {
+    /// Reads a file, `ASSETS` outside of tests.
+    read: &'a dyn Fn(&Path) -> Result<String>,
+    defines: HashMap<String, String>,
+    output: PreprocessedShader,
+    include_depth: usize,
+}
+
+impl Preprocessor<'_> {
+    fn process_file(&mut self, path: &Path) -> Result<()> {
+        if self.include_depth > MAX_INCLUDE_DEPTH {
+            bail!("Includes nested too deep at {path:?}, is there a cycle?");
+        }
+        let source = (self.read)(path)
+            .wrap_err_with(|| format!("Failed to read shader {path:?}"))?;
+        let mut conditionals: Vec<Conditional> = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let active = conditionals.last().is_none_or(|x| x.active);
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    let line = self.interpolate(line).wrap_err_with(|| {
+                        format!("In {path:?}:{line_number}")
+                    })?;
+                    self.output.source.push_str(&line);
+                    self.output.source.push('\n');
+                    self.output.lines.push((path.to_path_buf(), line_number));
+                }
+                continue;
+            };
+            let (name, argument) = directive
+                .trim()
+                .split_once(char::is_whitespace)
+                .map_or((directive.trim(), ""), |(name, argument)| {
+                    (name, argument.trim())
+                });
+            match name {
+                "ifdef" | "ifndef" => {
+                    let defined = self.defines.contains_key(argument);
+                    conditionals.push(Conditional {
+                        active: active && defined == (name == "ifdef"),
+                        parent_active: active,
+                        in_else: false,
+                        line: line_number,
+                    });
+                }
+                "else" => match conditionals.last_mut() {
+                    Some(conditional) if !conditional.in_else => {
+                        conditional.active =
+                            conditional.parent_active && !conditional.active;
+                        conditional.in_else = true;
+                    }
+                    _ => bail!("{path:?}:{line_number}: unexpected #else"),
+                },
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        bail!("{path:?}:{line_number}: unexpected #endif");
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let (key, value) = argument
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((argument, ""));
+                    self.defines
+                        .insert(key.to_string(), value.trim().to_string());
+                }
+                "undef" => {
+                    self.defines.remove(argument);
+                }
+                "include" => {
+                    let Some(name) = argument
+                        .strip_prefix('"')
+                        .and_then(|x| x.strip_suffix('"'))
+                    else {
+                        bail!(
+                            "{path:?}:{line_number}: expected #include \
+                             \"file.wgsl\""
+                        );
+                    };
+                    // includes are relative to the including file.
+                    let include_path = path.parent().unwrap().join(name);
+                    self.include_depth += 1;
+                    self.process_file(&include_path).wrap_err_with(|| {
+                        format!("Included from {path:?}:{line_number}")
+                    })?;
+                    self.include_depth -= 1;
+                }
+                _ => {
+                    bail!("{path:?}:{line_number}: unknown directive #{name}")
+                }
+            }
+        }
+        if let Some(conditional) = conditionals.last() {
+            bail!("{path:?}:{}: missing #endif", conditional.line);
+        }
+        Ok(())
+    }
+
+    /// Replaces `${key}` with the value of the define `key`.
+    fn interpolate(&self, line: &str) -> Result<String> {
+        let mut missing = None;
+        let line =
+            FIND_INTERPOLATION.replace_all(line, |captures: &Captures| {
+                match self.defines.get(&captures[1]) {
+                    Some(value) => value.clone(),
+                    None => {
+                        missing.get_or_insert_with(|| captures[1].to_string());
+                        String::new()
+                    }
+                }
+            });
+        match missing {
+            Some(key) => bail!("Missing define for \"${{{key}}}\""),
+            None => Ok(line.into_owned()),
+        }
+    }
+}
+
+/// Preprocesses the shader at `path`, which supports these directives:
+///
+/// - `#include "file.wgsl"` pastes a file, relative to the current one.
+/// - `#define NAME value` and `#undef NAME`, `value` may be empty.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif`, which also make
+///   include guards possible.
+///
+/// `${NAME}` is replaced by the value of a define. `defines` are set before
+/// the first line.
+pub fn preprocess(
+    path: &Path,
+    defines: &[(&str, &str)],
+) -> Result<PreprocessedShader> {
+    preprocess_with(path, defines, &|path| ASSETS.read_to_string(path))
+}
+
+fn preprocess_with(
+    path: &Path,
+    defines: &[(&str, &str)],
+    read: &dyn Fn(&Path) -> Result<String>,
+) -> Result<PreprocessedShader> {
+    let mut preprocessor = Preprocessor {
+        read,
+        defines: defines
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        output: PreprocessedShader {
+            source: String::new(),
+            lines: Vec::new(),
+        },
+        include_depth: 0,
+    };
+    preprocessor.process_file(path)?;
+    Ok(preprocessor.output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Preprocesses `main.wgsl` from the given files.
+    fn run(
+        files: &[(&str, &str)],
+        defines: &[(&str, &str)],
+    ) -> Result<PreprocessedShader> {
+        let files = files
+            .iter()
+            .map(|(name, source)| (Path::new(name).to_path_buf(), *source))
+            .collect::<HashMap<_, _>>();
+        preprocess_with(Path::new("main.wgsl"), defines, &|path| {
+            files
+                .get(path)
+                .map(|source| source.to_string())
+                .ok_or_else(|| eyre!("{path:?} not found"))
+        })
+    }
+
+    fn lines(shader: &PreprocessedShader) -> Vec<&str> {
+        shader.source.lines().collect()
+    }
+
+    /// The full message of an error, including its context.
+    fn error(result: Result<PreprocessedShader>) -> String {
+        format!("{:#}", result.err().expect("expected an error"))
+    }
+
+    const NESTED: &str = "\
+#ifdef A
+a
+#ifndef B
+a not b
+#else
+a and b
+#endif
+#else
+not a
+#ifdef B
+not a but b
+#endif
+#endif
+end";
+
+    #[test]
+    fn nested_conditionals() {
+        let files = [("main.wgsl", NESTED)];
+        let shader = run(&files, &[("A", "")]).unwrap();
+        assert_eq!(lines(&shader), ["a", "a not b", "end"]);
+        let shader = run(&files, &[("A", ""), ("B", "")]).unwrap();
+        assert_eq!(lines(&shader), ["a", "a and b", "end"]);
+        let shader = run(&files, &[("B", "")]).unwrap();
+        assert_eq!(lines(&shader), ["not a", "not a but b", "end"]);
+        // the #else of a block inside a disabled one stays disabled.
+        let shader = run(&files, &[]).unwrap();
+        assert_eq!(lines(&shader), ["not a", "end"]);
+    }
+
+    #[test]
+    fn defines_and_interpolation() {
+        let source = "\
+#define SIZE 4
+#ifdef SIZE
+let size = ${SIZE};
+#endif
+#undef SIZE
+#ifdef SIZE
+unreachable
+#endif
+let count = ${COUNT};";
+        let shader = run(&[("main.wgsl", source)], &[("COUNT", "2")]).unwrap();
+        assert_eq!(lines(&shader), ["let size = 4;", "let count = 2;"]);
+        let message = error(run(&[("main.wgsl", source)], &[]));
+        assert!(message.contains("main.wgsl\":9"), "{message}");
+        assert!(message.contains("${COUNT}"), "{message}");
+    }
+
+    #[test]
+    fn nested_includes_keep_their_lines() {
+        let files = [
+            (
+                "main.wgsl",
+                "#include \"a.wgsl\"\nmain\n#include \"a.wgsl\"",
+            ),
+            (
+                "a.wgsl",
+                "#ifndef A\n#define A\na\n#include \"lib/b.wgsl\"\n#endif",
+            ),
+            // relative to the including file.
+            ("lib/b.wgsl", "// b\nb"),
+        ];
+        let shader = run(&files, &[]).unwrap();
+        // the include guard keeps the second include of a.wgsl out.
+        assert_eq!(lines(&shader), ["a", "// b", "b", "main"]);
+        let location = |line| {
+            let (path, line) = shader.original_location(line).unwrap();
+            (path.to_str().unwrap(), line)
+        };
+        assert_eq!(location(1), ("a.wgsl", 3));
+        assert_eq!(location(3), ("lib/b.wgsl", 2));
+        assert_eq!(location(4), ("main.wgsl", 2));
+        assert_eq!(shader.original_location(5), None);
+        assert_eq!(shader.original_location(0), None);
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let files = [
+            ("main.wgsl", "#include \"a.wgsl\""),
+            ("a.wgsl", "a\n#include \"b.wgsl\""),
+            ("b.wgsl", "\n\nb\n#include \"a.wgsl\""),
+        ];
+        let message = error(run(&files, &[]));
+        assert!(message.contains("cycle"), "{message}");
+        assert!(message.contains("Included from \"b.wgsl\":4"), "{message}");
+        let message = error(run(&[("main.wgsl", "#include \"x.wgsl\"")], &[]));
+        assert!(message.contains("Included from \"main.wgsl\":1"));
+    }
+
+    #[test]
+    fn directive_errors_point_at_their_line() {
+        let cases = [
+            ("a\n#endif", "\"main.wgsl\":2: unexpected #endif"),
+            ("#ifdef A\n#else\n#else\n#endif", ":3: unexpected #else"),
+            ("a\n#ifdef A\nb\n#ifdef B\n#endif", ":2: missing #endif"),
+            ("\n\n  #pragma once", ":3: unknown directive #pragma"),
+            ("#include a.wgsl", ":1: expected #include"),
+        ];
+        for (source, expected) in cases {
+            let message = error(run(&[("main.wgsl", source)], &[]));
+            assert!(message.contains(expected), "{message}");
+        }
+    }
+
+    #[test]
+    fn validation_errors_point_at_the_original_line() {
+        let files = [
+            ("main.wgsl", "#include \"a.wgsl\"\nfn main() {}"),
+            (
+                "a.wgsl",
+                "// a\nfn broken() -> f32 {\n    return missing;\n}",
+            ),
+        ];
+        let shader = run(&files, &[]).unwrap();
+        let message = format!("{:#}", shader.validate().unwrap_err());
+        assert!(message.starts_with("a.wgsl:3:"), "{message}");
+    }
+}
@@ -0,0 +1,221 @@
+use std::path::Path;
+
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+
+use super::preprocess;
+use crate::assets::ASSETS;
+use crate::renderer::layouts::shader_pipelines;
+
+const SHADER_DIRECTORY: &str = "shaders";
+
+fn stage_flag(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+/// Whether a shader variable can be bound to a layout entry of this type.
+fn binding_type_matches(
+    module: &naga::Module,
+    variable: &naga::GlobalVariable,
+    binding_type: &wgpu::BindingType,
+) -> bool {
+    use naga::{AddressSpace, ImageClass, ImageDimension, TypeInner};
+    use wgpu::{BindingType, BufferBindingType, TextureViewDimension};
+
+    match (
+        variable.space,
+        &module.types[variable.ty].inner,
+        binding_type,
+    ) {
+        (
+            AddressSpace::Uniform,
+            _,
+            BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                ..
+            },
+        ) => true,
+        (
+            AddressSpace::Storage { access },
+            _,
+            BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                ..
+            },
+        ) => *read_only != access.contains(naga::StorageAccess::STORE),
+        (
+            AddressSpace::Handle,
+            TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+            BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            },
+        ) => {
+            let dimension = match (dim, arrayed) {
+                (ImageDimension::D1, false) => TextureViewDimension::D1,
+                (ImageDimension::D2, false) => TextureViewDimension::D2,
+                (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+                (ImageDimension::D3, false) => TextureViewDimension::D3,
+                (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+                (ImageDimension::Cube, true) => {
+                    TextureViewDimension::CubeArray
+                }
+                _ => return false,
+            };
+            let class_matches = match (class, sample_type) {
+                (
+                    ImageClass::Sampled { kind, multi },
+                    wgpu::TextureSampleType::Float { .. },
+                ) => *kind == naga::ScalarKind::Float && multi == multisampled,
+                (
+                    ImageClass::Sampled { kind, multi },
+                    wgpu::TextureSampleType::Sint,
+                ) => *kind == naga::ScalarKind::Sint && multi == multisampled,
+                (
+                    ImageClass::Sampled { kind, multi },
+                    wgpu::TextureSampleType::Uint,
+                ) => *kind == naga::ScalarKind::Uint && multi == multisampled,
+                (
+                    ImageClass::Depth { multi },
+                    wgpu::TextureSampleType::Depth,
+                ) => multi == multisampled,
+                _ => false,
+            };
+            dimension == *view_dimension && class_matches
+        }
+        (
+            AddressSpace::Handle,
+            TypeInner::Sampler { comparison },
+            BindingType::Sampler(sampler_type),
+        ) => {
+            *comparison
+                == (*sampler_type == wgpu::SamplerBindingType::Comparison)
+        }
+        _ => false,
+    }
+}
+
+/// Checks that every resource the entry points use is in the bind group
+/// layouts, with a matching type and visibility.
+fn check_bindings(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    bind_groups: &[Vec<wgpu::BindGroupLayoutEntry>],
+    describe_span: impl Fn(naga::Span) -> String,
+) -> Result<()> {
+    for (handle, variable) in module.global_variables.iter() {
+        let Some(binding) = &variable.binding else {
+            continue;
+        };
+        let name = variable.name.as_deref().unwrap_or("<unnamed>");
+        let position = describe_span(module.global_variables.get_span(handle));
+        let used_stages = module
+            .entry_points
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                !info.get_entry_point(*index)[handle].is_empty()
+            })
+            .fold(wgpu::ShaderStages::NONE, |stages, (_, entry_point)| {
+                stages | stage_flag(entry_point.stage)
+            });
+        if used_stages.is_empty() {
+            continue;
+        }
+        let Some(entry) = bind_groups
+            .get(binding.group as usize)
+            .and_then(|x| x.iter().find(|x| x.binding == binding.binding))
+        else {
+            bail!(
+                "{position}: `{name}` uses @group({}) @binding({}), which is \
+                 not in the bind group layouts",
+                binding.group,
+                binding.binding
+            );
+        };
+        if !binding_type_matches(module, variable, &entry.ty) {
+            bail!(
+                "{position}: the type of `{name}` doesn't match the layout \
+                 entry {:?}",
+                entry.ty
+            );
+        }
+        if !entry.visibility.contains(used_stages) {
+            bail!(
+                "{position}: `{name}` is used in {used_stages:?}, but only \
+                 visible to {:?}",
+                entry.visibility
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Preprocesses and validates every shader in the `shaders` directory of
+/// the built-in assets and the resource packs, without a GPU. Shaders used
+/// by a pipeline are checked against its bind group layouts, once for each
+/// set of defines they are loaded with.
+pub fn validate_shaders() -> Result<()> {
+    let shaders = ASSETS
+        .list(Path::new(SHADER_DIRECTORY))?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|x| x == "wgsl"));
+    let pipelines = shader_pipelines();
+
+    let mut checked = 0;
+    let mut failed = 0;
+    for path in shaders {
+        let shader_name = path
+            .strip_prefix(SHADER_DIRECTORY)?
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut variants = pipelines
+            .iter()
+            .filter(|x| x.shader_name == shader_name)
+            .map(|x| (x.defines, Some(&x.bind_groups)))
+            .collect::<Vec<_>>();
+        // files only meant to be included still have to be valid WGSL.
+        if variants.is_empty() {
+            variants.push((&[], None));
+        }
+        for (defines, bind_groups) in variants {
+            checked += 1;
+            let result = preprocess(&path, defines).and_then(|shader| {
+                let (module, info) = shader.validate()?;
+                if let Some(bind_groups) = bind_groups {
+                    check_bindings(&module, &info, bind_groups, |span| {
+                        shader
+                            .describe_location(span.location(&shader.source))
+                            .unwrap_or_else(|| format!("{}", path.display()))
+                    })?;
+                }
+                Ok(())
+            });
+            if let Err(error) = result {
+                failed += 1;
+                log::error!(
+                    "Shader {} with defines {defines:?} is invalid:\n\
+                     {error:?}",
+                    path.display()
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(eyre!(
+            "{failed} of {checked} shader variants are invalid"
+        ));
+    }
+    log::info!("All {checked} shader variants are valid");
+    Ok(())
+}
@@ -0,0 +1,409 @@
+use color_eyre::Result;
+use wgpu::util::DeviceExt;
+
+use super::layouts::SHADOW_CASCADE_LAYOUT;
+use super::mesh::{Mesh, Vertex};
+use super::shader::load_shader_module;
+use super::{draw_mesh, Texture};
+use crate::settings::SETTINGS;
+use crate::world::Chunk;
+
+/// The most cascades `ShadowUniform` has room for.
+const MAX_CASCADES: usize = 4;
+/// Blends between logarithmic (1) and evenly spaced (0) cascade splits.
+const SPLIT_LAMBDA: f32 = 0.5;
+/// How far behind a cascade blocks still cast shadows into it, so
+/// mountains between the light and the camera aren't cut off.
+const CASTER_DISTANCE: f32 = 128.0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    cascades: [glm::Mat4; MAX_CASCADES],
+    texel_sizes: glm::Vec4,
+    cascade_count: u32,
+    distance: f32,
+    _pad_0: [u32; 2],
+}
+
+/// Cascaded shadow maps for the sun and moon. Each cascade covers a slice
+/// of the view frustum, the closest ones are the sharpest.
+pub struct Shadows {
+    uniform: ShadowUniform,
+    uniform_buffer: wgpu::Buffer,
+    resolution: u32,
+    /// One layer per cascade.
+    view: wgpu::TextureView,
+    layer_views: Vec<wgpu::TextureView>,
+    sampler: wgpu::Sampler,
+    cascade_buffers: Vec<wgpu::Buffer>,
+    cascade_bind_groups: Vec<wgpu::BindGroup>,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    shader_defines: &'static [(&'static str, &'static str)],
+}
+
+impl Shadows {
+    pub fn new(
+        device: &wgpu::Device,
+        block_texture_bind_group_layout: &wgpu::BindGroupLayout,
+        shader_defines: &'static [(&'static str, &'static str)],
+    ) -> Result<Self> {
+        let settings = &SETTINGS.graphics.shadows;
+        // a single texel is still bound while shadows are turned off.
+        let (cascade_count, resolution) = match settings.enabled {
+            true => (
+                settings.cascade_count.clamp(1, MAX_CASCADES as u32),
+                settings
+                    .resolution
+                    .clamp(16, device.limits().max_texture_dimension_2d),
+            ),
+            false => (0, 1),
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow map"),
+            size: wgpu::Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: cascade_count.max(1),
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..cascade_count)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow map sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let uniform = ShadowUniform {
+            cascades: [glm::identity(); MAX_CASCADES],
+            texel_sizes: glm::vec4(0.0, 0.0, 0.0, 0.0),
+            cascade_count,
+            distance: settings.distance,
+            _pad_0: [0; 2],
+        };
+        let uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Shadow uniform"),
+                usage: wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(&[uniform]),
+            });
+
+        let cascade_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shadow cascade bind group layout"),
+                entries: &SHADOW_CASCADE_LAYOUT,
+            },
+        );
+        let cascade_buffers = (0..cascade_count)
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Shadow cascade uniform"),
+                    size: std::mem::size_of::<glm::Mat4>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM
+                        | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect::<Vec<_>>();
+        let cascade_bind_groups = cascade_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Shadow cascade bind group"),
+                    layout: &cascade_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
+
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shadow pipeline layout"),
+                bind_group_layouts: &[
+                    &cascade_bind_group_layout,
+                    block_texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let pipeline =
+            create_pipeline(device, &pipeline_layout, shader_defines)?;
+
+        Ok(Self {
+            uniform,
+            uniform_buffer,
+            resolution,
+            view,
+            layer_views,
+            sampler,
+            cascade_buffers,
+            cascade_bind_groups,
+            pipeline_layout,
+            pipeline,
+            shader_defines,
+        })
+    }
+
+    pub fn uniform_buffer(&self) -> &wgpu::Buffer {
+        &self.uniform_buffer
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// Recompiles `shadow.wgsl`, see `set_pipeline`.
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+    ) -> Result<wgpu::RenderPipeline> {
+        create_pipeline(device, &self.pipeline_layout, self.shader_defines)
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: wgpu::RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Fits the cascades to the camera frustum, `light_direction` points
+    /// towards the light.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        camera_view_projection: &glm::Mat4,
+        camera_position: glm::Vec3,
+        light_direction: glm::Vec3,
+    ) {
+        let cascade_count = self.cascade_bind_groups.len();
+        if cascade_count == 0 {
+            return;
+        }
+        let inverse = glm::inverse(camera_view_projection);
+        let unproject = |x: f32, y: f32, z: f32| {
+            let position = inverse * glm::vec4(x, y, z, 1.0);
+            position.xyz() / position.w
+        };
+        let corners = [(-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (1.0, -1.0)];
+        let near_corners = corners.map(|(x, y)| unproject(x, y, 0.0));
+        let far_corners = corners.map(|(x, y)| unproject(x, y, 1.0));
+        let near_center = near_corners.iter().sum::<glm::Vec3>() / 4.0;
+        let far_center = far_corners.iter().sum::<glm::Vec3>() / 4.0;
+        let forward = glm::normalize(&(far_center - near_center));
+        let depth = |position: &glm::Vec3| {
+            glm::dot(&(position - camera_position), &forward)
+        };
+        let (near, far) = (depth(&near_center), depth(&far_center));
+        let distance = SETTINGS.graphics.shadows.distance.min(far);
+        // the point at `split` along each edge of the frustum.
+        let slice = |split: f32| {
+            let t = (split - near) / (far - near);
+            std::array::from_fn::<_, 4, _>(|i| {
+                glm::lerp(&near_corners[i], &far_corners[i], t)
+            })
+        };
+
+        let light_view = glm::look_at_lh(
+            &glm::vec3(0.0, 0.0, 0.0),
+            &-light_direction,
+            &match light_direction.y.abs() > 0.99 {
+                true => glm::vec3(0.0, 0.0, 1.0),
+                false => glm::vec3(0.0, 1.0, 0.0),
+            },
+        );
+        let mut start = near;
+        for cascade in 0..cascade_count {
+            let i = (cascade + 1) as f32 / cascade_count as f32;
+            let end = SPLIT_LAMBDA * near * (distance / near).powf(i)
+                + (1.0 - SPLIT_LAMBDA) * (near + (distance - near) * i);
+            let slice_corners = [slice(start), slice(end)].concat();
+            start = end;
+
+            // a sphere around the slice keeps the size of the cascade the
+            // same while the camera turns.
+            let center = slice_corners.iter().sum::<glm::Vec3>() / 8.0;
+            let radius = slice_corners
+                .iter()
+                .map(|x| glm::distance(x, &center))
+                .fold(0.0, f32::max)
+                .ceil();
+            let texel_size = 2.0 * radius / self.resolution as f32;
+            // moving in whole texels keeps the shadow edges from
+            // flickering while the camera moves.
+            let center =
+                light_view * glm::vec4(center.x, center.y, center.z, 1.0);
+            let center = glm::vec3(
+                (center.x / texel_size).round() * texel_size,
+                (center.y / texel_size).round() * texel_size,
+                center.z,
+            );
+            let projection = glm::ortho_lh_zo(
+                center.x - radius,
+                center.x + radius,
+                center.y - radius,
+                center.y + radius,
+                center.z - radius - CASTER_DISTANCE,
+                center.z + radius,
+            );
+            let view_projection = projection * light_view;
+            self.uniform.cascades[cascade] = view_projection;
+            self.uniform.texel_sizes[cascade] = texel_size;
+            queue.write_buffer(
+                &self.cascade_buffers[cascade],
+                0,
+                bytemuck::cast_slice(&[view_projection]),
+            );
+        }
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniform]),
+        );
+    }
+
+    /// Renders the depth of the chunk meshes at their positions into every
+    /// cascade.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        meshes: &[(glm::IVec2, &Mesh)],
+        block_texture_bind_group: &wgpu::BindGroup,
+    ) -> Result<()> {
+        for (cascade, layer_view) in self.layer_views.iter().enumerate() {
+            let mut render_pass =
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Shadow render pass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(
+                        wgpu::RenderPassDepthStencilAttachment {
+                            view: layer_view,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        },
+                    ),
+                    ..Default::default()
+                });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(
+                0,
+                &self.cascade_bind_groups[cascade],
+                &[],
+            );
+            render_pass.set_bind_group(1, block_texture_bind_group, &[]);
+            let view_projection = &self.uniform.cascades[cascade];
+            for (position, mesh) in meshes {
+                if chunk_in_cascade(view_projection, *position) {
+                    draw_mesh(&mut render_pass, mesh)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether any part of the chunk column is inside the sides of the
+/// cascade. Chunks in front of it still cast shadows into it.
+fn chunk_in_cascade(
+    view_projection: &glm::Mat4,
+    position: glm::IVec2,
+) -> bool {
+    let low = glm::vec3(
+        (position.x * Chunk::SIZE_X as i32) as f32,
+        Chunk::LOWEST_HEIGHT as f32,
+        (position.y * Chunk::SIZE_Z as i32) as f32,
+    );
+    let size = glm::vec3(
+        Chunk::SIZE_X as f32,
+        Chunk::SIZE_Y as f32,
+        Chunk::SIZE_Z as f32,
+    );
+    let (mut min, mut max) = (glm::vec2(1.0, 1.0), glm::vec2(-1.0, -1.0));
+    for corner in 0..8 {
+        let offset = glm::vec3(
+            (corner & 1) as f32,
+            (corner >> 1 & 1) as f32,
+            (corner >> 2 & 1) as f32,
+        );
+        let position = low + offset.component_mul(&size);
+        let clip = view_projection
+            * glm::vec4(position.x, position.y, position.z, 1.0);
+        min = glm::min2(&min, &clip.xy());
+        max = glm::max2(&max, &clip.xy());
+    }
+    min.x <= 1.0 && min.y <= 1.0 && max.x >= -1.0 && max.y >= -1.0
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader_defines: &[(&str, &str)],
+) -> Result<wgpu::RenderPipeline> {
+    let shader = load_shader_module(device, "shadow", shader_defines)?;
+    Ok(
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_shadow",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_shadow",
+                targets: &[],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                // pushes the depth back on slopes against shadow acne.
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        }),
+    )
+}
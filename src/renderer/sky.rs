@@ -0,0 +1,319 @@
+use color_eyre::Result;
+use image::RgbaImage;
+use wgpu::util::DeviceExt;
+
+use super::daylight::Daylight;
+use super::layouts::{SKY_LAYOUT, SKY_TEXTURE_LAYOUT};
+use super::shader::load_shader_module;
+use super::{create_render_pipeline, PipelineOptions, Texture};
+
+/// Adds the sun and moon onto the sky, so black texture backgrounds vanish.
+const ADDITIVE_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent::OVER,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    inverse_view_projection: glm::Mat4,
+    star_rotation: glm::Mat4,
+    zenith_color: glm::Vec3,
+    star_visibility: f32,
+    horizon_color: glm::Vec3,
+    sun_glow: f32,
+    sun_direction: glm::Vec3,
+    _pad_0: u32,
+    moon_direction: glm::Vec3,
+    _pad_1: u32,
+}
+
+/// The images of the sun and moon, loaded with the block textures.
+pub struct SkyTextures {
+    pub sun: RgbaImage,
+    pub moon: RgbaImage,
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    textures: &SkyTextures,
+) -> wgpu::BindGroup {
+    let texture = |image: &RgbaImage, label| {
+        Texture::from_image(device, queue, image.clone().into(), Some(label))
+    };
+    let sun_texture = texture(&textures.sun, "Sun texture");
+    let moon_texture = texture(&textures.moon, "Moon texture");
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Sky texture bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    sun_texture.view(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(
+                    moon_texture.view(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(
+                    sun_texture.sampler(),
+                ),
+            },
+        ],
+    })
+}
+
+/// Draws the sky gradient, stars, sun and moon behind everything else.
+pub struct Sky {
+    uniform: SkyUniform,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    celestial_pipeline: wgpu::RenderPipeline,
+}
+
+impl Sky {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+        textures: &SkyTextures,
+    ) -> Result<Self> {
+        let uniform = SkyUniform {
+            inverse_view_projection: glm::identity(),
+            star_rotation: glm::identity(),
+            zenith_color: glm::vec3(0.0, 0.0, 0.0),
+            star_visibility: 0.0,
+            horizon_color: glm::vec3(0.0, 0.0, 0.0),
+            sun_glow: 0.0,
+            sun_direction: glm::vec3(0.0, 1.0, 0.0),
+            _pad_0: 0,
+            moon_direction: glm::vec3(0.0, -1.0, 0.0),
+            _pad_1: 0,
+        };
+        let uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Sky uniform"),
+                usage: wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(&[uniform]),
+            });
+        let uniform_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sky bind group layout"),
+                entries: &SKY_LAYOUT,
+            },
+        );
+        let uniform_bind_group =
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Sky bind group"),
+                layout: &uniform_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+            });
+
+        let texture_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sky texture bind group layout"),
+                entries: &SKY_TEXTURE_LAYOUT,
+            },
+        );
+        let texture_bind_group = create_texture_bind_group(
+            device,
+            queue,
+            &texture_bind_group_layout,
+            textures,
+        );
+
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Sky pipeline layout"),
+                bind_group_layouts: &[
+                    camera_bind_group_layout,
+                    &uniform_bind_group_layout,
+                    &texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let (pipeline, celestial_pipeline) = create_pipelines(
+            device,
+            &pipeline_layout,
+            color_format,
+            sample_count,
+        )?;
+
+        Ok(Self {
+            uniform,
+            uniform_buffer,
+            uniform_bind_group,
+            texture_bind_group_layout,
+            texture_bind_group,
+            pipeline_layout,
+            pipeline,
+            celestial_pipeline,
+        })
+    }
+
+    pub fn set_textures(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &SkyTextures,
+    ) {
+        self.texture_bind_group = create_texture_bind_group(
+            device,
+            queue,
+            &self.texture_bind_group_layout,
+            textures,
+        );
+    }
+
+    pub fn uniform_buffer(&self) -> &wgpu::Buffer {
+        &self.uniform_buffer
+    }
+
+    /// Recompiles `sky.wgsl`, see `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<(wgpu::RenderPipeline, wgpu::RenderPipeline)> {
+        create_pipelines(
+            device,
+            &self.pipeline_layout,
+            color_format,
+            sample_count,
+        )
+    }
+
+    pub fn set_pipelines(
+        &mut self,
+        (pipeline, celestial_pipeline): (
+            wgpu::RenderPipeline,
+            wgpu::RenderPipeline,
+        ),
+    ) {
+        self.pipeline = pipeline;
+        self.celestial_pipeline = celestial_pipeline;
+    }
+
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        view_projection: &glm::Mat4,
+        daylight: &Daylight,
+    ) {
+        self.uniform.star_rotation = daylight.star_rotation;
+        self.uniform.zenith_color = daylight.zenith_color;
+        self.uniform.star_visibility = daylight.star_visibility;
+        self.uniform.horizon_color = daylight.horizon_color;
+        self.uniform.sun_glow = daylight.sun_glow;
+        self.uniform.sun_direction = daylight.sun_direction;
+        self.uniform.moon_direction = daylight.moon_direction;
+        self.set_view_projection(queue, view_projection);
+    }
+
+    /// Only moves the camera, for drawing the same sky from another view.
+    pub fn set_view_projection(
+        &mut self,
+        queue: &wgpu::Queue,
+        view_projection: &glm::Mat4,
+    ) {
+        self.uniform.inverse_view_projection = glm::inverse(view_projection);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniform]),
+        );
+    }
+
+    /// Clears `view` to the sky.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        camera_bind_group: &wgpu::BindGroup,
+    ) {
+        let mut render_pass =
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Sky render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.texture_bind_group, &[]);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.draw(0..3, 0..1);
+        render_pass.set_pipeline(&self.celestial_pipeline);
+        // one quad each for the sun and the moon.
+        render_pass.draw(0..6, 0..2);
+    }
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> Result<(wgpu::RenderPipeline, wgpu::RenderPipeline)> {
+    let shader = load_shader_module(device, "sky", &[])?;
+    let pipeline = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout,
+            shader: &shader,
+            vertex_entry: "vs_sky",
+            fragment_entry: "fs_sky",
+            vertex_layouts: &[],
+            color_format: Some(color_format),
+            depth_format: None,
+            sample_count,
+            blend: wgpu::BlendState::REPLACE,
+            depth_write_enabled: false,
+        },
+    );
+    let celestial_pipeline = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout,
+            shader: &shader,
+            vertex_entry: "vs_celestial",
+            fragment_entry: "fs_celestial",
+            vertex_layouts: &[],
+            color_format: Some(color_format),
+            depth_format: None,
+            sample_count,
+            blend: ADDITIVE_BLENDING,
+            depth_write_enabled: false,
+        },
+    );
+    Ok((pipeline, celestial_pipeline))
+}
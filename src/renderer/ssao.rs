@@ -0,0 +1,431 @@
+use color_eyre::Result;
+use wgpu::util::DeviceExt;
+
+use super::layouts::SSAO_LAYOUT;
+use super::mesh::{Mesh, Vertex};
+use super::post_processing::fullscreen_pass;
+use super::shader::load_shader_module;
+use super::{create_render_pipeline, draw_mesh, PipelineOptions, Texture};
+use crate::settings::{SsaoQuality, SETTINGS};
+
+/// The normals in rgb and the depth in a, which is why it needs the
+/// precision.
+const NORMAL_DEPTH_FORMAT: wgpu::TextureFormat =
+    wgpu::TextureFormat::Rgba32Float;
+const OCCLUSION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+impl SsaoQuality {
+    fn sample_count(self) -> u32 {
+        match self {
+            SsaoQuality::Low => 8,
+            SsaoQuality::Medium => 16,
+            SsaoQuality::High => 32,
+        }
+    }
+
+    /// The occlusion is sampled at the window size divided by this and
+    /// blurred back up to it.
+    fn resolution_divisor(self) -> u32 {
+        match self {
+            SsaoQuality::Low | SsaoQuality::Medium => 2,
+            SsaoQuality::High => 1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniform {
+    view_projection: glm::Mat4,
+    inverse_view_projection: glm::Mat4,
+    radius: f32,
+    intensity: f32,
+    sample_count: u32,
+    _pad_0: u32,
+}
+
+pub struct SsaoPipelines {
+    normals: wgpu::RenderPipeline,
+    ssao: wgpu::RenderPipeline,
+    blur: wgpu::RenderPipeline,
+}
+
+/// The images the passes draw into, recreated with the window size.
+struct Targets {
+    /// Only depth tests the normals pass, see `normal_depth`.
+    depth: Texture,
+    normal_depth: Texture,
+    /// The noisy occlusion, possibly at a lower resolution.
+    raw: Texture,
+    /// The blurred occlusion at the window size, bound to the main pass.
+    occlusion: Texture,
+    ssao_bind_group: wgpu::BindGroup,
+    blur_bind_group: wgpu::BindGroup,
+}
+
+impl Targets {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let settings = &SETTINGS.graphics.ssao;
+        let divisor = settings.quality.resolution_divisor();
+        // the occlusion is a single texel when it isn't drawn anyway.
+        let (raw_size, occlusion_size) = match settings.enabled {
+            true => (
+                (size.width / divisor, size.height / divisor),
+                (size.width, size.height),
+            ),
+            false => ((1, 1), (1, 1)),
+        };
+        let target = |width: u32, height: u32, format, label| {
+            Texture::create_render_target(
+                device,
+                width.max(1),
+                height.max(1),
+                format,
+                Some(label),
+            )
+        };
+        let depth = Texture::create_depth_texture(
+            device,
+            size.width,
+            size.height,
+            1,
+            Some("SSAO depth"),
+        );
+        let normal_depth = target(
+            size.width,
+            size.height,
+            NORMAL_DEPTH_FORMAT,
+            "SSAO normals and depth",
+        );
+        let raw = target(
+            raw_size.0,
+            raw_size.1,
+            OCCLUSION_FORMAT,
+            "SSAO raw occlusion",
+        );
+        let occlusion = target(
+            occlusion_size.0,
+            occlusion_size.1,
+            OCCLUSION_FORMAT,
+            "Ambient occlusion",
+        );
+        let bind_group = |occlusion: &Texture| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("SSAO bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            normal_depth.view(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            occlusion.view(),
+                        ),
+                    },
+                ],
+            })
+        };
+        // the occlusion read is never the one a pass draws into.
+        let ssao_bind_group = bind_group(&occlusion);
+        let blur_bind_group = bind_group(&raw);
+        Self {
+            depth,
+            normal_depth,
+            raw,
+            occlusion,
+            ssao_bind_group,
+            blur_bind_group,
+        }
+    }
+
+    /// Makes the occlusion white, so the main pass sees no occlusion
+    /// without SSAO.
+    fn clear_occlusion(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("SSAO clear encoder"),
+            });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("SSAO clear pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.occlusion.view(),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Darkens the ambient light of the scene where the terrain on screen
+/// occludes it, from a depth and normal pass drawn before the scene. The
+/// water reads the depth of that pass as well.
+pub struct Ssao {
+    uniform: SsaoUniform,
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    normals_pipeline_layout: wgpu::PipelineLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: SsaoPipelines,
+    targets: Targets,
+    shader_defines: &'static [(&'static str, &'static str)],
+}
+
+impl Ssao {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        block_texture_bind_group_layout: &wgpu::BindGroupLayout,
+        shader_defines: &'static [(&'static str, &'static str)],
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Result<Self> {
+        let settings = &SETTINGS.graphics.ssao;
+        let uniform = SsaoUniform {
+            view_projection: glm::identity(),
+            inverse_view_projection: glm::identity(),
+            radius: settings.radius.max(0.01),
+            intensity: settings.intensity.clamp(0.0, 1.0),
+            sample_count: settings.quality.sample_count(),
+            _pad_0: 0,
+        };
+        let uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("SSAO uniform"),
+                usage: wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(&[uniform]),
+            });
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("SSAO bind group layout"),
+                entries: &SSAO_LAYOUT,
+            },
+        );
+        let normals_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("SSAO normals pipeline layout"),
+                bind_group_layouts: &[
+                    camera_bind_group_layout,
+                    block_texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("SSAO pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let pipelines = create_pipelines(
+            device,
+            &normals_pipeline_layout,
+            &pipeline_layout,
+            shader_defines,
+        )?;
+        let targets =
+            Targets::new(device, &bind_group_layout, &uniform_buffer, size);
+        if !settings.enabled {
+            targets.clear_occlusion(device, queue);
+        }
+        Ok(Self {
+            uniform,
+            uniform_buffer,
+            bind_group_layout,
+            normals_pipeline_layout,
+            pipeline_layout,
+            pipelines,
+            targets,
+            shader_defines,
+        })
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        self.targets = Targets::new(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            size,
+        );
+        if !SETTINGS.graphics.ssao.enabled {
+            self.targets.clear_occlusion(device, queue);
+        }
+    }
+
+    /// The ambient occlusion for the main pass, 1 where nothing occludes
+    /// the ambient light.
+    pub fn view(&self) -> &wgpu::TextureView {
+        self.targets.occlusion.view()
+    }
+
+    /// The normals in rgb and the depth in a of the terrain at the window
+    /// size, 1 where there is only sky.
+    pub fn normal_depth_view(&self) -> &wgpu::TextureView {
+        self.targets.normal_depth.view()
+    }
+
+    /// Recompiles `normals.wgsl` and `ssao.wgsl`, see `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+    ) -> Result<SsaoPipelines> {
+        create_pipelines(
+            device,
+            &self.normals_pipeline_layout,
+            &self.pipeline_layout,
+            self.shader_defines,
+        )
+    }
+
+    pub fn set_pipelines(&mut self, pipelines: SsaoPipelines) {
+        self.pipelines = pipelines;
+    }
+
+    pub fn set_view_projection(
+        &mut self,
+        queue: &wgpu::Queue,
+        view_projection: &glm::Mat4,
+    ) {
+        self.uniform.view_projection = *view_projection;
+        self.uniform.inverse_view_projection = glm::inverse(view_projection);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniform]),
+        );
+    }
+
+    /// Renders the depth and normals of the chunk meshes and computes the
+    /// occlusion from them. The water needs the depth even without SSAO.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        meshes: &[(glm::IVec2, &Mesh)],
+        camera_bind_group: &wgpu::BindGroup,
+        block_texture_bind_group: &wgpu::BindGroup,
+    ) -> Result<()> {
+        let targets = &self.targets;
+        {
+            let mut render_pass =
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("SSAO normals render pass"),
+                    color_attachments: &[Some(
+                        wgpu::RenderPassColorAttachment {
+                            view: targets.normal_depth.view(),
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                // the depth of the sky is 1.
+                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                store: wgpu::StoreOp::Store,
+                            },
+                        },
+                    )],
+                    depth_stencil_attachment: Some(
+                        wgpu::RenderPassDepthStencilAttachment {
+                            view: targets.depth.view(),
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        },
+                    ),
+                    ..Default::default()
+                });
+            render_pass.set_pipeline(&self.pipelines.normals);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            render_pass.set_bind_group(1, block_texture_bind_group, &[]);
+            for (_, mesh) in meshes {
+                draw_mesh(&mut render_pass, mesh)?;
+            }
+        }
+        if !SETTINGS.graphics.ssao.enabled {
+            return Ok(());
+        }
+        fullscreen_pass(
+            encoder,
+            targets.raw.view(),
+            &self.pipelines.ssao,
+            &targets.ssao_bind_group,
+        );
+        fullscreen_pass(
+            encoder,
+            targets.occlusion.view(),
+            &self.pipelines.blur,
+            &targets.blur_bind_group,
+        );
+        Ok(())
+    }
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    normals_layout: &wgpu::PipelineLayout,
+    layout: &wgpu::PipelineLayout,
+    shader_defines: &[(&str, &str)],
+) -> Result<SsaoPipelines> {
+    let normals_shader =
+        load_shader_module(device, "normals", shader_defines)?;
+    let normals = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout: normals_layout,
+            shader: &normals_shader,
+            vertex_entry: "vs_normals",
+            fragment_entry: "fs_normals",
+            vertex_layouts: &[Vertex::desc()],
+            color_format: Some(NORMAL_DEPTH_FORMAT),
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: 1,
+            blend: wgpu::BlendState::REPLACE,
+            depth_write_enabled: true,
+        },
+    );
+    let shader = load_shader_module(device, "ssao", &[])?;
+    let pipeline = |fragment_entry| {
+        create_render_pipeline(
+            device,
+            &PipelineOptions {
+                layout,
+                shader: &shader,
+                vertex_entry: "vs_fullscreen",
+                fragment_entry,
+                vertex_layouts: &[],
+                color_format: Some(OCCLUSION_FORMAT),
+                depth_format: None,
+                sample_count: 1,
+                blend: wgpu::BlendState::REPLACE,
+                depth_write_enabled: false,
+            },
+        )
+    };
+    Ok(SsaoPipelines {
+        normals,
+        ssao: pipeline("fs_ssao"),
+        blur: pipeline("fs_blur"),
+    })
+}
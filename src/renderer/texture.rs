@@ -1,7 +1,9 @@
 use color_eyre::Result;
 use image::GenericImageView;
 
-fn compute_mip_level(
+use crate::assets::ASSETS;
+
+pub fn compute_mip_level(
     width: u32,
     height: u32,
     rgba: Vec<u8>,
@@ -43,9 +45,10 @@ impl Texture {
         label: Option<&str>,
         file: &std::path::Path,
     ) -> Result<Self> {
-        let file = std::fs::File::open(file)?;
-        let reader = std::io::BufReader::new(file);
-        let image = image::load(reader, image::ImageFormat::Png)?;
+        let image = image::load_from_memory_with_format(
+            &ASSETS.read(file)?,
+            image::ImageFormat::Png,
+        )?;
         Ok(Self::from_image(device, queue, image, label))
     }
 
@@ -152,10 +155,90 @@ impl Texture {
         }
     }
 
+    /// Creates a `texture_2d_array` with one layer per image, each `size`
+    /// squared.
+    pub fn from_layers(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[Vec<u8>],
+        size: u32,
+        label: Option<&str>,
+        mip_level_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers.len() as u32,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+            label,
+            view_formats: &[],
+        });
+        for (layer, rgba) in layers.iter().enumerate() {
+            let mut size = size;
+            let mut rgba = rgba.clone();
+            for mip_level in 0..mip_level_count {
+                queue.write_texture(
+                    wgpu::ImageCopyTextureBase {
+                        texture: &texture,
+                        mip_level,
+                        origin: wgpu::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer as u32,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &rgba,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * size),
+                        rows_per_image: Some(size),
+                    },
+                    wgpu::Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: 1,
+                    },
+                );
+                (size, _, rgba) = compute_mip_level(size, size, rgba);
+            }
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        // layers hold a single texture, so faces can repeat it.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            sampler,
+            view,
+        }
+    }
+
     pub fn create_depth_texture(
         device: &wgpu::Device,
         width: u32,
         height: u32,
+        sample_count: u32,
         label: Option<&str>,
     ) -> Self {
         let size = wgpu::Extent3d {
@@ -164,15 +247,23 @@ impl Texture {
             depth_or_array_layers: 1,
         };
 
+        // multisampled depth can't be bound like a regular depth texture,
+        // and the GL backend fails to create it as one.
+        let usage = match sample_count {
+            1 => {
+                wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT
+            }
+            _ => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             view_formats: &[],
         });
 
@@ -199,6 +290,48 @@ impl Texture {
         }
     }
 
+    /// A texture to draw into and sample from afterwards, with a linear
+    /// sampler clamped to its edges.
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view =
+            texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
     pub fn texture(&self) -> &wgpu::Texture {
         &self.texture
     }
@@ -0,0 +1,236 @@
+use std::path::Path;
+
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use serde_derive::Deserialize;
+
+use super::block_textures::BlockTextures;
+use super::texture::compute_mip_level;
+use super::texture_atlas::pad_sprite;
+use crate::assets::ASSETS;
+use crate::world::WorldClock;
+
+fn default_frame_time() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct AnimationMetadataFile {
+    animation: AnimationMetadata,
+}
+
+#[derive(Deserialize)]
+struct AnimationMetadata {
+    #[serde(default = "default_frame_time", rename = "frametime")]
+    frame_time: u32,
+    frames: Option<Vec<FrameEntry>>,
+    #[serde(default)]
+    interpolate: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameEntry {
+    Index(u32),
+    Timed { index: u32, time: u32 },
+}
+
+/// A block texture that cycles through the frames of a vertical
+/// strip image.
+#[derive(Clone)]
+pub struct TextureAnimation {
+    texture_index: u32,
+    size: u32,
+    /// The strip images, each `size` squared.
+    images: Vec<Vec<u8>>,
+    /// The strip image and duration in ticks of each frame.
+    frames: Vec<(usize, u32)>,
+    interpolate: bool,
+}
+
+impl TextureAnimation {
+    /// Creates the animation for a strip of `images`, reading the optional
+    /// `.mcmeta` file next to the texture.
+    pub fn load(
+        texture_path: &Path,
+        texture_index: u32,
+        size: u32,
+        images: Vec<Vec<u8>>,
+    ) -> Result<Self> {
+        let metadata_path = texture_path.with_extension("png.mcmeta");
+        let metadata = match ASSETS.read_optional(&metadata_path)? {
+            Some(content) => {
+                serde_json::from_slice::<AnimationMetadataFile>(&content)
+                    .wrap_err_with(|| {
+                        format!("Failed to parse {metadata_path:?}")
+                    })?
+                    .animation
+            }
+            None => AnimationMetadata {
+                frame_time: default_frame_time(),
+                frames: None,
+                interpolate: false,
+            },
+        };
+
+        let frames = match metadata.frames {
+            Some(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    FrameEntry::Index(index) => {
+                        (index as usize, metadata.frame_time)
+                    }
+                    FrameEntry::Timed { index, time } => {
+                        (index as usize, time)
+                    }
+                })
+                .collect(),
+            None => (0..images.len())
+                .map(|index| (index, metadata.frame_time))
+                .collect::<Vec<_>>(),
+        };
+        if let Some((index, _)) =
+            frames.iter().find(|(index, _)| *index >= images.len())
+        {
+            bail!(
+                "Animation {metadata_path:?} uses frame {index}, but the \
+                 texture only has {} frames",
+                images.len()
+            );
+        }
+        if frames.is_empty() || frames.iter().any(|(_, time)| *time == 0) {
+            bail!("Animation {metadata_path:?} has no or zero length frames");
+        }
+        Ok(Self {
+            texture_index,
+            size,
+            images,
+            frames,
+            interpolate: metadata.interpolate,
+        })
+    }
+
+    fn duration(&self) -> u32 {
+        self.frames.iter().map(|(_, time)| time).sum()
+    }
+
+    /// Returns the frame shown after `ticks` and how far it has progressed
+    /// towards the next one.
+    fn frame(&self, ticks: u64) -> (usize, f32) {
+        let mut time = (ticks % self.duration() as u64) as u32;
+        let mut frame = 0;
+        while frame + 1 < self.frames.len() && time >= self.frames[frame].1 {
+            time -= self.frames[frame].1;
+            frame += 1;
+        }
+        (frame, time as f32 / self.frames[frame].1 as f32)
+    }
+
+    fn image(&self, frame: usize, progress: f32) -> Vec<u8> {
+        let image = &self.images[self.frames[frame].0];
+        if !self.interpolate {
+            return image.clone();
+        }
+        let next_image =
+            &self.images[self.frames[(frame + 1) % self.frames.len()].0];
+        image
+            .iter()
+            .zip(next_image)
+            .map(|(a, b)| {
+                (*a as f32 * (1.0 - progress) + *b as f32 * progress) as u8
+            })
+            .collect()
+    }
+}
+
+/// Uploads the current frames of all animated block textures.
+pub struct TextureAnimator {
+    animations: Vec<TextureAnimation>,
+    current: Vec<Option<(usize, u8)>>,
+    /// Where each animation is written to and the size of that region.
+    slots: Vec<(glm::UVec3, glm::UVec2)>,
+}
+
+impl TextureAnimator {
+    pub fn new(textures: &BlockTextures) -> Self {
+        let animations = textures.animations().to_vec();
+        Self {
+            current: vec![None; animations.len()],
+            slots: animations
+                .iter()
+                .map(|x| textures.slot(x.texture_index))
+                .collect(),
+            animations,
+        }
+    }
+
+    /// Shows the frames at the current tick of `clock`, so animations run
+    /// at the speed of the world.
+    pub fn update(
+        &mut self,
+        clock: &WorldClock,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) {
+        let ticks = clock.ticks();
+        for ((animation, current), (slot, slot_size)) in self
+            .animations
+            .iter()
+            .zip(&mut self.current)
+            .zip(&self.slots)
+        {
+            let (frame, progress) = animation.frame(ticks);
+            // interpolated animations change every few frames, others only
+            // when the frame changes.
+            let key = match animation.interpolate {
+                true => (frame, (progress * 255.0) as u8),
+                false => (frame, 0),
+            };
+            if *current == Some(key) {
+                continue;
+            }
+            *current = Some(key);
+
+            let mut image = animation.image(frame, progress);
+            // atlas padding has to follow the frame to keep mips from
+            // bleeding.
+            if *slot_size != glm::vec2(animation.size, animation.size) {
+                image = pad_sprite(
+                    &image,
+                    animation.size,
+                    animation.size,
+                    slot_size.x,
+                    slot_size.y,
+                );
+            }
+            let (mut width, mut height) = (slot_size.x, slot_size.y);
+            for mip_level in 0..texture.mip_level_count() {
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture,
+                        mip_level,
+                        origin: wgpu::Origin3d {
+                            x: slot.x >> mip_level,
+                            y: slot.y >> mip_level,
+                            z: slot.z,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &image,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * width),
+                        rows_per_image: Some(height),
+                    },
+                    wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                );
+                (width, height, image) =
+                    compute_mip_level(width, height, image);
+            }
+        }
+    }
+}
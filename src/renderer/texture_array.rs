@@ -0,0 +1,100 @@
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use image::imageops::FilterType;
+use image::RgbaImage;
+
+use super::texture_animation::TextureAnimation;
+use super::Texture;
+use crate::assets::ASSETS;
+
+/// The smallest `max_texture_array_layers` wgpu guarantees.
+const MAX_LAYERS: usize = 256;
+
+/// Uploads every texture as its own layer of a `texture_2d_array`, which
+/// lets texture coordinates repeat across a face and keeps mipmaps from
+/// mixing textures.
+pub struct TextureArray {
+    layer_size: u32,
+    layers: Vec<Vec<u8>>,
+    animations: Vec<TextureAnimation>,
+}
+
+impl TextureArray {
+    /// All layers are `layer_size` squared, other textures are scaled to
+    /// fit.
+    pub fn new(layer_size: u32) -> Self {
+        Self {
+            layer_size,
+            layers: Vec::new(),
+            animations: Vec::new(),
+        }
+    }
+
+    pub fn layer_size(&self) -> u32 {
+        self.layer_size
+    }
+
+    pub fn animations(&self) -> &[TextureAnimation] {
+        &self.animations
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.layer_size.ilog2() + 1
+    }
+
+    fn scale(&self, image: &RgbaImage) -> Vec<u8> {
+        if image.dimensions() == (self.layer_size, self.layer_size) {
+            return image.to_vec();
+        }
+        image::imageops::resize(
+            image,
+            self.layer_size,
+            self.layer_size,
+            FilterType::Nearest,
+        )
+        .into_raw()
+    }
+
+    pub fn add_texture(&mut self, image: &RgbaImage) -> Result<u32> {
+        if self.layers.len() == MAX_LAYERS {
+            bail!("Texture array may only contain {MAX_LAYERS} textures!");
+        }
+        self.layers.push(self.scale(image));
+        Ok(self.layers.len() as u32 - 1)
+    }
+
+    /// Adds the first frame and animates the layer if there are more.
+    pub fn add_frames(
+        &mut self,
+        path: &std::path::Path,
+        frames: &[RgbaImage],
+    ) -> Result<u32> {
+        let index = self.add_texture(&frames[0])?;
+        if frames.len() > 1 {
+            let images = frames.iter().map(|x| self.scale(x)).collect();
+            // a broken animation leaves the first frame in place.
+            match TextureAnimation::load(path, index, self.layer_size, images)
+            {
+                Ok(animation) => self.animations.push(animation),
+                Err(error) => ASSETS.report_error(error),
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        label: Option<&str>,
+    ) -> Texture {
+        Texture::from_layers(
+            device,
+            queue,
+            &self.layers,
+            self.layer_size,
+            label,
+            self.mip_level_count(),
+        )
+    }
+}
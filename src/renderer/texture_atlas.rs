@@ -1,114 +1,206 @@
-use color_eyre::eyre::Context;
+use color_eyre::eyre::bail;
 use color_eyre::Result;
-use image::Rgba;
+use image::{Rgba, RgbaImage};
 
+use super::texture_animation::TextureAnimation;
 use super::Texture;
+use crate::assets::ASSETS;
 
+/// Border texels duplicated around each sprite. Slots are aligned to this, so
+/// mip levels down to a block of `PADDING` texels never mix two sprites.
+const PADDING: u32 = 8;
+/// The smallest `max_texture_dimension_2d` wgpu guarantees.
+const MAX_SIDE_LENGTH: u32 = 8192;
+
+fn align(value: u32) -> u32 {
+    value.div_ceil(PADDING) * PADDING
+}
+
+/// Surrounds an image with copies of its border texels, filling a slot of
+/// `slot_width` x `slot_height` texels.
+pub fn pad_sprite(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    slot_width: u32,
+    slot_height: u32,
+) -> Vec<u8> {
+    let clamp = |value: u32, max: u32| {
+        value.saturating_sub(PADDING).min(max - 1) as usize
+    };
+    (0..slot_height)
+        .flat_map(|y| (0..slot_width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let offset =
+                (clamp(y, height) * width as usize + clamp(x, width)) * 4;
+            data[offset..offset + 4].iter().copied()
+        })
+        .collect()
+}
+
+/// A horizontal row of slots, filled from the left.
+struct Shelf {
+    y: u32,
+    height: u32,
+    width: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Sprite {
+    /// Top left corner of the slot, the sprite starts `PADDING` texels in.
+    slot: glm::UVec2,
+    slot_size: glm::UVec2,
+    size: glm::UVec2,
+}
+
+/// Packs textures of any size into one texture, growing as needed.
 pub struct TextureAtlas {
     texture_data: Vec<u8>,
-    texture_size: u32,
-    level: u8,
-    texture_index: u32,
+    side_length: u32,
+    shelves: Vec<Shelf>,
+    sprites: Vec<Sprite>,
+    animations: Vec<TextureAnimation>,
 }
 
 impl TextureAtlas {
-    pub fn new(texture_size: u32, texture_count: u32) -> Self {
-        let level = (texture_count as f32).log(4.0).ceil() as u8;
-        log::info!("atlas level: {level}");
+    pub fn new() -> Self {
         Self {
-            texture_index: 0,
-            texture_data: vec![
-                0;
-                ((texture_size * 2_u32.pow(level as u32)).pow(2) * 4)
-                    as usize
-            ],
-            texture_size,
-            level,
+            texture_data: Vec::new(),
+            side_length: 0,
+            shelves: Vec::new(),
+            sprites: Vec::new(),
+            animations: Vec::new(),
         }
     }
 
-    fn textures_per_side(&self) -> u32 {
-        2_u32.pow(self.level as u32)
+    /// Pixel position of the top left corner of a slot in the atlas and its
+    /// size, including the padding.
+    pub fn slot(&self, index: u32) -> (glm::UVec2, glm::UVec2) {
+        let sprite = self.sprites[index as usize];
+        (sprite.slot, sprite.slot_size)
+    }
+
+    pub fn animations(&self) -> &[TextureAnimation] {
+        &self.animations
     }
 
-    fn side_length(&self) -> u32 {
-        self.textures_per_side() * self.texture_size
+    pub fn mip_level_count(&self) -> u32 {
+        (PADDING.ilog2() + 1).min(self.side_length.max(1).ilog2() + 1)
     }
 
     pub fn get_texture_coordinates(
         &self,
         index: u32,
     ) -> (glm::Vec2, glm::Vec2) {
-        assert!(index < self.texture_index);
-        let textures_per_side = self.textures_per_side();
-        let tile_x = (index % textures_per_side) as f32;
-        let tile_y = (index / textures_per_side) as f32;
-        let textures_per_side = textures_per_side as f32;
+        let sprite = self.sprites[index as usize];
+        let start = sprite.slot.add_scalar(PADDING);
+        let side_length = self.side_length as f32;
         (
-            glm::vec2(tile_x / textures_per_side, tile_y / textures_per_side),
+            glm::vec2(start.x as f32, start.y as f32) / side_length,
             glm::vec2(
-                (tile_x + 1.0) / textures_per_side,
-                (tile_y + 1.0) / textures_per_side,
-            ),
+                (start.x + sprite.size.x) as f32,
+                (start.y + sprite.size.y) as f32,
+            ) / side_length,
         )
     }
 
-    pub fn add_texture(&mut self, data: &[u8]) -> u32 {
-        let expected_len = self.texture_size.pow(2) * 4;
-        if data.len() != expected_len as usize {
-            panic!(
-                "Texture has wrong size: {} instead of {expected_len}!",
-                data.len()
-            )
+    /// Finds room for a slot, preferring the lowest shelf it fits on.
+    fn find_slot(&mut self, width: u32, height: u32) -> Option<glm::UVec2> {
+        let side_length = self.side_length;
+        if let Some(shelf) = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= height && shelf.width + width <= side_length
+            })
+            .min_by_key(|shelf| shelf.height)
+        {
+            let slot = glm::vec2(shelf.width, shelf.y);
+            shelf.width += width;
+            return Some(slot);
         }
-        let index = self.texture_index;
-        let textures_per_side = self.textures_per_side();
-        if index >= textures_per_side.pow(2) {
-            panic!(
-                "Atlas may only contain {} textures!",
-                textures_per_side.pow(2)
-            )
+        let y = self.shelves.last().map_or(0, |x| x.y + x.height);
+        if y + height > side_length || width > side_length {
+            return None;
         }
+        self.shelves.push(Shelf { y, height, width });
+        Some(glm::vec2(0, y))
+    }
 
-        let side_length = self.side_length();
-
-        let x_offset = (index % textures_per_side) * self.texture_size;
-        let y_offset = (index / textures_per_side) * self.texture_size;
-        let atlas_offset = (y_offset * side_length + x_offset) * 4;
+    /// Doubles the side length, keeping all slots where they are.
+    fn grow(&mut self) -> Result<()> {
+        let side_length = (self.side_length * 2).max(PADDING);
+        if side_length > MAX_SIDE_LENGTH {
+            bail!(
+                "Texture atlas would exceed {MAX_SIDE_LENGTH}x\
+                 {MAX_SIDE_LENGTH} pixels!"
+            );
+        }
+        log::info!("growing atlas to {side_length}x{side_length} pixels");
+        let mut texture_data = vec![0; (side_length.pow(2) * 4) as usize];
+        let row_length = (self.side_length * 4) as usize;
+        // the first growth has no rows to copy yet.
+        for (y, row) in self
+            .texture_data
+            .chunks_exact(row_length.max(1))
+            .enumerate()
+        {
+            let offset = y * side_length as usize * 4;
+            texture_data[offset..offset + row_length].copy_from_slice(row);
+        }
+        self.texture_data = texture_data;
+        self.side_length = side_length;
+        Ok(())
+    }
 
-        for y in 0..self.texture_size {
-            for x in 0..self.texture_size * 4 {
-                self.texture_data
-                    [(atlas_offset + y * side_length * 4 + x) as usize] =
-                    data[(y * self.texture_size * 4 + x) as usize];
+    pub fn add_texture(&mut self, image: &RgbaImage) -> Result<u32> {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            bail!("Can't add an empty texture to the atlas!");
+        }
+        let slot_width = align(width) + 2 * PADDING;
+        let slot_height = align(height) + 2 * PADDING;
+        let slot = loop {
+            if let Some(slot) = self.find_slot(slot_width, slot_height) {
+                break slot;
             }
+            self.grow()?;
+        };
+
+        let padded = pad_sprite(image, width, height, slot_width, slot_height);
+        let row_length = (slot_width * 4) as usize;
+        for (y, row) in padded.chunks_exact(row_length).enumerate() {
+            let offset = ((slot.y as usize + y) * self.side_length as usize
+                + slot.x as usize)
+                * 4;
+            self.texture_data[offset..offset + row_length]
+                .copy_from_slice(row);
         }
-        self.texture_index += 1;
-        index
-    }
-
-    pub fn add_path(&mut self, path: &std::path::Path) -> Result<u32> {
-        log::info!("adding path to atlas: {path:?}");
-        let file = std::fs::File::open(path).wrap_err_with(|| {
-            format!("Failed to add file {path:?} to atlas!")
-        })?;
-        let reader = std::io::BufReader::new(file);
-        let image = image::load(reader, image::ImageFormat::Png)
-            .wrap_err_with(|| {
-                format!("Failed to add file {path:?} to atlas!")
-            })?;
-        if image.width() != self.texture_size
-            || image.height() != self.texture_size
-        {
-            panic!(
-                "Atlas expects image of size {}x{} pixels, got {}x{} pixels!",
-                self.texture_size,
-                self.texture_size,
-                image.width(),
-                image.height()
-            )
+        self.sprites.push(Sprite {
+            slot,
+            slot_size: glm::vec2(slot_width, slot_height),
+            size: glm::vec2(width, height),
+        });
+        Ok(self.sprites.len() as u32 - 1)
+    }
+
+    /// Adds the first frame and animates the sprite if there are more.
+    pub fn add_frames(
+        &mut self,
+        path: &std::path::Path,
+        frames: &[RgbaImage],
+    ) -> Result<u32> {
+        let index = self.add_texture(&frames[0])?;
+        if frames.len() > 1 {
+            let size = frames[0].width();
+            let images = frames.iter().map(|x| x.to_vec()).collect();
+            // a broken animation leaves the first frame in place.
+            match TextureAnimation::load(path, index, size, images) {
+                Ok(animation) => self.animations.push(animation),
+                Err(error) => ASSETS.report_error(error),
+            }
         }
-        Ok(self.add_texture(&image.to_rgba8()))
+        Ok(index)
     }
 
     pub fn texture(
@@ -121,20 +213,131 @@ impl TextureAtlas {
             device,
             queue,
             &self.texture_data,
-            self.side_length(),
-            self.side_length(),
+            self.side_length,
+            self.side_length,
             label,
-            self.texture_size.ilog2() + 1,
+            self.mip_level_count(),
         )
     }
 
     pub fn save_to_image(&self, path: &std::path::Path) {
         let image = image::ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(
-            self.side_length(),
-            self.side_length(),
+            self.side_length,
+            self.side_length,
             &self.texture_data,
         )
         .unwrap();
         image.save(path).unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(atlas: &TextureAtlas, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * atlas.side_length + x) * 4) as usize;
+        atlas.texture_data[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn grow_from_empty() {
+        let mut atlas = TextureAtlas::new();
+        atlas.grow().unwrap();
+        assert_eq!(atlas.side_length, PADDING);
+        assert_eq!(atlas.texture_data.len(), (PADDING * PADDING * 4) as usize);
+    }
+
+    #[test]
+    fn grow_keeps_sprites() {
+        let mut atlas = TextureAtlas::new();
+        let image = RgbaImage::from_pixel(16, 16, Rgba([1, 2, 3, 4]));
+        atlas.add_texture(&image).unwrap();
+        let side_length = atlas.side_length;
+        atlas.grow().unwrap();
+        assert_eq!(atlas.side_length, side_length * 2);
+        assert_eq!(pixel(&atlas, PADDING, PADDING), [1, 2, 3, 4]);
+        assert_eq!(pixel(&atlas, side_length, 0), [0; 4]);
+    }
+
+    #[test]
+    fn grow_stops_at_max_side_length() {
+        let mut atlas = TextureAtlas::new();
+        atlas.side_length = MAX_SIDE_LENGTH;
+        assert!(atlas.grow().is_err());
+    }
+
+    #[test]
+    fn find_slot_fills_shelves() {
+        let mut atlas = TextureAtlas::new();
+        atlas.side_length = 64;
+        assert_eq!(atlas.find_slot(32, 16), Some(glm::vec2(0, 0)));
+        assert_eq!(atlas.find_slot(16, 16), Some(glm::vec2(32, 0)));
+        // too tall for the first shelf, starts a new one below it.
+        assert_eq!(atlas.find_slot(16, 32), Some(glm::vec2(0, 16)));
+        // fits on both shelves, the shorter one wastes less space.
+        assert_eq!(atlas.find_slot(16, 8), Some(glm::vec2(48, 0)));
+        // the first shelf is full now.
+        assert_eq!(atlas.find_slot(16, 8), Some(glm::vec2(16, 16)));
+        assert_eq!(atlas.find_slot(16, 32), Some(glm::vec2(32, 16)));
+    }
+
+    #[test]
+    fn find_slot_without_room() {
+        let mut atlas = TextureAtlas::new();
+        assert_eq!(atlas.find_slot(16, 16), None);
+        atlas.side_length = 32;
+        assert_eq!(atlas.find_slot(48, 16), None);
+        assert_eq!(atlas.find_slot(32, 32), Some(glm::vec2(0, 0)));
+        assert_eq!(atlas.find_slot(16, 16), None);
+    }
+
+    #[test]
+    fn pad_sprite_repeats_borders() {
+        // a 2x2 image with a different color in each corner.
+        let data = [
+            [1, 0, 0, 255],
+            [2, 0, 0, 255],
+            [3, 0, 0, 255],
+            [4, 0, 0, 255],
+        ]
+        .concat();
+        let side = 2 + 2 * PADDING;
+        let padded = pad_sprite(&data, 2, 2, side, side);
+        assert_eq!(padded.len(), (side * side * 4) as usize);
+        let red = |x: u32, y: u32| padded[((y * side + x) * 4) as usize];
+        // the image sits `PADDING` texels in.
+        assert_eq!(red(PADDING, PADDING), 1);
+        assert_eq!(red(PADDING + 1, PADDING + 1), 4);
+        // the corners of the slot repeat the corners of the image.
+        assert_eq!(red(0, 0), 1);
+        assert_eq!(red(side - 1, 0), 2);
+        assert_eq!(red(0, side - 1), 3);
+        assert_eq!(red(side - 1, side - 1), 4);
+        // the edges repeat the closest border texel.
+        assert_eq!(red(PADDING + 1, 0), 2);
+        assert_eq!(red(0, PADDING + 1), 3);
+    }
+
+    #[test]
+    fn sprites_are_padded_in_the_atlas() {
+        let mut atlas = TextureAtlas::new();
+        let small = RgbaImage::from_pixel(4, 4, Rgba([9, 9, 9, 9]));
+        let large = RgbaImage::from_pixel(16, 8, Rgba([7, 7, 7, 7]));
+        let first = atlas.add_texture(&small).unwrap();
+        let second = atlas.add_texture(&large).unwrap();
+        let (slot, size) = atlas.slot(first);
+        assert_eq!(slot, glm::vec2(0, 0));
+        assert_eq!(
+            size,
+            glm::vec2(align(4), align(4)).add_scalar(2 * PADDING)
+        );
+        let (slot, _) = atlas.slot(second);
+        // the slot borders are filled with the sprite's own texels.
+        assert_eq!(pixel(&atlas, slot.x, slot.y), [7; 4]);
+        assert_eq!(pixel(&atlas, size.x - 1, size.y - 1), [9; 4]);
+        // slots are aligned so mipmaps never mix two sprites.
+        assert_eq!(slot.x % PADDING, 0);
+        assert_eq!(slot.y % PADDING, 0);
+    }
+}
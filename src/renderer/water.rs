@@ -0,0 +1,363 @@
+use std::time::Instant;
+
+use color_eyre::Result;
+use wgpu::util::DeviceExt;
+
+use super::layouts::{UNDERWATER_LAYOUT, WATER_LAYOUT};
+use super::mesh::{Mesh, Vertex};
+use super::post_processing::fullscreen_pass;
+use super::shader::load_shader_module;
+use super::{create_render_pipeline, draw_mesh, PipelineOptions, Texture};
+use crate::settings::SETTINGS;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct WaterUniform {
+    inverse_view_projection: glm::Mat4,
+    color: glm::Vec3,
+    /// Seconds since the water was created, moves the waves.
+    time: f32,
+    absorption: glm::Vec3,
+    wave_strength: f32,
+    underwater_distortion: f32,
+    _pad_0: u32,
+    _pad_1: u32,
+    _pad_2: u32,
+}
+
+impl WaterUniform {
+    fn new() -> Self {
+        let settings = &SETTINGS.graphics.water;
+        Self {
+            inverse_view_projection: glm::identity(),
+            color: glm::Vec3::from(settings.color),
+            time: 0.0,
+            absorption: glm::Vec3::from(settings.absorption)
+                .map(|x| x.max(0.0)),
+            wave_strength: settings.wave_strength.max(0.0),
+            underwater_distortion: settings.underwater_distortion.max(0.0),
+            _pad_0: 0,
+            _pad_1: 0,
+            _pad_2: 0,
+        }
+    }
+}
+
+pub struct WaterPipelines {
+    surface: wgpu::RenderPipeline,
+    underwater: wgpu::RenderPipeline,
+}
+
+/// Draws the surfaces of water with waves, reflections and the absorption
+/// of the light coming through them, and the view from inside water.
+pub struct Water {
+    uniform: WaterUniform,
+    uniform_buffer: wgpu::Buffer,
+    start: Instant,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    underwater_bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    underwater_pipeline_layout: wgpu::PipelineLayout,
+    pipelines: WaterPipelines,
+    /// What the scene is drawn or tonemapped into while the camera is
+    /// underwater, recreated with the window size.
+    underwater_input: Texture,
+    underwater_bind_group: wgpu::BindGroup,
+}
+
+/// The layouts shared with the rest of the scene and the formats the water
+/// is drawn into.
+pub struct WaterScene<'a> {
+    pub camera_bind_group_layout: &'a wgpu::BindGroupLayout,
+    pub light_bind_group_layout: &'a wgpu::BindGroupLayout,
+    pub fog_bind_group_layout: &'a wgpu::BindGroupLayout,
+    /// The format the surface is drawn into, with `sample_count` samples.
+    pub scene_format: wgpu::TextureFormat,
+    /// The format of the image the underwater effect is applied to.
+    pub color_format: wgpu::TextureFormat,
+    pub sample_count: u32,
+}
+
+impl Water {
+    /// `normal_depth` is the terrain drawn by the SSAO normals pass.
+    pub fn new(
+        device: &wgpu::Device,
+        scene: &WaterScene,
+        normal_depth: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Result<Self> {
+        let uniform = WaterUniform::new();
+        let uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Water uniform"),
+                usage: wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(&[uniform]),
+            });
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Water bind group layout"),
+                entries: &WATER_LAYOUT,
+            },
+        );
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            normal_depth,
+        );
+        let underwater_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Underwater bind group layout"),
+                entries: &UNDERWATER_LAYOUT,
+            },
+        );
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Water pipeline layout"),
+                bind_group_layouts: &[
+                    scene.camera_bind_group_layout,
+                    &bind_group_layout,
+                    scene.light_bind_group_layout,
+                    scene.fog_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let underwater_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Underwater pipeline layout"),
+                bind_group_layouts: &[&underwater_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let pipelines = create_pipelines(
+            device,
+            &pipeline_layout,
+            &underwater_pipeline_layout,
+            scene.scene_format,
+            scene.color_format,
+            scene.sample_count,
+        )?;
+        let (underwater_input, underwater_bind_group) =
+            create_underwater_input(
+                device,
+                &underwater_bind_group_layout,
+                &uniform_buffer,
+                size,
+                scene.color_format,
+            );
+        Ok(Self {
+            uniform,
+            uniform_buffer,
+            start: Instant::now(),
+            bind_group_layout,
+            bind_group,
+            underwater_bind_group_layout,
+            pipeline_layout,
+            underwater_pipeline_layout,
+            pipelines,
+            underwater_input,
+            underwater_bind_group,
+        })
+    }
+
+    /// Recreates the underwater image for the window size and binds the
+    /// terrain of the resized SSAO normals pass.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        normal_depth: &wgpu::TextureView,
+    ) {
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            normal_depth,
+        );
+        (self.underwater_input, self.underwater_bind_group) =
+            create_underwater_input(
+                device,
+                &self.underwater_bind_group_layout,
+                &self.uniform_buffer,
+                size,
+                self.underwater_input.texture().format(),
+            );
+    }
+
+    /// Where the image is drawn into before `draw_underwater`.
+    pub fn underwater_input_view(&self) -> &wgpu::TextureView {
+        self.underwater_input.view()
+    }
+
+    /// Recompiles `water.wgsl` and `underwater.wgsl`, see `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+        scene_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<WaterPipelines> {
+        create_pipelines(
+            device,
+            &self.pipeline_layout,
+            &self.underwater_pipeline_layout,
+            scene_format,
+            self.underwater_input.texture().format(),
+            sample_count,
+        )
+    }
+
+    pub fn set_pipelines(&mut self, pipelines: WaterPipelines) {
+        self.pipelines = pipelines;
+    }
+
+    /// Moves the waves on and sets the view projection the depth of the
+    /// terrain is read with.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        view_projection: &glm::Mat4,
+    ) {
+        self.uniform.inverse_view_projection = glm::inverse(view_projection);
+        self.uniform.time = self.start.elapsed().as_secs_f32();
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniform]),
+        );
+    }
+
+    /// Draws `meshes` in a pass that has the camera, light and fog bound
+    /// like the block passes.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        meshes: impl Iterator<Item = &'a Mesh>,
+    ) -> Result<()> {
+        render_pass.set_pipeline(&self.pipelines.surface);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        for mesh in meshes {
+            draw_mesh(render_pass, mesh)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the image seen from inside water into `view`.
+    pub fn draw_underwater(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        fullscreen_pass(
+            encoder,
+            view,
+            &self.pipelines.underwater,
+            &self.underwater_bind_group,
+        );
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    normal_depth: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Water bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(normal_depth),
+            },
+        ],
+    })
+}
+
+fn create_underwater_input(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    size: winit::dpi::PhysicalSize<u32>,
+    color_format: wgpu::TextureFormat,
+) -> (Texture, wgpu::BindGroup) {
+    let input = Texture::create_render_target(
+        device,
+        size.width,
+        size.height,
+        color_format,
+        Some("Underwater input"),
+    );
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Underwater bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(input.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(input.sampler()),
+            },
+        ],
+    });
+    (input, bind_group)
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    underwater_layout: &wgpu::PipelineLayout,
+    scene_format: wgpu::TextureFormat,
+    color_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> Result<WaterPipelines> {
+    let shader = load_shader_module(device, "water", &[])?;
+    // writes depth, so translucent blocks behind the water aren't drawn
+    // over it.
+    let surface = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout,
+            shader: &shader,
+            vertex_entry: "vs_water",
+            fragment_entry: "fs_water",
+            vertex_layouts: &[Vertex::desc()],
+            color_format: Some(scene_format),
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count,
+            blend: wgpu::BlendState::ALPHA_BLENDING,
+            depth_write_enabled: true,
+        },
+    );
+    let underwater_shader = load_shader_module(device, "underwater", &[])?;
+    let underwater = create_render_pipeline(
+        device,
+        &PipelineOptions {
+            layout: underwater_layout,
+            shader: &underwater_shader,
+            vertex_entry: "vs_fullscreen",
+            fragment_entry: "fs_underwater",
+            vertex_layouts: &[],
+            color_format: Some(color_format),
+            depth_format: None,
+            sample_count: 1,
+            blend: wgpu::BlendState::REPLACE,
+            depth_write_enabled: false,
+        },
+    );
+    Ok(WaterPipelines {
+        surface,
+        underwater,
+    })
+}
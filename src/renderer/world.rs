@@ -5,31 +5,66 @@ use glm::{I16Vec3, IVec2, IVec3};
 use strum::IntoEnumIterator;
 use winit::dpi::Position;
 
+use super::model::{MeshContext, RenderLayer};
 use super::{GameRenderData, Mesh};
 use crate::util::BlockSide;
-use crate::world::{Block, Chunk, ChunkEvent, World};
+use crate::world::{Block, Chunk, ChunkEvent, GameData, LightChannel, World};
+
+/// The meshes of a chunk, split by how they are drawn.
+pub struct ChunkMesh {
+    /// Opaque and cutout geometry.
+    pub opaque: Mesh,
+    /// Blended geometry, sorted back to front before drawing.
+    pub translucent: Mesh,
+    /// The outside of bodies of water.
+    pub water: Mesh,
+}
+
+impl ChunkMesh {
+    pub fn update_buffers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.opaque.update_buffers(device, queue);
+        self.translucent.update_buffers(device, queue);
+        self.water.update_buffers(device, queue);
+    }
+}
 
 pub enum ChunkMeshEvent {
-    Update { position: glm::IVec2, mesh: Mesh },
+    Update {
+        position: glm::IVec2,
+        mesh: ChunkMesh,
+    },
     Unload(glm::IVec2),
 }
 
+pub enum RenderWorldEvent {
+    Chunk(ChunkEvent),
+    /// Replaces the block models and textures and remeshes every chunk.
+    Reload(GameRenderData),
+}
+
 pub struct RenderWorld {
     chunks: HashSet<glm::IVec2>,
     mesh_queue: Arc<Mutex<VecDeque<ChunkMeshEvent>>>,
     world: Arc<Mutex<World>>,
+    game_data: Arc<GameData>,
     render_data: GameRenderData,
 }
 
 impl RenderWorld {
     pub fn new(
         world: Arc<Mutex<World>>,
+        game_data: Arc<GameData>,
         render_data: GameRenderData,
     ) -> (Self, Arc<Mutex<VecDeque<ChunkMeshEvent>>>) {
         let queue = Arc::new(Mutex::new(VecDeque::new()));
         (
             Self {
                 world,
+                game_data,
                 chunks: HashSet::new(),
                 mesh_queue: queue.clone(),
                 render_data,
@@ -38,17 +73,44 @@ impl RenderWorld {
         )
     }
 
-    pub fn handle_chunk_event(&mut self, event: ChunkEvent) {
-        let mut update_chunks: Vec<glm::IVec2> = vec![];
+    /// Handles a batch of events, remeshing every chunk they touch once.
+    pub fn handle_events(
+        &mut self,
+        events: impl IntoIterator<Item = RenderWorldEvent>,
+    ) {
+        let mut update_chunks = HashSet::new();
+        for event in events {
+            match event {
+                RenderWorldEvent::Chunk(event) => {
+                    self.handle_chunk_event(event, &mut update_chunks)
+                }
+                RenderWorldEvent::Reload(render_data) => {
+                    self.render_data = render_data;
+                    update_chunks.extend(self.chunks.iter().copied());
+                }
+            }
+        }
+        self.remesh(update_chunks);
+    }
+
+    fn handle_chunk_event(
+        &mut self,
+        event: ChunkEvent,
+        update_chunks: &mut HashSet<glm::IVec2>,
+    ) {
         match event {
             ChunkEvent::Update(position) => {
                 self.chunks.insert(position);
-                update_chunks.push(position);
+                update_chunks.insert(position);
+                // neighbors mesh their borders against this chunk.
+                BlockSide::cardinal_directions().for_each(|direction| {
+                    update_chunks.insert(position + direction);
+                });
             }
             ChunkEvent::Unload(position) => {
                 self.chunks.remove(&position);
                 BlockSide::cardinal_directions().for_each(|direction| {
-                    update_chunks.push(position + direction)
+                    update_chunks.insert(position + direction);
                 });
                 self.mesh_queue
                     .lock()
@@ -56,10 +118,18 @@ impl RenderWorld {
                     .push_back(ChunkMeshEvent::Unload(position));
             }
         };
-        for chunk_position in update_chunks {
+    }
+
+    fn remesh(&self, chunks: HashSet<glm::IVec2>) {
+        for chunk_position in chunks {
             let world = self.world.lock().unwrap();
             if let Some(chunk) = world.get_chunk(chunk_position) {
-                let mesh = mesh_chunk(chunk, &self.render_data, &world);
+                let mesh = mesh_chunk(
+                    chunk,
+                    &self.render_data,
+                    &self.game_data,
+                    &world,
+                );
                 self.mesh_queue.lock().unwrap().push_back(
                     ChunkMeshEvent::Update {
                         position: chunk_position,
@@ -71,96 +141,78 @@ impl RenderWorld {
     }
 }
 
+/// Packs the brightest light at `positions` and the light `block` emits
+/// itself into `Vertex::light`.
+fn vertex_light(
+    context: &MeshContext,
+    block: Block,
+    positions: &[IVec3],
+) -> u32 {
+    let level = |channel| {
+        positions
+            .iter()
+            .map(|position| context.light(channel, *position))
+            .max()
+            .unwrap_or(0) as u32
+    };
+    let emission = context
+        .game_data()
+        .block_type(block.block_type())
+        .light_emission() as u32;
+    level(LightChannel::Block) | level(LightChannel::Sky) << 4 | emission << 8
+}
+
 pub fn mesh_chunk(
     chunk: &Chunk,
     render_data: &GameRenderData,
+    game_data: &GameData,
     world: &World,
-) -> Mesh {
-    let mut mesh = Mesh::new();
-
-    let extra_chunks = [
-        world.get_chunk(chunk.position() + BlockSide::North.flat_direction()),
-        world.get_chunk(chunk.position() + BlockSide::East.flat_direction()),
-        world.get_chunk(chunk.position() + BlockSide::South.flat_direction()),
-        world.get_chunk(chunk.position() + BlockSide::West.flat_direction()),
-    ]
-    .into_iter()
-    .flatten()
-    .collect::<Vec<_>>();
+) -> ChunkMesh {
+    let mut opaque = Mesh::new();
+    let mut translucent = Mesh::new();
+    let mut water = Mesh::new();
+    let context = MeshContext::new(chunk, world, game_data, render_data);
 
     for z in 0..Chunk::SIZE_Z {
         for y in 0..Chunk::SIZE_Y {
             for x in 0..Chunk::SIZE_X {
                 let chunk_block_position =
                     glm::vec3(x as i16, (y as i16) - 64, z as i16);
+                let position = World::get_world_position(
+                    chunk_block_position,
+                    chunk.position(),
+                );
                 let block = chunk.get_block(chunk_block_position);
                 let model_generator =
                     render_data.mesh_generator(block.block_type());
+                let mesh = match model_generator.render_layer() {
+                    RenderLayer::Opaque | RenderLayer::Cutout => &mut opaque,
+                    RenderLayer::Translucent => &mut translucent,
+                    RenderLayer::Water => &mut water,
+                };
 
-                model_generator.mesh_always(
-                    World::get_world_position(
-                        chunk_block_position,
-                        chunk.position(),
-                    ),
-                    block,
-                    &mut mesh,
+                let start = mesh.vertex_count();
+                model_generator.mesh_always(&context, position, block, mesh);
+                mesh.set_light(
+                    start,
+                    vertex_light(&context, block, &[position]),
                 );
 
                 for side in BlockSide::iter() {
-                    let side_position = World::get_world_position(
-                        chunk_block_position,
-                        chunk.position(),
-                    ) + side.direction();
-                    if World::position_in_world(side_position.cast()) {
-                        let side_block =
-                            if chunk.position_in_chunk(side_position) {
-                                Some(chunk.get_block(
-                                    World::get_chunk_position(side_position),
-                                ))
-                            } else {
-                                extra_chunks
-                                    .iter()
-                                    .flat_map(|chunk| {
-                                        chunk
-                                            .position_in_chunk(side_position)
-                                            .then(|| {
-                                                chunk.get_block(
-                                                    World::get_chunk_position(
-                                                        side_position,
-                                                    ),
-                                                )
-                                            })
-                                    })
-                                    .collect::<Vec<_>>()
-                                    .into_iter()
-                                    .next()
-                            };
-                        let side_solid = match side_block {
-                            Some(side_block) => render_data
-                                .mesh_generator(side_block.block_type())
-                                .solid_side(side_block, side.opposite()),
-                            None => true,
-                        };
-                        if !side_solid {
-                            model_generator.mesh_side(
-                                World::get_world_position(
-                                    chunk_block_position,
-                                    chunk.position(),
-                                ),
+                    let neighbor = position + side.direction();
+                    if !context.solid_side(neighbor, side.opposite()) {
+                        let start = mesh.vertex_count();
+                        model_generator
+                            .mesh_side(&context, position, block, side, mesh);
+                        // faces are lit by the light in front of them,
+                        // which for opaque blocks is all there is.
+                        mesh.set_light(
+                            start,
+                            vertex_light(
+                                &context,
                                 block,
-                                side,
-                                &mut mesh,
-                            );
-                        }
-                    } else {
-                        model_generator.mesh_side(
-                            World::get_world_position(
-                                chunk_block_position,
-                                chunk.position(),
+                                &[position, neighbor],
                             ),
-                            block,
-                            side,
-                            &mut mesh,
                         );
                     }
                 }
@@ -168,5 +220,9 @@ pub fn mesh_chunk(
         }
     }
 
-    mesh
+    ChunkMesh {
+        opaque,
+        translucent,
+        water,
+    }
 }
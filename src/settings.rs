@@ -1,9 +1,11 @@
 use std::io::ErrorKind;
+use std::path::PathBuf;
 
 use color_eyre::Result;
 use log::info;
 use once_cell::sync::Lazy;
 use serde_derive::{Deserialize, Serialize};
+use strum::EnumIter;
 
 const CONFIG_PATH: &str = "./settings.toml";
 
@@ -49,12 +51,249 @@ impl Default for LogSettings {
     }
 }
 
+/// How block textures are stored on the GPU.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, EnumIter)]
+pub enum TextureBackend {
+    /// All textures packed into a single texture.
+    #[default]
+    Atlas,
+    /// One layer of a texture array per texture, textures of other sizes
+    /// are scaled to the largest one.
+    Array,
+}
+
+/// How fog thickens between its start and end.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum FogMode {
+    Linear,
+    /// Thickens quickly near the start, faster with a higher `density`.
+    Exponential {
+        density: f32,
+    },
+}
+
+/// Fog in one kind of surroundings.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FogParameters {
+    pub mode: FogMode,
+    /// Where the fog starts and where it hides everything, as fractions of
+    /// the render distance.
+    pub start: f32,
+    pub end: f32,
+    /// Linear RGB, the fog blends into the sky if this isn't set.
+    pub color: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FogSettings {
+    pub enabled: bool,
+    pub surface: FogParameters,
+    /// Used while the camera is below a block, like in a cave.
+    pub cave: FogParameters,
+    /// Used while the camera is inside a water block.
+    pub underwater: FogParameters,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            surface: FogParameters {
+                mode: FogMode::Linear,
+                start: 0.6,
+                end: 1.0,
+                color: None,
+            },
+            cave: FogParameters {
+                mode: FogMode::Exponential { density: 3.0 },
+                start: 0.0,
+                end: 0.6,
+                color: Some([0.01, 0.01, 0.015]),
+            },
+            underwater: FogParameters {
+                mode: FogMode::Exponential { density: 2.0 },
+                start: 0.0,
+                end: 0.15,
+                color: Some([0.02, 0.1, 0.2]),
+            },
+        }
+    }
+}
+
+/// Shadows cast by the sun and moon.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Width and height of the shadow map of each cascade in texels.
+    pub resolution: u32,
+    /// How many shadow maps split up the shadow distance, 1 to 4. Closer
+    /// cascades cover less area and are sharper.
+    pub cascade_count: u32,
+    /// Blocks from the camera up to which shadows are drawn.
+    pub distance: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: 2048,
+            cascade_count: 3,
+            distance: 96.0,
+        }
+    }
+}
+
+/// Trades the look of the ambient occlusion for speed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum SsaoQuality {
+    /// 8 samples per pixel at half resolution.
+    Low,
+    /// 16 samples per pixel at half resolution.
+    Medium,
+    /// 32 samples per pixel at full resolution.
+    High,
+}
+
+/// Screen space ambient occlusion, darkening the ambient light in corners
+/// and crevices found in the depth and normals of the terrain on screen.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SsaoSettings {
+    pub enabled: bool,
+    pub quality: SsaoQuality,
+    /// Blocks around each point within which other blocks occlude it.
+    pub radius: f32,
+    /// How dark fully occluded points get, 1 takes away all ambient light.
+    pub intensity: f32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quality: SsaoQuality::Medium,
+            radius: 1.0,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// The look of water surfaces and of the view from inside water.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaterSettings {
+    /// Linear RGB of the light deep water scatters back.
+    pub color: [f32; 3],
+    /// How quickly red, green and blue light fade with each block of water
+    /// the view passes through, red first like in real water.
+    pub absorption: [f32; 3],
+    /// Steepness of the waves, 0 for a flat surface.
+    pub wave_strength: f32,
+    /// How much the view wobbles while the camera is underwater.
+    pub underwater_distortion: f32,
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            color: [0.02, 0.08, 0.12],
+            absorption: [0.6, 0.2, 0.12],
+            wave_strength: 0.15,
+            underwater_distortion: 1.0,
+        }
+    }
+}
+
+/// How the HDR colors of the scene are mapped to the screen.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum Tonemapping {
+    /// Clips everything brighter than white.
+    None,
+    Reinhard,
+    /// A filmic curve with more contrast than `Reinhard`.
+    Aces,
+}
+
+/// A glow around the parts of the scene brighter than white.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// Brightness above which pixels start to glow.
+    pub threshold: f32,
+    /// How much of the glow is added to the scene.
+    pub intensity: f32,
+    /// How often the glow is blurred, more passes spread it further.
+    pub blur_passes: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            intensity: 0.2,
+            blur_passes: 2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostProcessingSettings {
+    /// Draws the scene into an HDR image first. Without it, the scene is
+    /// drawn straight to the screen and the other settings here have no
+    /// effect.
+    pub hdr: bool,
+    /// Scales the scene brightness before tonemapping.
+    pub exposure: f32,
+    pub tonemapping: Tonemapping,
+    pub bloom: BloomSettings,
+}
+
+impl Default for PostProcessingSettings {
+    fn default() -> Self {
+        Self {
+            hdr: true,
+            exposure: 1.0,
+            tonemapping: Tonemapping::Aces,
+            bloom: Default::default(),
+        }
+    }
+}
+
+/// How the jagged edges of blocks are smoothed. F4 switches between the
+/// modes the GPU supports while playing.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AntiAliasing {
+    None,
+    /// Draws the scene with 2, 4 or 8 samples per pixel. Only smooths the
+    /// edges of geometry, not those inside textures.
+    Msaa {
+        samples: u32,
+    },
+    /// Smooths every edge it finds in the finished image. Cheaper than
+    /// MSAA, but slightly blurs textures.
+    Fxaa,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct GraphicsSettings {
     pub fov: f32,
+    /// Blocks in every direction that are generated and drawn.
     pub render_distance: u32,
     pub window: WindowMode,
+    pub texture_backend: TextureBackend,
+    pub fog: FogSettings,
+    pub shadows: ShadowSettings,
+    pub ssao: SsaoSettings,
+    pub water: WaterSettings,
+    pub post_processing: PostProcessingSettings,
+    pub anti_aliasing: AntiAliasing,
 }
 
 impl Default for GraphicsSettings {
@@ -63,6 +302,68 @@ impl Default for GraphicsSettings {
             fov: 70.0,
             render_distance: 128,
             window: Default::default(),
+            texture_backend: Default::default(),
+            fog: Default::default(),
+            shadows: Default::default(),
+            ssao: Default::default(),
+            water: Default::default(),
+            post_processing: Default::default(),
+            anti_aliasing: AntiAliasing::Msaa { samples: 4 },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetSettings {
+    /// Directories or zip files laid out like the `assets` directory. Packs
+    /// listed first override the ones after them and the built-in assets.
+    pub resource_packs: Vec<PathBuf>,
+    /// Reload shaders, textures and block models when they change on disk.
+    pub hot_reload: bool,
+}
+
+impl Default for AssetSettings {
+    fn default() -> Self {
+        Self {
+            resource_packs: Vec::new(),
+            hot_reload: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldSettings {
+    /// Where the world is saved.
+    pub directory: PathBuf,
+    /// Seconds a whole day and night take.
+    pub day_length: u32,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("world"),
+            day_length: 1200,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenshotSettings {
+    pub directory: PathBuf,
+    /// High resolution screenshots are rendered in `tile_scale` x
+    /// `tile_scale` tiles of the window size.
+    pub tile_scale: u32,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("screenshots"),
+            tile_scale: 4,
         }
     }
 }
@@ -72,6 +373,9 @@ impl Default for GraphicsSettings {
 pub struct Settings {
     pub log: LogSettings,
     pub graphics: GraphicsSettings,
+    pub assets: AssetSettings,
+    pub screenshots: ScreenshotSettings,
+    pub world: WorldSettings,
 }
 
 impl Settings {
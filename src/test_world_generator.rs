@@ -5,6 +5,9 @@ use noise::{NoiseFn, Perlin};
 
 use crate::world::{Block, Chunk, GameData};
 
+/// Terrain below this height is flooded with water.
+const SEA_LEVEL: i16 = 62;
+
 pub struct ChunkGenerator {
     noise: Perlin,
 }
@@ -33,6 +36,21 @@ impl ChunkGenerator {
             as i16
     }
 
+    fn sample_plant(&self, position: IVec2) -> Option<&'static str> {
+        let value = self.noise.get([
+            position.x as f64 / 2.7,
+            position.y as f64 / 2.7,
+            0.5,
+        ]);
+        if value > 0.55 {
+            Some("poppy")
+        } else if value > 0.3 {
+            Some("short_grass")
+        } else {
+            None
+        }
+    }
+
     pub fn generate_chunk(
         &self,
         chunk_position: IVec2,
@@ -41,10 +59,12 @@ impl ChunkGenerator {
         let mut chunk = Chunk::new(chunk_position);
         for x in 0..16 {
             for z in 0..16 {
-                for y in -64..self.sample_height(glm::vec2(
+                let world_position = glm::vec2(
                     x + chunk_position.x * 16,
                     z + chunk_position.y * 16,
-                )) {
+                );
+                let height = self.sample_height(world_position);
+                for y in -64..height {
                     chunk.set_block(
                         glm::vec3(
                             x.rem_euclid(16) as i16,
@@ -52,6 +72,24 @@ impl ChunkGenerator {
                             z.rem_euclid(16) as i16,
                         ),
                         Block::new(game_data.block_id("dirt").unwrap()),
+                        game_data,
+                    )
+                }
+                for y in height..SEA_LEVEL {
+                    chunk.set_block(
+                        glm::vec3(x as i16, y, z as i16),
+                        Block::new(game_data.block_id("water").unwrap()),
+                        game_data,
+                    )
+                }
+                let plant = self
+                    .sample_plant(world_position)
+                    .filter(|_| height >= SEA_LEVEL);
+                if let Some(plant) = plant {
+                    chunk.set_block(
+                        glm::vec3(x as i16, height, z as i16),
+                        Block::new(game_data.block_id(plant).unwrap()),
+                        game_data,
                     )
                 }
             }
@@ -61,20 +99,74 @@ impl ChunkGenerator {
                 chunk.set_block(
                     glm::vec3(x, 100, 0),
                     Block::new(game_data.block_id("dirt").unwrap()),
+                    game_data,
                 )
             }
             for y in 100..108 {
                 chunk.set_block(
                     glm::vec3(0, y, 0),
                     Block::new(game_data.block_id("cobblestone").unwrap()),
+                    game_data,
                 )
             }
             for z in 0..8 {
                 chunk.set_block(
                     glm::vec3(0, 100, z),
                     Block::new(game_data.block_id("cobblestone").unwrap()),
+                    game_data,
                 )
             }
+            for y in 100..104 {
+                for x in 4..8 {
+                    let block = if x < 6 { "glass" } else { "ice" };
+                    chunk.set_block(
+                        glm::vec3(x, y, 4),
+                        Block::new(game_data.block_id(block).unwrap()),
+                        game_data,
+                    )
+                }
+            }
+            for y in 100..103 {
+                for z in 6..10 {
+                    chunk.set_block(
+                        glm::vec3(2, y, z),
+                        Block::new(game_data.block_id("bookshelf").unwrap()),
+                        game_data,
+                    )
+                }
+            }
+            chunk.set_block(
+                glm::vec3(2, 100, 2),
+                Block::new(game_data.block_id("cobblestone_slab").unwrap()),
+                game_data,
+            );
+            chunk.set_block(
+                glm::vec3(3, 100, 2),
+                Block::new(game_data.block_id("cobblestone_stairs").unwrap()),
+                game_data,
+            );
+            chunk.set_block(
+                glm::vec3(1, 101, 0),
+                Block::new(game_data.block_id("torch").unwrap()),
+                game_data,
+            );
+            // a pool two blocks deep on a stone floor.
+            for x in 3..9 {
+                for z in 6..10 {
+                    chunk.set_block(
+                        glm::vec3(x, 98, z),
+                        Block::new(game_data.block_id("stone").unwrap()),
+                        game_data,
+                    );
+                    for y in 99..101 {
+                        chunk.set_block(
+                            glm::vec3(x, y, z),
+                            Block::new(game_data.block_id("water").unwrap()),
+                            game_data,
+                        )
+                    }
+                }
+            }
         }
         chunk
     }
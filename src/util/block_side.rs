@@ -1,6 +1,6 @@
 use strum::EnumIter;
 
-#[derive(Clone, Copy, Debug, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
 pub enum BlockSide {
     Top,
     Bottom,
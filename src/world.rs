@@ -1,15 +1,23 @@
 mod block;
 mod block_type;
 mod chunk;
+mod clock;
 mod game_data;
+mod heightmap;
+mod level;
+mod light;
 
 use std::collections::HashMap;
 
 pub use block::Block;
 pub use block_type::BlockType;
 pub use chunk::Chunk;
+pub use clock::{WorldClock, TICK_DURATION};
 pub use game_data::GameData;
 use glm::{I16Vec3, IVec2, IVec3};
+pub use heightmap::{Heightmap, HeightmapType};
+pub use level::Level;
+pub use light::{LightChannel, LightLevels, MAX_LIGHT};
 
 pub struct World {
     chunks: HashMap<IVec2, Chunk>,
@@ -47,6 +55,28 @@ impl World {
             .map(|chunk| chunk.get_block(Self::get_chunk_position(position)))
     }
 
+    /// The height of the `kind` heightmap in the column at `x` and `z`,
+    /// `None` if its chunk isn't loaded.
+    pub fn get_height(
+        &self,
+        kind: HeightmapType,
+        x: i32,
+        z: i32,
+    ) -> Option<i16> {
+        self.chunks
+            .get(&glm::vec2(
+                x.div_euclid(Chunk::SIZE_X as i32),
+                z.div_euclid(Chunk::SIZE_Z as i32),
+            ))
+            .map(|chunk| {
+                chunk.height(
+                    kind,
+                    x.rem_euclid(Chunk::SIZE_X as i32) as i16,
+                    z.rem_euclid(Chunk::SIZE_Z as i32) as i16,
+                )
+            })
+    }
+
     pub fn position_in_world(position: IVec3) -> bool {
         Chunk::LOWEST_HEIGHT as i32 <= position.y
             && position.y
@@ -1,16 +1,52 @@
 #[derive(Debug)]
 pub struct BlockType {
     name: String,
+    opaque: bool,
+    motion_blocking: bool,
+    light_emission: u8,
 }
 
 impl BlockType {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_string(),
+            opaque: false,
+            motion_blocking: true,
+            light_emission: 0,
         }
     }
 
+    /// Makes the block stop light from passing through it.
+    pub fn opaque(mut self) -> Self {
+        self.opaque = true;
+        self
+    }
+
+    /// Lets entities walk through the block, like air and plants.
+    pub fn passable(mut self) -> Self {
+        self.motion_blocking = false;
+        self
+    }
+
+    /// Makes the block a light source of `level`, up to `MAX_LIGHT`.
+    pub fn with_light_emission(mut self, level: u8) -> Self {
+        self.light_emission = level.min(super::MAX_LIGHT);
+        self
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    pub fn is_motion_blocking(&self) -> bool {
+        self.motion_blocking
+    }
+
+    pub fn light_emission(&self) -> u8 {
+        self.light_emission
+    }
 }
@@ -1,9 +1,20 @@
-use super::Block;
+use super::{
+    Block,
+    GameData,
+    Heightmap,
+    HeightmapType,
+    LightChannel,
+    LightLevels,
+};
 
 pub struct Chunk {
     position: glm::IVec2,
     blocks: [[[Block; Self::SIZE_Z as usize]; Self::SIZE_X as usize];
         Self::SIZE_Y as usize],
+    block_light: LightLevels,
+    sky_light: LightLevels,
+    world_surface: Heightmap,
+    motion_blocking: Heightmap,
 }
 
 impl Chunk {
@@ -17,6 +28,10 @@ impl Chunk {
             blocks: [[[Block::air(); Self::SIZE_Z as usize];
                 Self::SIZE_X as usize];
                 Self::SIZE_Y as usize],
+            block_light: LightLevels::new(),
+            sky_light: LightLevels::new(),
+            world_surface: Heightmap::new(HeightmapType::WorldSurface),
+            motion_blocking: Heightmap::new(HeightmapType::MotionBlocking),
             position,
         }
     }
@@ -39,13 +54,59 @@ impl Chunk {
         position.y < 0 || position.y >= Self::SIZE_Y as i16
     }
 
-    pub fn set_block(&mut self, position: glm::I16Vec3, block: Block) {
+    pub fn set_block(
+        &mut self,
+        position: glm::I16Vec3,
+        block: Block,
+        game_data: &GameData,
+    ) {
         assert!(
             position.y >= Self::LOWEST_HEIGHT
                 && position.y < (Self::SIZE_Y as i16) + Self::LOWEST_HEIGHT
         );
         self.blocks[(position.y - Self::LOWEST_HEIGHT) as usize]
-            [position.z as usize][position.x as usize] = block
+            [position.z as usize][position.x as usize] = block;
+        let blocks = &self.blocks;
+        let get_block = |y: i16| {
+            blocks[(y - Self::LOWEST_HEIGHT) as usize][position.z as usize]
+                [position.x as usize]
+        };
+        self.world_surface
+            .update(position, block, game_data, get_block);
+        self.motion_blocking
+            .update(position, block, game_data, get_block);
+    }
+
+    /// One above the highest block of `kind` in the column at `x` and `z`,
+    /// `LOWEST_HEIGHT` if there is none.
+    pub fn height(&self, kind: HeightmapType, x: i16, z: i16) -> i16 {
+        match kind {
+            HeightmapType::WorldSurface => self.world_surface.get(x, z),
+            HeightmapType::MotionBlocking => self.motion_blocking.get(x, z),
+        }
+    }
+
+    pub fn get_light(
+        &self,
+        channel: LightChannel,
+        position: glm::I16Vec3,
+    ) -> u8 {
+        match channel {
+            LightChannel::Block => self.block_light.get(position),
+            LightChannel::Sky => self.sky_light.get(position),
+        }
+    }
+
+    pub fn set_light(
+        &mut self,
+        channel: LightChannel,
+        position: glm::I16Vec3,
+        level: u8,
+    ) {
+        match channel {
+            LightChannel::Block => self.block_light.set(position, level),
+            LightChannel::Sky => self.sky_light.set(position, level),
+        }
     }
 
     pub fn position(&self) -> glm::IVec2 {
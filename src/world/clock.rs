@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::settings::SETTINGS;
+
+/// Game ticks per second of real time.
+pub const TICKS_PER_SECOND: u64 = 20;
+pub const TICK_DURATION: Duration =
+    Duration::from_nanos(1_000_000_000 / TICKS_PER_SECOND);
+
+/// Counts the ticks since the world was created. It is advanced by the game
+/// loop and read by the renderer, so it can be shared without locking the
+/// world.
+pub struct WorldClock {
+    ticks: AtomicU64,
+}
+
+impl WorldClock {
+    pub fn new(ticks: u64) -> Self {
+        Self {
+            ticks: AtomicU64::new(ticks),
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    pub fn set_ticks(&self, ticks: u64) {
+        self.ticks.store(ticks, Ordering::Relaxed);
+    }
+
+    pub fn tick(&self) {
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Ticks in a whole day and night, see `WorldSettings::day_length`.
+    pub fn day_length() -> u64 {
+        (SETTINGS.world.day_length as u64 * TICKS_PER_SECOND).max(1)
+    }
+
+    /// How much of the day has passed, from 0 to 1. The sun rises at 0, is
+    /// highest at 0.25 and sets at 0.5.
+    pub fn time_of_day(&self) -> f32 {
+        let day_length = Self::day_length();
+        (self.ticks() % day_length) as f32 / day_length as f32
+    }
+}
@@ -29,4 +29,8 @@ impl GameData {
             .map(|x| *x)
             .wrap_err("Unknown block name!")
     }
+
+    pub fn block_type(&self, id: u16) -> &BlockType {
+        &self.block_types[id as usize]
+    }
 }
@@ -0,0 +1,131 @@
+use super::{Block, Chunk, GameData};
+
+/// Which blocks a `Heightmap` keeps track of.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HeightmapType {
+    /// Every block but air.
+    WorldSurface,
+    /// Blocks that can't be walked through, see `BlockType::passable`.
+    MotionBlocking,
+}
+
+impl HeightmapType {
+    fn includes(self, block: Block, game_data: &GameData) -> bool {
+        match self {
+            Self::WorldSurface => block.block_type() != 0,
+            Self::MotionBlocking => game_data
+                .block_type(block.block_type())
+                .is_motion_blocking(),
+        }
+    }
+}
+
+/// The height of the highest block of a `HeightmapType` in every column of
+/// a chunk.
+pub struct Heightmap {
+    kind: HeightmapType,
+    /// One above the highest block, `Chunk::LOWEST_HEIGHT` for columns
+    /// without one.
+    heights: [[i16; Chunk::SIZE_X as usize]; Chunk::SIZE_Z as usize],
+}
+
+impl Heightmap {
+    pub fn new(kind: HeightmapType) -> Self {
+        Self {
+            kind,
+            heights: [[Chunk::LOWEST_HEIGHT; Chunk::SIZE_X as usize];
+                Chunk::SIZE_Z as usize],
+        }
+    }
+
+    pub fn get(&self, x: i16, z: i16) -> i16 {
+        self.heights[z as usize][x as usize]
+    }
+
+    /// Updates the column of `position` after `block` was placed there.
+    /// `get_block` reads the other blocks of the column, in case the
+    /// highest one was removed.
+    pub fn update(
+        &mut self,
+        position: glm::I16Vec3,
+        block: Block,
+        game_data: &GameData,
+        get_block: impl Fn(i16) -> Block,
+    ) {
+        let kind = self.kind;
+        let height =
+            &mut self.heights[position.z as usize][position.x as usize];
+        if kind.includes(block, game_data) {
+            *height = (*height).max(position.y + 1);
+        } else if *height == position.y + 1 {
+            *height = (Chunk::LOWEST_HEIGHT..position.y)
+                .rev()
+                .find(|y| kind.includes(get_block(*y), game_data))
+                .map_or(Chunk::LOWEST_HEIGHT, |y| y + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::BlockType;
+
+    const STONE: u16 = 1;
+    const POPPY: u16 = 2;
+
+    fn game_data() -> GameData {
+        GameData::new(vec![
+            BlockType::new("air").passable(),
+            BlockType::new("stone").opaque(),
+            BlockType::new("poppy").passable(),
+        ])
+    }
+
+    fn heights(chunk: &Chunk, x: i16, z: i16) -> (i16, i16) {
+        (
+            chunk.height(HeightmapType::WorldSurface, x, z),
+            chunk.height(HeightmapType::MotionBlocking, x, z),
+        )
+    }
+
+    #[test]
+    fn empty_columns_are_at_the_bottom() {
+        let chunk = Chunk::new(glm::vec2(0, 0));
+        let bottom = Chunk::LOWEST_HEIGHT;
+        assert_eq!(heights(&chunk, 3, 5), (bottom, bottom));
+    }
+
+    #[test]
+    fn placing_blocks_raises_the_height() {
+        let game_data = game_data();
+        let mut chunk = Chunk::new(glm::vec2(0, 0));
+        chunk.set_block(glm::vec3(3, 10, 5), Block::new(STONE), &game_data);
+        assert_eq!(heights(&chunk, 3, 5), (11, 11));
+        // blocks below the highest one change nothing.
+        chunk.set_block(glm::vec3(3, 4, 5), Block::new(STONE), &game_data);
+        assert_eq!(heights(&chunk, 3, 5), (11, 11));
+        // plants can be walked through.
+        chunk.set_block(glm::vec3(3, 11, 5), Block::new(POPPY), &game_data);
+        assert_eq!(heights(&chunk, 3, 5), (12, 11));
+        // other columns stay empty.
+        let bottom = Chunk::LOWEST_HEIGHT;
+        assert_eq!(heights(&chunk, 4, 5), (bottom, bottom));
+    }
+
+    #[test]
+    fn removing_blocks_lowers_the_height() {
+        let game_data = game_data();
+        let mut chunk = Chunk::new(glm::vec2(0, 0));
+        chunk.set_block(glm::vec3(3, 4, 5), Block::new(STONE), &game_data);
+        chunk.set_block(glm::vec3(3, 10, 5), Block::new(STONE), &game_data);
+        chunk.set_block(glm::vec3(3, 11, 5), Block::new(POPPY), &game_data);
+        chunk.set_block(glm::vec3(3, 10, 5), Block::air(), &game_data);
+        assert_eq!(heights(&chunk, 3, 5), (12, 5));
+        chunk.set_block(glm::vec3(3, 11, 5), Block::air(), &game_data);
+        assert_eq!(heights(&chunk, 3, 5), (5, 5));
+        chunk.set_block(glm::vec3(3, 4, 5), Block::air(), &game_data);
+        let bottom = Chunk::LOWEST_HEIGHT;
+        assert_eq!(heights(&chunk, 3, 5), (bottom, bottom));
+    }
+}
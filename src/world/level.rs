@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::settings::SETTINGS;
+
+/// World state that is saved between sessions.
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Level {
+    /// The `WorldClock` ticks, a new world starts at sunrise.
+    pub ticks: u64,
+}
+
+impl Level {
+    fn path() -> PathBuf {
+        SETTINGS.world.directory.join("level.json")
+    }
+
+    /// Loads the saved level, or creates a new one if there is none yet.
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            log::info!("No saved level at {path:?}, creating a new one");
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failure reading {path:?}"))?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Failure parsing {path:?}"))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        std::fs::create_dir_all(&SETTINGS.world.directory)?;
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("Failure writing {path:?}"))?;
+        log::info!("Saved level to {path:?}");
+        Ok(())
+    }
+}
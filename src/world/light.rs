@@ -0,0 +1,599 @@
+use std::collections::{HashSet, VecDeque};
+
+use glm::{I16Vec3, IVec2, IVec3};
+use strum::IntoEnumIterator;
+
+use super::{Block, Chunk, GameData, HeightmapType, World};
+use crate::util::BlockSide;
+
+/// The brightest light level, light loses one level per block it travels.
+pub const MAX_LIGHT: u8 = 15;
+
+const BLOCKS_PER_CHUNK: usize =
+    Chunk::SIZE_X as usize * Chunk::SIZE_Y as usize * Chunk::SIZE_Z as usize;
+
+/// One above the highest block of the world.
+const TOP_HEIGHT: i16 = Chunk::LOWEST_HEIGHT + Chunk::SIZE_Y as i16;
+
+/// The kinds of light every block stores a level of.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightChannel {
+    /// Light from light emitting blocks.
+    Block,
+    /// Light from the sky, darkened with the time of day in the shader.
+    Sky,
+}
+
+impl LightChannel {
+    /// The light `level` turns into after moving one block to `side`.
+    fn spread(self, level: u8, side: BlockSide) -> u8 {
+        if self == Self::Sky && side == BlockSide::Bottom && level == MAX_LIGHT
+        {
+            // sunlight falls straight down without getting darker.
+            MAX_LIGHT
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+}
+
+/// A 4 bit light level for every block of a chunk, two to a byte.
+pub struct LightLevels {
+    levels: Box<[u8]>,
+}
+
+impl LightLevels {
+    pub fn new() -> Self {
+        Self {
+            levels: vec![0; BLOCKS_PER_CHUNK / 2].into_boxed_slice(),
+        }
+    }
+
+    fn index(position: I16Vec3) -> usize {
+        ((position.y - Chunk::LOWEST_HEIGHT) as usize * Chunk::SIZE_Z as usize
+            + position.z as usize)
+            * Chunk::SIZE_X as usize
+            + position.x as usize
+    }
+
+    pub fn get(&self, position: I16Vec3) -> u8 {
+        let index = Self::index(position);
+        (self.levels[index / 2] >> (index % 2 * 4)) & 0xf
+    }
+
+    pub fn set(&mut self, position: I16Vec3, level: u8) {
+        let index = Self::index(position);
+        let shift = index % 2 * 4;
+        let byte = &mut self.levels[index / 2];
+        *byte = (*byte & !(0xf << shift)) | ((level & 0xf) << shift);
+    }
+}
+
+fn chunk_containing(position: IVec3) -> IVec2 {
+    glm::vec2(
+        position.x.div_euclid(Chunk::SIZE_X as i32),
+        position.z.div_euclid(Chunk::SIZE_Z as i32),
+    )
+}
+
+/// A breadth first flood fill of one light channel through the loaded
+/// chunks.
+///
+/// Brighter light is spread from the positions in `increase`. Light that
+/// came from the positions in `decrease` is removed first, anything still
+/// lit at the edge of the removed area is spread again afterwards.
+struct LightUpdate<'a> {
+    world: &'a mut World,
+    game_data: &'a GameData,
+    channel: LightChannel,
+    increase: VecDeque<IVec3>,
+    decrease: VecDeque<(IVec3, u8)>,
+    changed_chunks: HashSet<IVec2>,
+}
+
+impl<'a> LightUpdate<'a> {
+    fn new(
+        world: &'a mut World,
+        game_data: &'a GameData,
+        channel: LightChannel,
+    ) -> Self {
+        Self {
+            world,
+            game_data,
+            channel,
+            increase: VecDeque::new(),
+            decrease: VecDeque::new(),
+            changed_chunks: HashSet::new(),
+        }
+    }
+
+    /// The chunk of a loaded position inside the world height.
+    fn chunk(&self, position: IVec3) -> Option<&Chunk> {
+        if !World::position_in_world(position) {
+            return None;
+        }
+        self.world.get_chunk(chunk_containing(position))
+    }
+
+    fn light(&self, position: IVec3) -> u8 {
+        self.world.get_light(self.channel, position)
+    }
+
+    fn set_light(&mut self, position: IVec3, level: u8) {
+        if !World::position_in_world(position) {
+            return;
+        }
+        let chunk_position = chunk_containing(position);
+        if let Some(chunk) = self.world.chunks.get_mut(&chunk_position) {
+            chunk.set_light(
+                self.channel,
+                World::get_chunk_position(position),
+                level,
+            );
+            // neighbors mesh their faces against this block, too.
+            self.changed_chunks.insert(chunk_position);
+            for side in BlockSide::cardinal_sides() {
+                let neighbor = chunk_containing(position + side.direction());
+                if neighbor != chunk_position {
+                    self.changed_chunks.insert(neighbor);
+                }
+            }
+        }
+    }
+
+    fn emission(&self, position: IVec3) -> u8 {
+        if self.channel == LightChannel::Sky {
+            return 0;
+        }
+        self.chunk(position).map_or(0, |chunk| {
+            let block = chunk.get_block(World::get_chunk_position(position));
+            self.game_data
+                .block_type(block.block_type())
+                .light_emission()
+        })
+    }
+
+    /// Whether light can enter the loaded block at `position`.
+    fn transparent(&self, position: IVec3) -> bool {
+        self.chunk(position).is_some_and(|chunk| {
+            let block = chunk.get_block(World::get_chunk_position(position));
+            !self.game_data.block_type(block.block_type()).is_opaque()
+        })
+    }
+
+    /// Fills the columns of a chunk with full skylight down to the first
+    /// opaque block, and queues the lit blocks next to darker columns.
+    fn fill_sky_columns(&mut self, chunk_position: IVec2) {
+        let game_data = self.game_data;
+        let Some(chunk) = self.world.chunks.get_mut(&chunk_position) else {
+            return;
+        };
+        let mut lowest_lit =
+            [[TOP_HEIGHT; Chunk::SIZE_X as usize]; Chunk::SIZE_Z as usize];
+        for z in 0..Chunk::SIZE_Z as i16 {
+            for x in 0..Chunk::SIZE_X as i16 {
+                // there is only air above the world surface.
+                let surface = chunk.height(HeightmapType::WorldSurface, x, z);
+                let mut y = TOP_HEIGHT - 1;
+                while y >= Chunk::LOWEST_HEIGHT {
+                    let position = glm::vec3(x, y, z);
+                    let block = chunk.get_block(position);
+                    if y < surface
+                        && game_data.block_type(block.block_type()).is_opaque()
+                    {
+                        break;
+                    }
+                    chunk.set_light(LightChannel::Sky, position, MAX_LIGHT);
+                    y -= 1;
+                }
+                lowest_lit[z as usize][x as usize] = y + 1;
+            }
+        }
+        for z in 0..Chunk::SIZE_Z as i16 {
+            for x in 0..Chunk::SIZE_X as i16 {
+                for side in BlockSide::cardinal_sides() {
+                    let neighbor = glm::vec2(x, z) + side.flat_direction();
+                    if !(0..Chunk::SIZE_X as i16).contains(&neighbor.x)
+                        || !(0..Chunk::SIZE_Z as i16).contains(&neighbor.y)
+                    {
+                        continue;
+                    }
+                    // the neighbor column is dark below its lowest lit
+                    // block, light from this one spreads into it.
+                    let start = lowest_lit[z as usize][x as usize];
+                    let end =
+                        lowest_lit[neighbor.y as usize][neighbor.x as usize];
+                    for y in start..end {
+                        self.increase.push_back(World::get_world_position(
+                            glm::vec3(x, y, z),
+                            chunk_position,
+                        ));
+                    }
+                }
+            }
+        }
+        self.changed_chunks.insert(chunk_position);
+        for direction in BlockSide::cardinal_directions::<i32>() {
+            self.changed_chunks.insert(chunk_position + direction);
+        }
+    }
+
+    /// Queues the light crossing between a chunk and its loaded neighbors,
+    /// from whichever side of the border is brighter.
+    fn seed_borders(&mut self, chunk_position: IVec2) {
+        let channel = self.channel;
+        let game_data = self.game_data;
+        let transparent = |chunk: &Chunk, position| {
+            let block: Block = chunk.get_block(position);
+            !game_data.block_type(block.block_type()).is_opaque()
+        };
+        for side in BlockSide::cardinal_sides() {
+            let neighbor_position = chunk_position + side.flat_direction();
+            let (Some(chunk), Some(neighbor)) = (
+                self.world.get_chunk(chunk_position),
+                self.world.get_chunk(neighbor_position),
+            ) else {
+                continue;
+            };
+            for z in 0..Chunk::SIZE_Z as i16 {
+                for x in 0..Chunk::SIZE_X as i16 {
+                    let across = glm::vec2(x, z) + side.flat_direction();
+                    if (0..Chunk::SIZE_X as i16).contains(&across.x)
+                        && (0..Chunk::SIZE_Z as i16).contains(&across.y)
+                    {
+                        continue;
+                    }
+                    let across = glm::vec2(
+                        across.x.rem_euclid(Chunk::SIZE_X as i16),
+                        across.y.rem_euclid(Chunk::SIZE_Z as i16),
+                    );
+                    for y in Chunk::LOWEST_HEIGHT..TOP_HEIGHT {
+                        let position = glm::vec3(x, y, z);
+                        let other = glm::vec3(across.x, y, across.y);
+                        let level = chunk.get_light(channel, position);
+                        let other_level = neighbor.get_light(channel, other);
+                        if level > other_level + 1
+                            && transparent(neighbor, other)
+                        {
+                            self.increase.push_back(
+                                World::get_world_position(
+                                    position,
+                                    chunk_position,
+                                ),
+                            );
+                        } else if other_level > level + 1
+                            && transparent(chunk, position)
+                        {
+                            self.increase.push_back(
+                                World::get_world_position(
+                                    other,
+                                    neighbor_position,
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn propagate_decrease(&mut self) {
+        while let Some((position, level)) = self.decrease.pop_front() {
+            for side in BlockSide::iter() {
+                let neighbor = position + side.direction::<i32>();
+                let neighbor_level = self.light(neighbor);
+                if neighbor_level == 0 {
+                    continue;
+                }
+                if neighbor_level <= self.channel.spread(level, side) {
+                    // lit by the removed light, unless it shines itself.
+                    let emission = self.emission(neighbor);
+                    self.set_light(neighbor, emission);
+                    self.decrease.push_back((neighbor, neighbor_level));
+                    if emission > 0 {
+                        self.increase.push_back(neighbor);
+                    }
+                } else {
+                    // lit from somewhere else, fills the dark area again.
+                    self.increase.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    fn propagate_increase(&mut self) {
+        while let Some(position) = self.increase.pop_front() {
+            let level = self.light(position);
+            for side in BlockSide::iter() {
+                let neighbor = position + side.direction::<i32>();
+                let spread = self.channel.spread(level, side);
+                if self.light(neighbor) < spread && self.transparent(neighbor)
+                {
+                    self.set_light(neighbor, spread);
+                    self.increase.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    fn run(mut self) -> HashSet<IVec2> {
+        self.propagate_decrease();
+        self.propagate_increase();
+        self.changed_chunks
+    }
+}
+
+impl World {
+    /// The light level of `channel` at `position`. Skylight is full above
+    /// the world, anything else outside of the loaded chunks is dark.
+    pub fn get_light(&self, channel: LightChannel, position: IVec3) -> u8 {
+        if channel == LightChannel::Sky && position.y >= TOP_HEIGHT as i32 {
+            return MAX_LIGHT;
+        }
+        if !Self::position_in_world(position) {
+            return 0;
+        }
+        self.get_chunk(chunk_containing(position))
+            .map_or(0, |chunk| {
+                chunk.get_light(channel, Self::get_chunk_position(position))
+            })
+    }
+
+    /// Lights a newly loaded chunk from the sky and its light sources, and
+    /// spreads the light of its loaded neighbors into it.
+    ///
+    /// Returns the chunks whose light changed and need to be remeshed.
+    pub fn light_chunk(
+        &mut self,
+        chunk_position: IVec2,
+        game_data: &GameData,
+    ) -> HashSet<IVec2> {
+        let Some(chunk) = self.get_chunk(chunk_position) else {
+            return HashSet::new();
+        };
+        let mut sources = Vec::new();
+        for y in Chunk::LOWEST_HEIGHT..TOP_HEIGHT {
+            for z in 0..Chunk::SIZE_Z as i16 {
+                for x in 0..Chunk::SIZE_X as i16 {
+                    let chunk_block_position = glm::vec3(x, y, z);
+                    let block = chunk.get_block(chunk_block_position);
+                    let emission = game_data
+                        .block_type(block.block_type())
+                        .light_emission();
+                    if emission > 0 {
+                        sources.push((
+                            World::get_world_position(
+                                chunk_block_position,
+                                chunk_position,
+                            ),
+                            emission,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut update =
+            LightUpdate::new(self, game_data, LightChannel::Block);
+        for (position, emission) in sources {
+            update.set_light(position, emission);
+            update.increase.push_back(position);
+        }
+        update.seed_borders(chunk_position);
+        let mut changed_chunks = update.run();
+
+        let mut update = LightUpdate::new(self, game_data, LightChannel::Sky);
+        update.fill_sky_columns(chunk_position);
+        update.seed_borders(chunk_position);
+        changed_chunks.extend(update.run());
+        changed_chunks
+    }
+
+    /// Replaces a block in a loaded chunk and updates the light around it.
+    ///
+    /// Returns the chunks whose blocks or light changed and need to be
+    /// remeshed, callers send a `ChunkEvent::Update` for each of them.
+    /// Nothing in the game changes blocks yet, this is the entry point for
+    /// block edits and is only called by the tests so far.
+    #[allow(dead_code)]
+    pub fn set_block(
+        &mut self,
+        position: IVec3,
+        block: Block,
+        game_data: &GameData,
+    ) -> HashSet<IVec2> {
+        if !Self::position_in_world(position) {
+            return HashSet::new();
+        }
+        let chunk_position = chunk_containing(position);
+        let Some(chunk) = self.chunks.get_mut(&chunk_position) else {
+            return HashSet::new();
+        };
+        chunk.set_block(Self::get_chunk_position(position), block, game_data);
+
+        let mut changed_chunks = HashSet::from([chunk_position]);
+        for side in BlockSide::cardinal_sides() {
+            changed_chunks
+                .insert(chunk_containing(position + side.direction()));
+        }
+        for channel in [LightChannel::Block, LightChannel::Sky] {
+            let mut update = LightUpdate::new(self, game_data, channel);
+            let level = update.light(position);
+            // removes the old light here, neighbors that are brighter than
+            // what is left then spread into the new block.
+            update.set_light(position, 0);
+            update.decrease.push_back((position, level));
+            let emission = update.emission(position);
+            if emission > 0 {
+                update.set_light(position, emission);
+                update.increase.push_back(position);
+            }
+            changed_chunks.extend(update.run());
+        }
+        changed_chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::BlockType;
+
+    const STONE: u16 = 1;
+    const TORCH: u16 = 2;
+
+    fn game_data() -> GameData {
+        GameData::new(vec![
+            BlockType::new("air").passable(),
+            BlockType::new("stone").opaque(),
+            BlockType::new("torch").passable().with_light_emission(14),
+        ])
+    }
+
+    fn loaded_world(game_data: &GameData, chunks: &[IVec2]) -> World {
+        let mut world = World::new();
+        for position in chunks {
+            world.set_chunk(Chunk::new(*position));
+            world.light_chunk(*position, game_data);
+        }
+        world
+    }
+
+    fn block_light(world: &World, x: i32, y: i32, z: i32) -> u8 {
+        world.get_light(LightChannel::Block, glm::vec3(x, y, z))
+    }
+
+    #[test]
+    fn placing_and_removing_a_torch() {
+        let game_data = game_data();
+        let mut world = loaded_world(&game_data, &[glm::vec2(0, 0)]);
+        let torch = glm::vec3(8, 10, 8);
+        let changed = world.set_block(torch, Block::new(TORCH), &game_data);
+        assert!(changed.contains(&glm::vec2(0, 0)));
+        assert_eq!(block_light(&world, 8, 10, 8), 14);
+        assert_eq!(block_light(&world, 9, 10, 8), 13);
+        assert_eq!(block_light(&world, 8, 7, 8), 11);
+        assert_eq!(block_light(&world, 10, 11, 6), 9);
+
+        world.set_block(torch, Block::air(), &game_data);
+        for (x, y, z) in [(8, 10, 8), (9, 10, 8), (8, 7, 8), (10, 11, 6)] {
+            assert_eq!(block_light(&world, x, y, z), 0);
+        }
+    }
+
+    #[test]
+    fn opaque_block_in_front_of_a_torch() {
+        let game_data = game_data();
+        let mut world = loaded_world(&game_data, &[glm::vec2(0, 0)]);
+        world.set_block(glm::vec3(8, 10, 8), Block::new(TORCH), &game_data);
+        let wall = glm::vec3(9, 10, 8);
+        world.set_block(wall, Block::new(STONE), &game_data);
+        assert_eq!(block_light(&world, 9, 10, 8), 0);
+        // the light goes around the wall instead of through it.
+        assert_eq!(block_light(&world, 10, 10, 8), 10);
+
+        world.set_block(wall, Block::air(), &game_data);
+        assert_eq!(block_light(&world, 9, 10, 8), 13);
+        assert_eq!(block_light(&world, 10, 10, 8), 12);
+    }
+
+    #[test]
+    fn torch_light_crosses_chunk_borders() {
+        let game_data = game_data();
+        let mut world =
+            loaded_world(&game_data, &[glm::vec2(0, 0), glm::vec2(1, 0)]);
+        let torch = glm::vec3(14, 10, 8);
+        let changed = world.set_block(torch, Block::new(TORCH), &game_data);
+        assert!(changed.contains(&glm::vec2(1, 0)));
+        assert_eq!(block_light(&world, 17, 10, 8), 11);
+
+        let wall = glm::vec3(15, 10, 8);
+        let changed = world.set_block(wall, Block::new(STONE), &game_data);
+        assert!(changed.contains(&glm::vec2(1, 0)));
+        assert_eq!(block_light(&world, 16, 10, 8), 10);
+        assert_eq!(block_light(&world, 17, 10, 8), 9);
+
+        world.set_block(torch, Block::air(), &game_data);
+        assert_eq!(block_light(&world, 16, 10, 8), 0);
+        assert_eq!(block_light(&world, 17, 10, 8), 0);
+    }
+
+    #[test]
+    fn loading_a_chunk_next_to_a_torch() {
+        let game_data = game_data();
+        let mut world = loaded_world(&game_data, &[glm::vec2(0, 0)]);
+        world.set_block(glm::vec3(15, 10, 8), Block::new(TORCH), &game_data);
+        world.set_chunk(Chunk::new(glm::vec2(1, 0)));
+        let changed = world.light_chunk(glm::vec2(1, 0), &game_data);
+        assert!(changed.contains(&glm::vec2(1, 0)));
+        assert_eq!(block_light(&world, 16, 10, 8), 13);
+        assert_eq!(block_light(&world, 20, 10, 8), 9);
+    }
+
+    fn sky_light(world: &World, x: i32, y: i32, z: i32) -> u8 {
+        world.get_light(LightChannel::Sky, glm::vec3(x, y, z))
+    }
+
+    #[test]
+    fn skylight_under_an_overhang() {
+        let game_data = game_data();
+        let mut chunk = Chunk::new(glm::vec2(0, 0));
+        // a roof over x 0..8 at y 20, open to the sky east of it.
+        for z in 0..Chunk::SIZE_Z as i16 {
+            for x in 0..8 {
+                chunk.set_block(
+                    glm::vec3(x, 20, z),
+                    Block::new(STONE),
+                    &game_data,
+                );
+            }
+        }
+        let mut world = World::new();
+        world.set_chunk(chunk);
+        world.light_chunk(glm::vec2(0, 0), &game_data);
+        assert_eq!(sky_light(&world, 8, 10, 8), MAX_LIGHT);
+        assert_eq!(sky_light(&world, 4, 21, 8), MAX_LIGHT);
+        assert_eq!(sky_light(&world, 4, 20, 8), 0);
+        // the light spreads sideways under the roof, getting darker.
+        assert_eq!(sky_light(&world, 7, 10, 8), MAX_LIGHT - 1);
+        assert_eq!(sky_light(&world, 4, 10, 8), MAX_LIGHT - 4);
+        assert_eq!(sky_light(&world, 0, 19, 8), MAX_LIGHT - 8);
+    }
+
+    #[test]
+    fn skylight_after_removing_the_top_block() {
+        let game_data = game_data();
+        let mut world = loaded_world(&game_data, &[glm::vec2(0, 0)]);
+        // a closed box around x 4..7, y 10..13, z 4..7.
+        for x in 3..8 {
+            for y in 9..14 {
+                for z in 3..8 {
+                    let inside = (4..7).contains(&x)
+                        && (10..13).contains(&y)
+                        && (4..7).contains(&z);
+                    if !inside {
+                        world.set_block(
+                            glm::vec3(x, y, z),
+                            Block::new(STONE),
+                            &game_data,
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(sky_light(&world, 5, 11, 5), 0);
+        assert_eq!(sky_light(&world, 5, 8, 5), MAX_LIGHT - 3);
+
+        // sunlight falls through the opening all the way down.
+        let changed =
+            world.set_block(glm::vec3(5, 13, 5), Block::air(), &game_data);
+        assert!(changed.contains(&glm::vec2(0, 0)));
+        assert_eq!(sky_light(&world, 5, 13, 5), MAX_LIGHT);
+        assert_eq!(sky_light(&world, 5, 10, 5), MAX_LIGHT);
+        assert_eq!(sky_light(&world, 4, 10, 4), MAX_LIGHT - 2);
+
+        world.set_block(glm::vec3(5, 13, 5), Block::new(STONE), &game_data);
+        assert_eq!(sky_light(&world, 5, 10, 5), 0);
+        assert_eq!(sky_light(&world, 4, 10, 4), 0);
+    }
+}